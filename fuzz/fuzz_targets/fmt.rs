@@ -0,0 +1,15 @@
+#![no_main]
+
+use js_light::formatter::{self, Options};
+use js_light::parser;
+use libfuzzer_sys::fuzz_target;
+
+// formatting keeps the program, and formatting formatted code changes nothing
+fuzz_target!(|src: &str| {
+    let options = Options::default();
+    if let (Ok(prg), Ok(formatted)) = (parser::parse_prg(src), formatter::format(src, &options)) {
+        let reparsed = parser::parse_prg(&formatted).unwrap();
+        assert_eq!(reparsed.to_string(), prg.to_string());
+        assert_eq!(formatter::format(&formatted, &options).unwrap(), formatted);
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use js_light::parser;
+use js_light::runner::{parse_json, parse_json5, DuplicateKeys};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|src: &str| {
+    let _ = parser::parse_literal(src);
+    // duplicate keys are the only thing the policy changes, and json is json5 too
+    if parse_json(src, DuplicateKeys::Error).is_ok() {
+        assert!(parse_json(src, DuplicateKeys::LastWins).is_ok());
+        assert!(parse_json5(src, DuplicateKeys::Error).is_ok());
+    }
+    let _ = parse_json5(src, DuplicateKeys::LastWins);
+});
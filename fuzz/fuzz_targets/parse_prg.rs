@@ -0,0 +1,20 @@
+#![no_main]
+
+use js_light::parser;
+use libfuzzer_sys::fuzz_target;
+
+// any source is either a program or a syntax error, and a program can be printed back and parsed
+// again. the syntax tree gives back the source, and the same statements
+fuzz_target!(|src: &str| {
+    if let Ok(prg) = parser::parse_prg(src) {
+        let printed = prg.to_string();
+        assert_eq!(parser::parse_prg(&printed).unwrap().to_string(), printed);
+    }
+    let tree = parser::parse_cst(src);
+    assert_eq!(tree.to_string(), src);
+    let (prg, _) = parser::parse_prg_partial(src);
+    assert_eq!(
+        format!("{:?}", tree.to_prg().unwrap()),
+        format!("{:?}", prg)
+    );
+});
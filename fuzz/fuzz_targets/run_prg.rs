@@ -0,0 +1,17 @@
+#![no_main]
+
+use js_light::{parser, runner};
+use libfuzzer_sys::fuzz_target;
+
+// enough for the programs the fuzzer comes up with, while stopping the infinite loops quickly
+const STEP_LIMIT: u64 = 10_000;
+
+// a program either runs or fails with an error. fuzz/seeds/run_prg has programs which once
+// panicked, to start from: `cargo fuzz run run_prg fuzz/corpus/run_prg fuzz/seeds/run_prg`
+fuzz_target!(|src: &str| {
+    if let Ok(prg) = parser::parse_prg(src) {
+        let mut runner = runner::Runner::new();
+        runner.step_limit = Some(STEP_LIMIT);
+        let _ = runner.run_prg(&prg);
+    }
+});
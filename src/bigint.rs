@@ -0,0 +1,390 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// Arbitrary precision integers, for `123n` literals and json integers which don't fit in an i64.
+// Only what the runner needs: parsing, printing, arithmetic and comparisons.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    // never set for zero
+    negative: bool,
+    // base 2^32 digits, least significant first, without leading zeros: zero has none
+    mag: Vec<u32>,
+}
+
+// like javascript engines, the size is limited: an operation giving a larger result fails
+pub const MAX_BITS: u64 = 1 << 20;
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt {
+            negative: false,
+            mag: vec![],
+        }
+    }
+
+    pub fn from_i64(n: i64) -> Self {
+        let mut res = BigInt::from_u64(n.unsigned_abs());
+        res.negative = n < 0;
+        res
+    }
+
+    pub fn from_u64(n: u64) -> Self {
+        BigInt {
+            negative: false,
+            mag: trim(vec![n as u32, (n >> 32) as u32]),
+        }
+    }
+
+    fn new(negative: bool, mag: Vec<u32>) -> Self {
+        let mag = trim(mag);
+        BigInt {
+            negative: negative && !mag.is_empty(),
+            mag,
+        }
+    }
+
+    // digits in the radix, after an optional sign. no prefix, like `0x`, and no separators.
+    // None if it isn't a number, or is too large
+    pub fn parse(s: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return None;
+        }
+        let digits = digits.trim_start_matches('0');
+        if digits.len() as f64 * (radix as f64).log2() > (MAX_BITS + 32) as f64 {
+            return None;
+        }
+        // as many digits at once as fit in a u32
+        let mut chunk_len = 1;
+        while (radix as u64).pow(chunk_len + 1) <= u32::MAX as u64 {
+            chunk_len += 1;
+        }
+        let mut mag = vec![];
+        for chunk in digits.as_bytes().chunks(chunk_len as usize) {
+            let chunk = std::str::from_utf8(chunk).ok()?;
+            let factor = radix.pow(chunk.len() as u32);
+            mul_add_small(&mut mag, factor, u32::from_str_radix(chunk, radix).ok()?);
+        }
+        let res = BigInt::new(negative, mag);
+        (res.bits() <= MAX_BITS).then_some(res)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.mag.first().is_some_and(|d| d % 2 == 1)
+    }
+
+    pub fn bits(&self) -> u64 {
+        match self.mag.last() {
+            Some(top) => self.mag.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.mag.len() > 2 {
+            return None;
+        }
+        let abs = self
+            .mag
+            .iter()
+            .rev()
+            .fold(0u64, |n, &d| (n << 32) | d as u64);
+        match self.negative {
+            true => 0i64.checked_sub_unsigned(abs),
+            false => i64::try_from(abs).ok(),
+        }
+    }
+
+    // the closest f64, Infinity when it is too large
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    pub fn neg(&self) -> Self {
+        BigInt::new(!self.negative, self.mag.clone())
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_mag(&self.mag, &other.mag));
+        }
+        // opposite signs: the larger magnitude gives its sign
+        match cmp_mag(&self.mag, &other.mag) {
+            Ordering::Less => BigInt::new(other.negative, sub_mag(&other.mag, &self.mag)),
+            _ => BigInt::new(self.negative, sub_mag(&self.mag, &other.mag)),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    // None when the result is too large
+    pub fn mul(&self, other: &Self) -> Option<Self> {
+        if self.bits() + other.bits() > MAX_BITS + 1 {
+            return None;
+        }
+        let res = BigInt::new(
+            self.negative != other.negative,
+            mul_mag(&self.mag, &other.mag),
+        );
+        (res.bits() <= MAX_BITS).then_some(res)
+    }
+
+    // truncated, like javascript: the remainder has the sign of the dividend.
+    // None for a division by zero
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_mag(&self.mag, &other.mag);
+        Some((
+            BigInt::new(self.negative != other.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+
+    // None when the result is too large
+    pub fn pow(&self, exp: u64) -> Option<Self> {
+        if self.bits() <= 1 {
+            // 0, 1 and -1: no need to multiply
+            let negative = self.negative && exp % 2 == 1;
+            let mag = if exp == 0 { vec![1] } else { self.mag.clone() };
+            return Some(BigInt::new(negative, mag));
+        }
+        if exp > MAX_BITS {
+            return None;
+        }
+        let mut res = BigInt::from_i64(1);
+        let mut base = self.clone();
+        let mut exp = exp;
+        while exp > 0 {
+            if exp % 2 == 1 {
+                res = res.mul(&base)?;
+            }
+            exp /= 2;
+            if exp > 0 {
+                base = base.mul(&base)?;
+            }
+        }
+        Some(res)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// in base 10, without the `n` suffix
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // chunks of 9 digits, least significant first
+        let mut chunks = vec![];
+        let mut mag = self.mag.clone();
+        while !mag.is_empty() {
+            let (quotient, remainder) = div_rem_small(&mag, 1_000_000_000);
+            chunks.push(remainder);
+            mag = quotient;
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn trim(mut mag: Vec<u32>) -> Vec<u32> {
+    while mag.last() == Some(&0) {
+        mag.pop();
+    }
+    mag
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+// mag = mag * factor + digit
+fn mul_add_small(mag: &mut Vec<u32>, factor: u32, digit: u32) {
+    let mut carry = digit as u64;
+    for d in mag.iter_mut() {
+        let n = *d as u64 * factor as u64 + carry;
+        *d = n as u32;
+        carry = n >> 32;
+    }
+    if carry > 0 {
+        mag.push(carry as u32);
+    }
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut res = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u64;
+    for (idx, &d) in a.iter().enumerate() {
+        let n = d as u64 + *b.get(idx).unwrap_or(&0) as u64 + carry;
+        res.push(n as u32);
+        carry = n >> 32;
+    }
+    res.push(carry as u32);
+    trim(res)
+}
+
+// a - b, with a >= b
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (idx, &d) in a.iter().enumerate() {
+        let n = d as i64 - *b.get(idx).unwrap_or(&0) as i64 - borrow;
+        res.push(n as u32);
+        borrow = (n < 0) as i64;
+    }
+    trim(res)
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let n = x as u64 * y as u64 + res[i + j] as u64 + carry;
+            res[i + j] = n as u32;
+            carry = n >> 32;
+        }
+        res[i + b.len()] = carry as u32;
+    }
+    trim(res)
+}
+
+fn div_rem_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+    for idx in (0..a.len()).rev() {
+        let n = (remainder << 32) | a[idx] as u64;
+        quotient[idx] = (n / d as u64) as u32;
+        remainder = n % d as u64;
+    }
+    (trim(quotient), remainder as u32)
+}
+
+// long division (knuth's algorithm D). `b` is not zero
+fn div_rem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_mag(a, b) == Ordering::Less {
+        return (vec![], a.to_vec());
+    }
+    if b.len() == 1 {
+        let (quotient, remainder) = div_rem_small(a, b[0]);
+        return (quotient, trim(vec![remainder]));
+    }
+    // normalize, so that the top digit of the divisor has its high bit set
+    let shift = b.last().unwrap().leading_zeros();
+    let v = shl(b, shift);
+    let mut u = shl(a, shift);
+    u.resize(a.len() + 1, 0);
+    let n = v.len();
+    let m = a.len() - n;
+    let mut quotient = vec![0u32; m + 1];
+    for j in (0..=m).rev() {
+        // estimate the digit from the top two digits, it is at most 2 too large
+        let top = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+        let mut qhat = top / v[n - 1] as u64;
+        let mut rhat = top % v[n - 1] as u64;
+        while qhat > u32::MAX as u64
+            || qhat * v[n - 2] as u64 > ((rhat << 32) | u[j + n - 2] as u64)
+        {
+            qhat -= 1;
+            rhat += v[n - 1] as u64;
+            if rhat > u32::MAX as u64 {
+                break;
+            }
+        }
+        // u -= qhat * v, at position j
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let p = qhat * v[i] as u64 + carry;
+            carry = p >> 32;
+            let t = u[i + j] as i64 - borrow - (p & 0xFFFF_FFFF) as i64;
+            u[i + j] = t as u32;
+            borrow = (t < 0) as i64;
+        }
+        let t = u[j + n] as i64 - borrow - carry as i64;
+        u[j + n] = t as u32;
+        // one too many: add v back
+        if t < 0 {
+            qhat -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let s = u[i + j] as u64 + v[i] as u64 + carry;
+                u[i + j] = s as u32;
+                carry = s >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+        quotient[j] = qhat as u32;
+    }
+    u.truncate(n);
+    (trim(quotient), trim(shr(&u, shift)))
+}
+
+fn shl(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut res = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u32;
+    for &d in a {
+        res.push((d << shift) | carry);
+        carry = d >> (32 - shift);
+    }
+    if carry > 0 {
+        res.push(carry);
+    }
+    res
+}
+
+fn shr(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut res = vec![0u32; a.len()];
+    for idx in 0..a.len() {
+        let high = a.get(idx + 1).map_or(0, |d| d << (32 - shift));
+        res[idx] = (a[idx] >> shift) | high;
+    }
+    res
+}
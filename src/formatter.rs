@@ -0,0 +1,740 @@
+// an opinionated formatter, working on the lossless syntax tree: the layout of the source is thrown
+// away, its comments are kept. statements go on lines of their own, and lists (object and array
+// literals, params, call arguments) stay on one line when they fit in the line width, else get one
+// item per line. formatting formatted code changes nothing
+use crate::parser::cst::{NodeKind, SyntaxElement, SyntaxNode, SyntaxTree};
+use crate::parser::lexer::{Token, TokenKind};
+use crate::parser::{self, Result};
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    // lines are wrapped past this many chars, where they can be
+    pub line_width: usize,
+    // the spaces of one level of indentation
+    pub indent: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            line_width: 80,
+            indent: 2,
+        }
+    }
+}
+
+// the formatted source, or its first syntax error: a program with errors is not formatted
+pub fn format(src: &str, options: &Options) -> Result<String> {
+    let mut tree = parser::parse_cst(src);
+    if !tree.errors.is_empty() {
+        return Err(tree.errors.remove(0));
+    }
+    Ok(format_tree(&tree, options))
+}
+
+// the tree shouldn't have errors: the tokens of an error node are printed as they are
+pub fn format_tree(tree: &SyntaxTree, options: &Options) -> String {
+    let formatter = Formatter { src: tree.src };
+    let mut printer = Printer {
+        options,
+        out: String::new(),
+        col: 0,
+        suffixes: vec![],
+    };
+    printer.print(&formatter.prg(&tree.root));
+    printer.out
+}
+
+// the layout of the code, before it is printed at a given width
+enum Doc<'a> {
+    Text(&'a str),
+    // a space, or a line break when its group is broken
+    Line,
+    // nothing, or a line break when its group is broken
+    SoftLine,
+    // always a line break
+    HardLine,
+    // a `//` comment, printed at the end of the line: the next text goes on the next line
+    LineSuffix(&'a str),
+    // breaks the group it is in
+    BreakParent,
+    // indented when its group is broken
+    Indent(Vec<Doc<'a>>),
+    // printed on one line if it fits, else broken: the flag is set when it can't be on one line
+    Group(Vec<Doc<'a>>, bool),
+}
+
+fn group(docs: Vec<Doc>) -> Doc {
+    let broken = docs.iter().any(breaks_group);
+    Doc::Group(docs, broken)
+}
+
+// a line break which has to stay, outside of the nested groups
+fn breaks_group(doc: &Doc) -> bool {
+    match doc {
+        Doc::HardLine | Doc::BreakParent => true,
+        Doc::Indent(docs) => docs.iter().any(breaks_group),
+        _ => false,
+    }
+}
+
+struct Printer<'o, 'a> {
+    options: &'o Options,
+    out: String,
+    // the chars on the current line
+    col: usize,
+    // the comments for the end of the current line
+    suffixes: Vec<&'a str>,
+}
+
+impl<'a> Printer<'_, 'a> {
+    fn print(&mut self, doc: &Doc<'a>) {
+        // the docs left to print, the next one last, with their indentation and whether their
+        // group is on one line
+        let mut cmds = vec![(0, false, doc)];
+        while let Some((indent, flat, doc)) = cmds.pop() {
+            match doc {
+                Doc::Text(text) => self.text(text, indent),
+                Doc::Line if flat => self.text(" ", indent),
+                Doc::SoftLine if flat => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine => self.newline(indent),
+                Doc::LineSuffix(comment) => self.suffixes.push(comment),
+                Doc::BreakParent => {}
+                Doc::Indent(docs) => {
+                    let indent = if flat {
+                        indent
+                    } else {
+                        indent + self.options.indent
+                    };
+                    cmds.extend(docs.iter().rev().map(|doc| (indent, flat, doc)));
+                }
+                Doc::Group(docs, broken) => {
+                    let flat = !broken && (flat || self.fits(docs, &cmds));
+                    cmds.extend(docs.iter().rev().map(|doc| (indent, flat, doc)));
+                }
+            }
+        }
+        self.end_line();
+    }
+
+    fn text(&mut self, text: &str, indent: usize) {
+        // nothing goes after a `//` comment on its line
+        if self
+            .suffixes
+            .iter()
+            .any(|comment| comment.starts_with("//"))
+        {
+            if text.trim().is_empty() {
+                return;
+            }
+            self.newline(indent);
+        }
+        self.out.push_str(text);
+        match text.rfind('\n') {
+            Some(idx) => self.col = text[idx + 1..].chars().count(),
+            None => self.col += text.chars().count(),
+        }
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.end_line();
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(indent));
+        self.col = indent;
+    }
+
+    // the comments left for the end of the line, and no trailing spaces. a `//` comment ends the
+    // line, so the ones after the first go on lines of their own, indented like the current one
+    fn end_line(&mut self) {
+        let line = &self.out[self.out.rfind('\n').map_or(0, |idx| idx + 1)..];
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let mut after_line_comment = false;
+        for comment in std::mem::take(&mut self.suffixes) {
+            self.trim_end();
+            if after_line_comment {
+                self.out.push('\n');
+                self.out.push_str(&" ".repeat(indent));
+            } else if !self.out.is_empty() && !self.out.ends_with('\n') {
+                self.out.push(' ');
+            }
+            self.out.push_str(comment);
+            after_line_comment = comment.starts_with("//");
+        }
+        self.trim_end();
+    }
+
+    fn trim_end(&mut self) {
+        let len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(len);
+    }
+
+    // whether the docs fit on one line, with what follows them up to the next line break
+    fn fits(&self, docs: &[Doc<'a>], rest: &[(usize, bool, &Doc<'a>)]) -> bool {
+        let mut width = self.options.line_width as isize - self.col as isize;
+        let mut stack: Vec<(bool, &Doc)> = docs.iter().rev().map(|doc| (true, doc)).collect();
+        let mut rest = rest.iter().rev();
+        while width >= 0 {
+            let (flat, doc) = match stack.pop() {
+                Some(next) => next,
+                None => match rest.next() {
+                    Some((_, flat, doc)) => (*flat, *doc),
+                    None => return true,
+                },
+            };
+            match doc {
+                Doc::Text(text) => match text.find('\n') {
+                    Some(idx) => return width >= text[..idx].chars().count() as isize,
+                    None => width -= text.chars().count() as isize,
+                },
+                Doc::Line if flat => width -= 1,
+                Doc::SoftLine if flat => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+                Doc::LineSuffix(_) | Doc::BreakParent => {}
+                Doc::Indent(docs) => stack.extend(docs.iter().rev().map(|doc| (flat, doc))),
+                Doc::Group(docs, broken) => {
+                    stack.extend(docs.iter().rev().map(|doc| (flat && !broken, doc)))
+                }
+            }
+        }
+        false
+    }
+}
+
+// the comments between two items of a list
+struct Gap<'a> {
+    // on the line of the previous item: they stay at its end
+    trailing: Vec<Doc<'a>>,
+    // on lines of their own, with whether a blank line is before them
+    own_lines: Vec<(bool, &'a str)>,
+    // `/* ... */` on the line of the next item, right before it
+    inline: Vec<&'a str>,
+    // whether a blank line is before the next item
+    blank: bool,
+}
+
+// goes through the children of a node, formatting the comments on the way
+struct Cursor<'a, 'n> {
+    src: &'a str,
+    children: &'n [SyntaxElement],
+    pos: usize,
+}
+
+fn is_trivia(element: &SyntaxElement) -> bool {
+    matches!(element, SyntaxElement::Token(token) if token.kind.is_trivia())
+}
+
+impl<'a, 'n> Cursor<'a, 'n> {
+    fn new(src: &'a str, node: &'n SyntaxNode) -> Self {
+        Cursor {
+            src,
+            children: &node.children,
+            pos: 0,
+        }
+    }
+
+    // the next token or node, after the trivia
+    fn peek(&self) -> Option<&'n SyntaxElement> {
+        self.children[self.pos..]
+            .iter()
+            .find(|element| !is_trivia(element))
+    }
+
+    fn peek_kind(&self) -> Option<TokenKind> {
+        match self.peek() {
+            Some(SyntaxElement::Token(token)) => Some(token.kind),
+            _ => None,
+        }
+    }
+
+    fn peek_node(&self) -> Option<&'n SyntaxNode> {
+        match self.peek() {
+            Some(SyntaxElement::Node(node)) => Some(node),
+            _ => None,
+        }
+    }
+
+    fn trivia(&mut self) -> Vec<Token> {
+        let mut trivia = vec![];
+        while let Some(SyntaxElement::Token(token)) = self.children.get(self.pos) {
+            if !token.kind.is_trivia() {
+                break;
+            }
+            trivia.push(*token);
+            self.pos += 1;
+        }
+        trivia
+    }
+
+    // the next element: the trivia before it have to be formatted already
+    fn bump(&mut self) {
+        self.pos += 1;
+    }
+
+    // comments in the middle of a line: a `/* ... */` one stays where it is, a `// ...` one ends the
+    // line, and the code goes on on the next one
+    fn comments(&mut self, out: &mut Vec<Doc<'a>>) {
+        let closing = matches!(
+            self.peek_kind(),
+            Some(
+                TokenKind::RParen
+                    | TokenKind::RBracket
+                    | TokenKind::RBrace
+                    | TokenKind::Semi
+                    | TokenKind::Comma
+                    | TokenKind::Dot
+                    | TokenKind::Colon
+            )
+        );
+        for token in self.trivia() {
+            let text = token.text(self.src);
+            match (token.kind, text.starts_with("//"), closing) {
+                (TokenKind::Whitespace, ..) => {}
+                (_, true, _) => out.extend([Doc::LineSuffix(text), Doc::BreakParent]),
+                (_, false, true) => out.extend([Doc::Text(" "), Doc::Text(text)]),
+                (_, false, false) => out.extend([Doc::Text(text), Doc::Text(" ")]),
+            }
+        }
+    }
+
+    // `after_item` is false at the start of the source or of a list: the comments there don't stay
+    // at the end of the line
+    fn gap(&mut self, after_item: bool) -> Gap<'a> {
+        let trivia = self.trivia();
+        let mut gap = Gap {
+            trailing: vec![],
+            own_lines: vec![],
+            inline: vec![],
+            blank: false,
+        };
+        let mut newlines = 0;
+        for (idx, token) in trivia.iter().enumerate() {
+            let text = token.text(self.src);
+            if token.kind == TokenKind::Whitespace {
+                newlines += text.matches('\n').count();
+                continue;
+            }
+            let line_comment = text.starts_with("//");
+            // whether the line ends before the next item
+            let newline_after = trivia[idx + 1..]
+                .iter()
+                .any(|next| next.text(self.src).contains('\n'));
+            if after_item
+                && newlines == 0
+                && gap.own_lines.is_empty()
+                && (line_comment || newline_after)
+            {
+                match line_comment {
+                    true => gap
+                        .trailing
+                        .extend([Doc::LineSuffix(text), Doc::BreakParent]),
+                    false => gap.trailing.extend([Doc::Text(" "), Doc::Text(text)]),
+                }
+            } else if !line_comment && !newline_after {
+                if gap.inline.is_empty() {
+                    gap.blank = newlines > 1;
+                }
+                gap.inline.push(text);
+            } else {
+                gap.own_lines.push((newlines > 1, text));
+            }
+            newlines = 0;
+        }
+        if gap.inline.is_empty() {
+            gap.blank = newlines > 1;
+        }
+        gap
+    }
+
+    // the next token, as it is
+    fn token(&mut self, out: &mut Vec<Doc<'a>>) {
+        self.comments(out);
+        if let Some(SyntaxElement::Token(token)) = self.children.get(self.pos) {
+            out.push(Doc::Text(token.text(self.src)));
+            self.pos += 1;
+        }
+    }
+
+    // a `;`, whether it is there or not
+    fn semi(&mut self, out: &mut Vec<Doc<'a>>) {
+        match self.peek_kind() {
+            Some(TokenKind::Semi) => self.token(out),
+            _ => out.push(Doc::Text(";")),
+        }
+    }
+}
+
+struct Formatter<'a> {
+    src: &'a str,
+}
+
+impl<'a> Formatter<'a> {
+    fn prg(&self, root: &SyntaxNode) -> Doc<'a> {
+        let mut cur = Cursor::new(self.src, root);
+        let mut docs = vec![];
+        self.stmts(&mut cur, &mut docs, false);
+        if !docs.is_empty() {
+            docs.push(Doc::HardLine);
+        }
+        Doc::Group(docs, true)
+    }
+
+    // the next node, formatted
+    fn node<'n>(&self, cur: &mut Cursor<'a, 'n>, out: &mut Vec<Doc<'a>>) -> Option<&'n SyntaxNode> {
+        cur.comments(out);
+        let node = cur.peek_node()?;
+        cur.bump();
+        self.format_node(node, out);
+        Some(node)
+    }
+
+    // statements, each on a line of its own. a blank line between two is kept
+    fn stmts(&self, cur: &mut Cursor<'a, '_>, out: &mut Vec<Doc<'a>>, break_first: bool) {
+        let mut first = true;
+        loop {
+            let gap = cur.gap(!first || break_first);
+            out.extend(gap.trailing);
+            let node = cur.peek_node();
+            let mut own_lines = gap.own_lines;
+            let mut inline = gap.inline;
+            if node.is_none() {
+                // at the end of a block, the comments left go on lines of their own
+                own_lines.extend(inline.drain(..).map(|comment| (false, comment)));
+            }
+            for (blank, comment) in own_lines {
+                if !first || break_first {
+                    out.push(Doc::HardLine);
+                }
+                if blank && !first {
+                    out.push(Doc::HardLine);
+                }
+                out.push(Doc::Text(comment));
+                first = false;
+            }
+            let node = match node {
+                Some(node) => node,
+                None => break,
+            };
+            if !first || break_first {
+                out.push(Doc::HardLine);
+            }
+            if gap.blank && !first {
+                out.push(Doc::HardLine);
+            }
+            for comment in inline {
+                out.extend([Doc::Text(comment), Doc::Text(" ")]);
+            }
+            cur.bump();
+            self.format_node(node, out);
+            first = false;
+        }
+    }
+
+    // `(a, b)`, `[a, b]` or `{ a, b }`: on one line if it fits, else one item per line
+    fn list(&self, cur: &mut Cursor<'a, '_>, out: &mut Vec<Doc<'a>>, spaced: bool, broken: bool) {
+        let line = || if spaced { Doc::Line } else { Doc::SoftLine };
+        let mut docs = vec![];
+        cur.token(&mut docs);
+        let mut items = vec![];
+        let mut empty = true;
+        loop {
+            let gap = cur.gap(!empty);
+            items.extend(gap.trailing);
+            let node = match cur.peek_node() {
+                Some(node) => node,
+                None => {
+                    // the comments before the closing bracket
+                    for (_, comment) in gap.own_lines {
+                        items.extend([Doc::HardLine, Doc::Text(comment)]);
+                    }
+                    for comment in gap.inline {
+                        // `f(/* nothing */)` can't break
+                        match empty {
+                            true => docs.push(Doc::Text(comment)),
+                            false => items.extend([Doc::Text(" "), Doc::Text(comment)]),
+                        }
+                    }
+                    break;
+                }
+            };
+            items.push(if empty { line() } else { Doc::Line });
+            for (_, comment) in gap.own_lines {
+                items.extend([Doc::Text(comment), Doc::HardLine]);
+            }
+            for comment in gap.inline {
+                items.extend([Doc::Text(comment), Doc::Text(" ")]);
+            }
+            cur.bump();
+            self.format_node(node, &mut items);
+            empty = false;
+            if cur.peek_kind() == Some(TokenKind::Comma) {
+                cur.comments(&mut items);
+                cur.bump();
+                // a trailing comma is dropped
+                if cur.peek_node().is_some() {
+                    items.push(Doc::Text(","));
+                }
+            }
+        }
+        if !items.is_empty() {
+            docs.push(Doc::Indent(items));
+            docs.push(if empty { Doc::SoftLine } else { line() });
+        }
+        cur.token(&mut docs);
+        let broken = (broken && !empty) || docs.iter().any(breaks_group);
+        out.push(Doc::Group(docs, broken));
+    }
+
+    fn block(&self, cur: &mut Cursor<'a, '_>, out: &mut Vec<Doc<'a>>) {
+        let mut docs = vec![];
+        cur.token(&mut docs);
+        let mut body = vec![];
+        self.stmts(cur, &mut body, true);
+        if !body.is_empty() {
+            docs.push(Doc::Indent(body));
+            docs.push(Doc::HardLine);
+        }
+        cur.token(&mut docs);
+        out.push(group(docs));
+    }
+
+    // the body of a loop or of an `if`. after a `//` comment, one which isn't a block goes on the
+    // next line, indented
+    fn body<'n>(&self, cur: &mut Cursor<'a, 'n>, out: &mut Vec<Doc<'a>>) -> Option<&'n SyntaxNode> {
+        out.push(Doc::Text(" "));
+        let mut docs = vec![];
+        cur.comments(&mut docs);
+        let node = cur.peek_node()?;
+        cur.bump();
+        self.format_node(node, &mut docs);
+        let after_line_comment = docs.iter().any(|doc| matches!(doc, Doc::LineSuffix(_)));
+        match after_line_comment && node.kind != NodeKind::Block {
+            true => out.push(Doc::Indent(docs)),
+            false => out.extend(docs),
+        }
+        Some(node)
+    }
+
+    // `async function* name`, `get name` or `*name`, then the params and the body
+    fn function(&self, cur: &mut Cursor<'a, '_>, out: &mut Vec<Doc<'a>>, is_method: bool) {
+        let mut first = true;
+        loop {
+            match cur.peek() {
+                Some(SyntaxElement::Token(token)) if token.kind == TokenKind::LParen => break,
+                Some(SyntaxElement::Token(token)) => {
+                    let after_keyword = !first && token.kind == TokenKind::Ident;
+                    if after_keyword && !is_method {
+                        out.push(Doc::Text(" "));
+                    }
+                    cur.token(out);
+                    if is_method && token.kind == TokenKind::Ident {
+                        out.push(Doc::Text(" "));
+                    }
+                }
+                Some(SyntaxElement::Node(_)) => {
+                    self.node(cur, out);
+                }
+                None => return,
+            }
+            first = false;
+        }
+        self.list(cur, out, false, false);
+        out.push(Doc::Text(" "));
+        self.node(cur, out);
+    }
+
+    // `a |> f(%) |> g(%)`: the steps go on lines of their own when it doesn't fit
+    fn pipe(&self, node: &SyntaxNode, out: &mut Vec<Doc<'a>>, steps: &mut Vec<Doc<'a>>) {
+        let mut cur = Cursor::new(self.src, node);
+        cur.comments(out);
+        if let Some(lhs) = cur.peek_node() {
+            cur.bump();
+            match lhs.kind {
+                NodeKind::Pipe => self.pipe(lhs, out, steps),
+                _ => self.format_node(lhs, out),
+            }
+        }
+        steps.push(Doc::Line);
+        cur.token(steps);
+        steps.push(Doc::Text(" "));
+        self.node(&mut cur, steps);
+    }
+
+    // the tokens and nodes left, without spaces between them
+    fn tight(&self, cur: &mut Cursor<'a, '_>, out: &mut Vec<Doc<'a>>) {
+        while let Some(element) = cur.peek() {
+            match element {
+                SyntaxElement::Token(_) => cur.token(out),
+                SyntaxElement::Node(_) => {
+                    self.node(cur, out);
+                }
+            }
+        }
+    }
+
+    fn format_node(&self, node: &SyntaxNode, out: &mut Vec<Doc<'a>>) {
+        let mut cur = Cursor::new(self.src, node);
+        let cur = &mut cur;
+        match node.kind {
+            NodeKind::VarDecl => {
+                cur.token(out);
+                out.push(Doc::Text(" "));
+                cur.token(out);
+                if cur.peek_kind() == Some(TokenKind::Assign) {
+                    out.push(Doc::Text(" "));
+                    cur.token(out);
+                    out.push(Doc::Text(" "));
+                    self.node(cur, out);
+                }
+                cur.semi(out);
+            }
+            NodeKind::Reassign | NodeKind::SetMember => {
+                match node.kind {
+                    NodeKind::Reassign => cur.token(out),
+                    _ => {
+                        self.node(cur, out);
+                    }
+                }
+                out.push(Doc::Text(" "));
+                cur.token(out);
+                out.push(Doc::Text(" "));
+                self.node(cur, out);
+                cur.semi(out);
+            }
+            NodeKind::ExprStmt => {
+                self.node(cur, out);
+                cur.semi(out);
+            }
+            NodeKind::Return => {
+                cur.token(out);
+                if cur.peek_node().is_some() {
+                    out.push(Doc::Text(" "));
+                    self.node(cur, out);
+                }
+                cur.semi(out);
+            }
+            NodeKind::Block => self.block(cur, out),
+            // `for (const x of xs) body`
+            NodeKind::For => {
+                cur.token(out);
+                out.push(Doc::Text(" "));
+                cur.token(out);
+                cur.token(out);
+                for _ in 0..2 {
+                    out.push(Doc::Text(" "));
+                    cur.token(out);
+                }
+                out.push(Doc::Text(" "));
+                self.node(cur, out);
+                cur.token(out);
+                self.body(cur, out);
+            }
+            NodeKind::While | NodeKind::If => {
+                cur.token(out);
+                out.push(Doc::Text(" "));
+                cur.token(out);
+                self.node(cur, out);
+                cur.token(out);
+                let then = self.body(cur, out);
+                if cur.peek_kind().is_some() {
+                    // the comments at the end of the body stay on its line
+                    let gap = cur.gap(true);
+                    out.extend(gap.trailing);
+                    match then.map(|then| then.kind) {
+                        Some(NodeKind::Block) if gap.own_lines.is_empty() => {
+                            out.push(Doc::Text(" "))
+                        }
+                        _ => out.push(Doc::HardLine),
+                    }
+                    for (_, comment) in gap.own_lines {
+                        out.extend([Doc::Text(comment), Doc::HardLine]);
+                    }
+                    for comment in gap.inline {
+                        out.extend([Doc::Text(comment), Doc::Text(" ")]);
+                    }
+                    cur.token(out);
+                    self.body(cur, out);
+                }
+            }
+            NodeKind::FunctionDecl | NodeKind::Function => self.function(cur, out, false),
+            NodeKind::PropMethod => self.function(cur, out, true),
+            NodeKind::Object => {
+                // methods get a line each. the layout of the source doesn't count: an object broken
+                // because it was too long would stay broken, and formatting again could change it
+                let broken = node.nodes().any(|prop| prop.kind == NodeKind::PropMethod);
+                self.list(cur, out, true, broken)
+            }
+            NodeKind::Array => self.list(cur, out, false, false),
+            NodeKind::Call => {
+                cur.token(out);
+                self.list(cur, out, false, false);
+            }
+            NodeKind::Member | NodeKind::MethodCall => {
+                if let Some(obj) = self.node(cur, out) {
+                    // `1.x` would be the number `1.` followed by `x`
+                    let text = obj.text(self.src).trim_start_matches('-');
+                    if obj.kind == NodeKind::Literal && text.bytes().all(|c| c.is_ascii_digit()) {
+                        out.push(Doc::Text(" "));
+                    }
+                }
+                cur.token(out);
+                cur.token(out);
+                if node.kind == NodeKind::MethodCall {
+                    self.list(cur, out, false, false);
+                }
+            }
+            NodeKind::Binary => {
+                self.node(cur, out);
+                out.push(Doc::Text(" "));
+                cur.token(out);
+                out.push(Doc::Text(" "));
+                self.node(cur, out);
+            }
+            NodeKind::Pipe => {
+                let mut docs = vec![];
+                let mut steps = vec![];
+                self.pipe(node, &mut docs, &mut steps);
+                docs.push(Doc::Indent(steps));
+                out.push(group(docs));
+            }
+            NodeKind::Yield | NodeKind::Await => {
+                cur.token(out);
+                if cur.peek_kind() == Some(TokenKind::Star) {
+                    cur.token(out);
+                }
+                if cur.peek_node().is_some() {
+                    out.push(Doc::Text(" "));
+                    self.node(cur, out);
+                }
+            }
+            NodeKind::Param | NodeKind::PropValue => {
+                match node.kind {
+                    NodeKind::Param => cur.token(out),
+                    _ => {
+                        self.node(cur, out);
+                    }
+                }
+                if let Some(kind) = cur.peek_kind() {
+                    if kind == TokenKind::Assign {
+                        out.push(Doc::Text(" "));
+                    }
+                    cur.token(out);
+                    out.push(Doc::Text(" "));
+                    self.node(cur, out);
+                }
+            }
+            NodeKind::Prefix => {
+                let minus = cur.peek_kind() == Some(TokenKind::Minus);
+                cur.token(out);
+                // `--1` would read like a decrement
+                let operand = cur.peek_node().map_or("", |operand| operand.text(self.src));
+                if minus && operand.starts_with('-') {
+                    out.push(Doc::Text(" "));
+                }
+                self.node(cur, out);
+            }
+            NodeKind::Error => out.push(Doc::Text(node.text(self.src))),
+            // names, literals, `-x`, `...x`, `(x)`, property keys
+            _ => self.tight(cur, out),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+pub mod bigint;
+pub mod formatter;
+pub mod parser;
+pub mod runner;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Cannot read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("parse error: {0}")]
+    Parse(#[from] parser::Error),
+    #[error("runtime error: {0}")]
+    Runtime(#[from] runner::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
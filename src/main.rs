@@ -1,45 +1,68 @@
 use clap::{CommandFactory, Parser as _};
-
-extern crate pest;
-#[macro_use]
-extern crate pest_derive;
-use anyhow::Result;
-
-#[derive(Parser)]
-#[grammar = "parser/lang.pest"]
-pub struct LangParser;
+use js_light::parser::ast::Prg;
+use js_light::{formatter, parser, runner, Error, Result};
 
 #[derive(clap::Parser, Debug)]
 #[command(about = "A tiny js-like interpreter", long_about = None)]
 pub struct MainArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[clap(long)]
     file: Option<String>,
     #[clap(long)]
     expr: Option<String>,
+    /// no implicit type conversions, like `"use strict";` at the top of the program
+    #[clap(long)]
+    strict: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// format files in place, or stdin to stdout
+    Fmt(FmtArgs),
 }
 
-use std::{fs, process};
+#[derive(clap::Args, Debug)]
+struct FmtArgs {
+    files: Vec<String>,
+    /// don't write anything, and fail if a file isn't formatted
+    #[clap(long)]
+    check: bool,
+    #[clap(long, default_value_t = formatter::Options::default().line_width)]
+    line_width: usize,
+    /// spaces per indentation level
+    #[clap(long, default_value_t = formatter::Options::default().indent)]
+    indent: usize,
+}
 
-mod parser;
-mod runner;
+use std::io::Read;
+use std::{fs, io, process, thread};
 
-// #[derive(Debug, thiserror::Error)]
-// pub enum Error {
-//     #[error("parse error {0}")]
-//     ParseError(#[from] parser::Error),
-//     #[error("runtime error {0}")]
-//     RuntimeError(#[from] runner::Error),
-// }
-//
-// pub type Result<T> = std::result::Result<T, Error>;
+// the parser and the runner recurse on nested code: their depth limits fit in the stack of the main
+// thread of a release build, not of a debug build
+const STACK_SIZE: usize = 64 * 1024 * 1024;
 
 fn main() {
     let args = MainArgs::parse();
+    let handle = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&args))
+        .unwrap();
+    if handle.join().is_err() {
+        process::exit(101)
+    }
+}
+
+fn run(args: &MainArgs) {
+    if let Some(Command::Fmt(fmt_args)) = &args.command {
+        process::exit(fmt(fmt_args))
+    }
 
     // expr
     if let Some(expr) = &args.expr {
-        run_prg(&expr).unwrap_or_else(|e| {
-            eprintln!("Error running expr: {}", e);
+        let prg = parse("<expr>", expr);
+        run_expr(&prg, args.strict).unwrap_or_else(|e| {
+            eprintln!("Error running expr: {}", locate("<expr>", expr, &e));
             process::exit(1)
         });
         process::exit(0)
@@ -51,8 +74,9 @@ fn main() {
             eprintln!("Error reading file {}: {}", file, e);
             process::exit(1)
         });
-        run_prg(&content).unwrap_or_else(|e| {
-            eprintln!("Error running prg: {}", e);
+        let prg = parse(file, &content);
+        run_prg(&prg, args.strict).unwrap_or_else(|e| {
+            eprintln!("Error running prg: {}", locate(file, &content, &e));
             process::exit(1)
         });
         process::exit(0)
@@ -62,13 +86,138 @@ fn main() {
     process::exit(0)
 }
 
-fn run_prg(content: &str) -> Result<()> {
-    let prg = parser::parse_prg(&content)?;
+// report every syntax error, not just the first one
+fn parse(path: &str, content: &str) -> Prg {
+    let (prg, errors) = parser::parse_prg_partial(content);
+    if errors.is_empty() {
+        return prg;
+    }
+    for err in errors {
+        eprintln!(
+            "Error parsing prg: {}",
+            locate(path, content, &Error::Parse(err))
+        );
+    }
+    process::exit(1)
+}
+
+// the exit code: 1 if a file has a syntax error, or isn't formatted with `--check`
+fn fmt(args: &FmtArgs) -> i32 {
+    let options = formatter::Options {
+        line_width: args.line_width,
+        indent: args.indent,
+    };
+    if args.files.is_empty() {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .unwrap_or_else(|e| {
+                eprintln!("Error reading stdin: {}", e);
+                process::exit(1)
+            });
+        let formatted = format("<stdin>", &content, &options);
+        if args.check {
+            return check("<stdin>", &content, &formatted);
+        }
+        print!("{}", formatted);
+        return 0;
+    }
+    let mut code = 0;
+    for file in &args.files {
+        let content = fs::read_to_string(file).unwrap_or_else(|e| {
+            eprintln!("Error reading file {}: {}", file, e);
+            process::exit(1)
+        });
+        let formatted = format(file, &content, &options);
+        if args.check {
+            code = code.max(check(file, &content, &formatted));
+        } else if formatted != content {
+            fs::write(file, formatted).unwrap_or_else(|e| {
+                eprintln!("Error writing file {}: {}", file, e);
+                process::exit(1)
+            });
+        }
+    }
+    code
+}
+
+// like `parse`, every syntax error is reported
+fn format(path: &str, content: &str, options: &formatter::Options) -> String {
+    let tree = parser::parse_cst(content);
+    if tree.errors.is_empty() {
+        return formatter::format_tree(&tree, options);
+    }
+    for err in tree.errors {
+        eprintln!(
+            "Error parsing prg: {}",
+            locate(path, content, &Error::Parse(err))
+        );
+    }
+    process::exit(1)
+}
+
+fn check(path: &str, content: &str, formatted: &str) -> i32 {
+    if content == formatted {
+        return 0;
+    }
+    println!("{} is not formatted", path);
+    1
+}
+
+// only print the result, so it can be used as a calculator or in a pipeline
+fn run_expr(prg: &Prg, strict: bool) -> Result<()> {
+    let mut runner = runner::Runner::new();
+    runner.strict = strict;
+    match runner.run_prg(prg)? {
+        runner::Value::Str(s) => println!("{}", s),
+        val => println!("{}", val),
+    }
+    Ok(())
+}
+
+fn run_prg(prg: &Prg, strict: bool) -> Result<()> {
     println!("parsed prg:\n{}", prg);
 
     println!("executing prg");
     let mut runner = runner::Runner::new();
-    runner.run_prg(&prg)?;
+    runner.strict = strict;
+    runner.run_prg(prg)?;
     runner.print_vars();
     Ok(())
 }
+
+// `file:line:col: message`, followed by the line of code, with the failing part underlined
+fn locate(path: &str, src: &str, err: &Error) -> String {
+    let (message, line, col, len) = match err {
+        Error::Parse(parser::Error::Syntax {
+            line, col, message, ..
+        }) => (message.clone(), *line, *col, 1),
+        Error::Runtime(err) => match err.span() {
+            Some(span) => {
+                let (line, col) = span.line_col(src);
+                let len = src[span.start..span.end]
+                    .chars()
+                    .take_while(|&c| c != '\n')
+                    .count();
+                (err.to_string(), line, col, len.max(1))
+            }
+            None => return err.to_string(),
+        },
+        err => return err.to_string(),
+    };
+    let code = src.lines().nth(line - 1).unwrap_or_default();
+    let gutter = " ".repeat(line.to_string().len());
+    format!(
+        "{}:{}:{}: {}\n{} |\n{} | {}\n{} | {}{}",
+        path,
+        line,
+        col,
+        message,
+        gutter,
+        line,
+        code,
+        gutter,
+        " ".repeat(col - 1),
+        "^".repeat(len)
+    )
+}
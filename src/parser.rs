@@ -1,171 +1,250 @@
-use crate::pest::Parser;
-use crate::LangParser;
-use crate::Rule;
-use anyhow::{bail, Result};
-use pest::iterators::Pair;
-
-use ast::{Expr, InfixOp, Literal, PrefixOp, Prg, Stmt};
+use ast::{Literal, Prg, Span};
 
 pub mod ast;
+pub mod cst;
+#[cfg(feature = "grammar")]
+pub mod grammar;
+pub mod lexer;
+mod pratt;
 
-// use pest::error::Error as PestError;
-// #[derive(Debug, thiserror::Error)]
-// pub enum Error {
-//     #[error("invalid json {0}")]
-//     InvalidJson(Expr),
-//     #[error(transparent)]
-//     Pest(#[from] PestError<Rule>),
-//     #[error("invalid infix {0:?}")]
-//     Infix(Rule),
-//     #[error("invalid prefix {0:?}")]
-//     Prefix(Rule),
-//     #[error("invalid litteral {0:?}")]
-//     Litteral(Rule),
-//     #[error("invalid expression {0:?}")]
-//     Expr(Rule),
-//     #[error("unexpected unary {0}")]
-//     Unary(String),
-//     #[error("invalid statement {0:?}")]
-//     Stmt(Rule),
-// }
-//
-// pub type Result<T> = std::result::Result<T, Error>;
-
-pub fn parse_json(str: &str) -> Result<Literal> {
-    let pairs = LangParser::parse(Rule::literal, str);
-    let pair = pairs.unwrap().into_iter().next().unwrap();
-    match parse_expr(pair)? {
-        Expr::Literal(literal) => Ok(literal),
-        unknown => bail!("Unexpected json expr: {}", unknown),
-    }
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    // rejected by the grammar, or not a valid program, like a reserved word used as a variable name
+    #[error("line {line}, column {col}: {message}")]
+    Syntax {
+        line: usize,
+        col: usize,
+        span: Span,
+        message: String,
+    },
+    // a tree the parser doesn't expect, which the grammar shouldn't produce
+    #[error("{0}")]
+    Unexpected(String),
 }
 
-pub fn parse_prg(str: &str) -> Result<Prg> {
-    let mut ast = vec![];
-    let pairs = LangParser::parse(Rule::prg, str);
-    //println!("[DDA] mod::pairs {:?}", pairs);
+pub type Result<T> = std::result::Result<T, Error>;
 
-    for pair in pairs? {
-        ast.push(parse_stmt(pair)?)
+// an error at a span of the source. the line and column are filled in by `locate`, by the entry
+// points: they are counted on the whole source, which is slow to do for each error
+fn syntax_error(span: Span, message: String) -> Error {
+    Error::Syntax {
+        line: 0,
+        col: 0,
+        span,
+        message,
     }
-    Ok(Prg { stmts: ast })
 }
 
-fn parse_stmt(pair: Pair<Rule>) -> Result<Stmt> {
-    match pair.as_rule() {
-        Rule::assignment => {
-            let mut inner_rules = pair.into_inner();
-            let name = inner_rules.next().unwrap().as_str().to_string();
-            let val = parse_expr(inner_rules.next().unwrap())?;
-            Ok(Stmt::Assign(name, Box::new(val)))
-        }
-        unknown => bail!("Unexpected statement: {:?}", unknown),
+fn locate(mut err: Error, str: &str) -> Error {
+    if let Error::Syntax {
+        line, col, span, ..
+    } = &mut err
+    {
+        (*line, *col) = span.line_col(str);
+    }
+    err
+}
+
+// an object, array, string, number or keyword literal, in the syntax of the language: for json
+// files, `runner::json::parse_json` builds the values directly
+pub fn parse_literal(str: &str) -> Result<Literal> {
+    pratt::parse_literal(str).map_err(|err| locate(err, str))
+}
+
+pub fn parse_prg(str: &str) -> Result<Prg> {
+    let (prg, mut errors) = parse_prg_partial(str);
+    match errors.is_empty() {
+        true => Ok(prg),
+        false => Err(errors.remove(0)),
     }
 }
 
-fn parse_expr(pair: Pair<Rule>) -> Result<Expr> {
-    match pair.as_rule() {
-        Rule::fct_call => {
-            let mut inner_rules = pair.into_inner();
-            // node: we skip the function_start rule and go directly to the ident inside it
-            let name = inner_rules
-                .next()
-                .unwrap()
-                .into_inner()
-                .next()
-                .unwrap()
-                .as_str()
-                .to_string();
-            let mut params = vec![];
-            for nx_pair in inner_rules {
-                params.push(parse_expr(nx_pair)?)
+// parse as much as possible, for tooling and to report every error at once: on a syntax error,
+// parsing starts again at the next statement boundary. returns the statements which could be
+// parsed, and all the errors
+pub fn parse_prg_partial(str: &str) -> (Prg, Vec<Error>) {
+    let (prg, errors) = pratt::parse_prg_partial(str);
+    (
+        prg,
+        errors.into_iter().map(|err| locate(err, str)).collect(),
+    )
+}
+
+// the lossless syntax tree of the source, with its comments and whitespace. like
+// `parse_prg_partial`, it never fails: the syntax errors are in `errors`, and the tokens which
+// couldn't be parsed in `Error` nodes
+pub fn parse_cst(str: &str) -> cst::SyntaxTree<'_> {
+    let mut tree = pratt::parse_cst(str);
+    tree.errors = tree
+        .errors
+        .into_iter()
+        .map(|err| locate(err, str))
+        .collect();
+    tree
+}
+
+const STMT_KEYWORDS: [&str; 10] = [
+    "var", "let", "const", "function", "async", "if", "for", "while", "return", "await",
+];
+
+// the parsers are recursive: past this many nested brackets, statements or expressions, they would
+// run out of stack
+pub const MAX_NESTING: usize = 256;
+
+const RESERVED_WORDS: [&str; 39] = [
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+// an identifier, with its `\u` escapes decoded. the lexer already checked they are well formed
+fn decode_ident(raw: &str, span: Span) -> Result<String> {
+    if !raw.contains('\\') {
+        return Ok(raw.to_string());
+    }
+    let mut res = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(idx) = rest.find("\\u") {
+        res.push_str(&rest[..idx]);
+        rest = &rest[idx + 2..];
+        let hex = match rest.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced.find('}').unwrap();
+                rest = &braced[end + 1..];
+                &braced[..end]
             }
-            Ok(Expr::FctCall(name, params))
-        }
-        Rule::sum | Rule::factor | Rule::power => {
-            let mut inner_rules = pair.into_inner();
-            let lhs_pair = inner_rules.next().unwrap();
-            let mut lhs = parse_expr(lhs_pair)?;
-            while let (Some(op_pair), Some(rhs_pair)) = (inner_rules.next(), inner_rules.next()) {
-                let infix = parse_infix_op(op_pair)?;
-                let rhs = parse_expr(rhs_pair)?;
-                lhs = Expr::Infix(infix, Box::new(lhs), Box::new(rhs))
+            None => {
+                let hex = &rest[..4];
+                rest = &rest[4..];
+                hex
             }
-            Ok(lhs)
-        }
-        Rule::unary => {
-            let mut inner_rules = pair.into_inner();
-            match (inner_rules.next(), inner_rules.next()) {
-                (Some(op_pair), Some(rhs_pair)) => {
-                    let prefix = parse_prefix_op(op_pair)?;
-                    let rhs = parse_expr(rhs_pair)?;
-                    Ok(Expr::Prefix(prefix, Box::new(rhs)))
-                }
-                (Some(lhs_pair), None) => parse_expr(lhs_pair),
-                unknown => bail!("Unexpected unary: {:?}", unknown),
+        };
+        let c = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+        // an escape still has to be a valid ident char
+        let is_valid = |c: char| match res.is_empty() {
+            true => lexer::is_ident_start(c),
+            false => lexer::is_ident_part(c),
+        };
+        match c {
+            Some(c) if is_valid(c) => res.push(c),
+            _ => {
+                let message = format!("Invalid escape \\u{} in identifier {}", hex, raw);
+                return Err(syntax_error(span, message));
             }
         }
-        Rule::ident => Ok(Expr::Ident(pair.as_str().to_string())),
-        Rule::literal => Ok(Expr::Literal(parse_literal(
-            pair.into_inner().next().unwrap(),
-        )?)),
-        Rule::inparens => Ok(Expr::Parens(Box::new(parse_expr(
-            pair.into_inner().next().unwrap(),
-        )?))),
-        unknown => bail!("Unexpected expression: {:?}", unknown),
     }
+    res.push_str(rest);
+    Ok(res)
 }
 
-fn parse_literal(pair: Pair<Rule>) -> Result<Literal> {
-    match pair.as_rule() {
-        Rule::object => {
-            let mut fields = vec![];
-            for pair in pair.into_inner() {
-                let mut inner_rules = pair.into_inner();
-                let name_pair = inner_rules.next().unwrap();
-                let name = (match name_pair.as_rule() {
-                    Rule::ident => Ok::<String, anyhow::Error>(name_pair.as_str().to_string()),
-                    Rule::string => Ok(name_pair.into_inner().next().unwrap().as_str().to_string()),
-                    unknown => bail!("Unexpected litteral: {:?}", unknown),
-                })?;
-                let value = parse_expr(inner_rules.next().unwrap())?;
-                fields.push((name, value))
-            }
-            Ok(Literal::Object(fields))
-        }
-        Rule::array => {
-            let mut fields = vec![];
-            for pair in pair.into_inner() {
-                fields.push(parse_expr(pair)?)
-            }
-            Ok(Literal::Array(fields))
-        }
-        Rule::string => Ok(Literal::Str(
-            pair.into_inner().next().unwrap().as_str().to_string(),
-        )),
-        Rule::number => Ok(Literal::Num(pair.as_str().parse().unwrap())),
-        Rule::boolean => Ok(Literal::Bool(pair.as_str().parse().unwrap())),
-        Rule::null => Ok(Literal::Null),
-        unknown => bail!("Unexpected litteral: {:?}", unknown),
+// an identifier used to name or reference a binding: it can't be a reserved word.
+// property names can: `obj.default` is fine
+fn check_binding(name: String, span: Span) -> Result<String> {
+    if RESERVED_WORDS.contains(&name.as_str()) {
+        let message = format!(
+            "`{}` is a reserved word, it can't be used as a variable or function name",
+            name
+        );
+        return Err(syntax_error(span, message));
+    }
+    // these are parsed as literals, a variable of that name could never be read
+    if matches!(name.as_str(), "NaN" | "Infinity" | "undefined") {
+        let message = format!(
+            "`{}` is read-only, it can't be used as a variable or function name",
+            name
+        );
+        return Err(syntax_error(span, message));
     }
+    Ok(name)
 }
 
-fn parse_infix_op(pair: Pair<Rule>) -> Result<InfixOp> {
-    match pair.as_rule() {
-        Rule::add => Ok(InfixOp::Add),
-        Rule::sub => Ok(InfixOp::Sub),
-        Rule::mul => Ok(InfixOp::Mul),
-        Rule::div => Ok(InfixOp::Div),
-        Rule::pow => Ok(InfixOp::Pow),
-        Rule::modulo => Ok(InfixOp::Modulo),
-        unknown => bail!("Unexpected infix: {:?}", unknown),
+// decode the escape sequences of a string literal. the lexer already checked they are well formed
+fn unescape(raw: &str) -> Result<String> {
+    let mut res = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => res.push('\u{8}'),
+            Some('f') => res.push('\u{c}'),
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some('t') => res.push('\t'),
+            Some('u') => {
+                let unit = parse_code_unit(&mut chars)?;
+                // utf-16 surrogate pair: `\ud83d\ude00`
+                if (0xD800..0xDC00).contains(&unit) && chars.as_str().starts_with("\\u") {
+                    let mut lookahead = chars.clone();
+                    lookahead.nth(1);
+                    let low = parse_code_unit(&mut lookahead)?;
+                    if (0xDC00..0xE000).contains(&low) {
+                        chars = lookahead;
+                        let code = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+                        res.push(char::from_u32(code).unwrap());
+                        continue;
+                    }
+                }
+                // a lone surrogate can't be represented in a rust string
+                res.push(char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            Some(c) => res.push(c),
+            None => {
+                return Err(Error::Unexpected(format!(
+                    "Unexpected end of string after \\: {}",
+                    raw
+                )))
+            }
+        }
     }
+    Ok(res)
 }
 
-fn parse_prefix_op(pair: Pair<Rule>) -> Result<PrefixOp> {
-    match pair.as_rule() {
-        Rule::add => Ok(PrefixOp::Plus),
-        Rule::sub => Ok(PrefixOp::Minus),
-        unknown => bail!("Unexpected prefix: {:?}", unknown),
+fn parse_code_unit(chars: &mut std::str::Chars) -> Result<u32> {
+    let hex: String = chars.take(4).collect();
+    match u32::from_str_radix(&hex, 16) {
+        Ok(unit) if hex.len() == 4 => Ok(unit),
+        _ => Err(Error::Unexpected(format!(
+            "Invalid unicode escape: \\u{}",
+            hex
+        ))),
     }
 }
@@ -1,4 +1,7 @@
 use std::fmt;
+use std::rc::Rc;
+
+use crate::bigint::BigInt;
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct Prg {
@@ -14,75 +17,645 @@ impl fmt::Display for Prg {
     }
 }
 
+// byte offsets in the source, for error messages
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    // 1-based, with the column counted in chars
+    pub fn line_col(&self, src: &str) -> (usize, usize) {
+        let before = &src[..self.start.min(src.len())];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
 #[derive(PartialEq, Clone, Debug)]
-pub enum Stmt {
-    Assign(String, Box<Expr>),
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
 }
 
 impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum StmtKind {
+    Assign(DeclKind, String, Option<Box<Expr>>),
+    Reassign(String, Box<Expr>),
+    // `obj.name = val;`
+    SetMember(Box<Expr>, String, Box<Expr>),
+    Expr(Box<Expr>),
+    Block(Vec<Stmt>),
+    ForOf(DeclKind, String, Box<Expr>, Box<Stmt>),
+    ForIn(DeclKind, String, Box<Expr>, Box<Stmt>),
+    While(Box<Expr>, Box<Stmt>),
+    If(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
+    Return(Option<Box<Expr>>),
+    Function(Rc<FunctionDef>),
+}
+
+// a string literal, quoted and escaped as json
+pub fn quote_str(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            '\u{8}' => res.push_str("\\b"),
+            '\u{c}' => res.push_str("\\f"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+// a number, formatted like javascript's Number.prototype.toString()
+pub fn num_to_string(n: f64) -> String {
+    if n.is_nan() {
+        return String::from("NaN");
+    }
+    if n == 0.0 {
+        // -0 too
+        return String::from("0");
+    }
+    if n < 0.0 {
+        return format!("-{}", num_to_string(-n));
+    }
+    if n.is_infinite() {
+        return String::from("Infinity");
+    }
+    // rust gives the shortest digits which round trip, as `d.ddde-x`
+    let sci = format!("{:e}", n);
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    // the decimal point goes after the nth digit
+    let n = exp.parse::<i32>().unwrap() + 1;
+    if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { "-" } else { "+" };
+        match k {
+            1 => format!("{}e{}{}", digits, sign, (n - 1).abs()),
+            _ => format!(
+                "{}.{}e{}{}",
+                &digits[..1],
+                &digits[1..],
+                sign,
+                (n - 1).abs()
+            ),
+        }
+    }
+}
+
+// write a list of statements inside braces, indenting every line of them
+fn fmt_block(f: &mut fmt::Formatter, stmts: &[Stmt]) -> fmt::Result {
+    writeln!(f, "{{")?;
+    for stmt in stmts {
+        for line in stmt.to_string().lines() {
+            writeln!(f, "  {}", line)?;
+        }
+    }
+    write!(f, "}}")
+}
+
+impl StmtKind {
+    pub fn at(self, span: Span) -> Stmt {
+        Stmt { kind: self, span }
+    }
+}
+
+impl fmt::Display for StmtKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Stmt::Assign(name, val) => {
-                write!(f, "var {} = {};", name, val)
+            StmtKind::Assign(kind, name, Some(val)) => write!(f, "{} {} = {};", kind, name, val),
+            StmtKind::Assign(kind, name, None) => write!(f, "{} {};", kind, name),
+            StmtKind::Reassign(name, val) => write!(f, "{} = {};", name, val),
+            StmtKind::SetMember(obj, name, val) => {
+                match at_stmt_start(&obj.kind) {
+                    true => write!(f, "({})", obj)?,
+                    // `-1 .x = 2;` sets a member of the number -1
+                    false => fmt_obj(f, &obj.kind, true)?,
+                }
+                write!(f, ".{} = {};", name, val)
             }
+            StmtKind::Expr(expr) if at_stmt_start(&expr.kind) => write!(f, "({});", expr),
+            StmtKind::Expr(expr) => write!(f, "{};", expr),
+            StmtKind::Block(stmts) => fmt_block(f, stmts),
+            StmtKind::ForOf(kind, name, iterable, body) => {
+                write!(f, "for ({} {} of {}) {}", kind, name, iterable, body)
+            }
+            StmtKind::ForIn(kind, name, obj, body) => {
+                write!(f, "for ({} {} in {}) {}", kind, name, obj, body)
+            }
+            StmtKind::While(cond, body) => write!(f, "while ({}) {}", cond, body),
+            StmtKind::If(cond, then, otherwise) => {
+                write!(f, "if ({}) ", cond)?;
+                match otherwise {
+                    // the `else` would go to the `if` inside
+                    Some(_) if takes_else(then) => fmt_block(f, std::slice::from_ref(then))?,
+                    _ => write!(f, "{}", then)?,
+                }
+                match otherwise {
+                    Some(otherwise) => write!(f, " else {}", otherwise),
+                    None => Ok(()),
+                }
+            }
+            StmtKind::Return(Some(val)) => write!(f, "return {};", val),
+            StmtKind::Return(None) => write!(f, "return;"),
+            StmtKind::Function(def) => write!(f, "{}", def),
+        }
+    }
+}
+
+// whether a statement starting with the expression would be another statement: a block for
+// `{}`, a declaration for a named function. `{"a": 1}` can't be a block, so it is an object
+fn at_stmt_start(kind: &ExprKind) -> bool {
+    match kind {
+        ExprKind::Literal(Literal::Object(props)) => props.is_empty(),
+        ExprKind::Function(def) => def.name.is_some(),
+        ExprKind::Infix(_, lhs, _)
+        | ExprKind::Pipe(lhs, _)
+        | ExprKind::Member(lhs, _)
+        | ExprKind::MethodCall(lhs, ..) => at_stmt_start(&lhs.kind),
+        _ => false,
+    }
+}
+
+// whether an `else` after the statement would belong to an `if` at its end
+fn takes_else(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::If(_, _, None) => true,
+        StmtKind::If(_, _, Some(body))
+        | StmtKind::While(_, body)
+        | StmtKind::ForOf(_, _, _, body)
+        | StmtKind::ForIn(_, _, _, body) => takes_else(body),
+        _ => false,
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum DeclKind {
+    Var,
+    Let,
+    Const,
+}
+
+impl fmt::Display for DeclKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeclKind::Var => write!(f, "var"),
+            DeclKind::Let => write!(f, "let"),
+            DeclKind::Const => write!(f, "const"),
         }
     }
 }
 
 #[derive(PartialEq, Clone, Debug)]
-pub enum Expr {
+pub struct FunctionDef {
+    pub name: Option<String>,
+    pub params: Vec<Param>,
+    pub body: Vec<Stmt>,
+    pub is_generator: bool,
+    pub is_async: bool,
+    // the body reads `arguments`, so the function takes any number of params
+    pub uses_arguments: bool,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Param {
+    pub name: String,
+    // evaluated at call time, when the param is missing or undefined
+    pub default: Option<Expr>,
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.default {
+            Some(default) => write!(f, "{} = {}", self.name, default),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl fmt::Display for FunctionDef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_async {
+            write!(f, "async ")?;
+        }
+        write!(f, "function")?;
+        if self.is_generator {
+            write!(f, "*")?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, " {}", name)?;
+        }
+        self.fmt_rest(f)
+    }
+}
+
+impl FunctionDef {
+    // the params and the body, shared with the method shorthand of object literals
+    fn fmt_rest(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = self.params.iter().map(|param| param.to_string()).collect();
+        write!(f, "({}) ", params.join(", "))?;
+        fmt_block(f, &self.body)
+    }
+
+    // the params which have to be given: the ones before the first default, like js's `f.length`
+    pub fn nb_required(&self) -> usize {
+        self.params
+            .iter()
+            .take_while(|param| param.default.is_none())
+            .count()
+    }
+}
+
+// whether `arguments` is read by the statements, or by the param defaults.
+// nested functions have their own `arguments`, so they are not looked into
+pub fn reads_arguments(stmts: &[Stmt], params: &[Param]) -> bool {
+    let defaults = params.iter().filter_map(|param| param.default.as_ref());
+    defaults.into_iter().any(expr_reads_arguments) || stmts.iter().any(stmt_reads_arguments)
+}
+
+fn stmt_reads_arguments(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::Assign(_, _, val) | StmtKind::Return(val) => {
+            val.as_deref().is_some_and(expr_reads_arguments)
+        }
+        StmtKind::Reassign(_, val) | StmtKind::Expr(val) => expr_reads_arguments(val),
+        StmtKind::SetMember(obj, _, val) => expr_reads_arguments(obj) || expr_reads_arguments(val),
+        StmtKind::Block(stmts) => stmts.iter().any(stmt_reads_arguments),
+        StmtKind::ForOf(_, _, val, body)
+        | StmtKind::ForIn(_, _, val, body)
+        | StmtKind::While(val, body) => expr_reads_arguments(val) || stmt_reads_arguments(body),
+        StmtKind::If(cond, then, otherwise) => {
+            expr_reads_arguments(cond)
+                || stmt_reads_arguments(then)
+                || otherwise.as_deref().is_some_and(stmt_reads_arguments)
+        }
+        StmtKind::Function(_) => false,
+    }
+}
+
+fn expr_reads_arguments(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Ident(name) => name == "arguments",
+        ExprKind::This | ExprKind::Topic | ExprKind::Function(_) => false,
+        ExprKind::Infix(_, lhs, rhs) | ExprKind::Pipe(lhs, rhs) => {
+            expr_reads_arguments(lhs) || expr_reads_arguments(rhs)
+        }
+        ExprKind::Prefix(_, val)
+        | ExprKind::Parens(val)
+        | ExprKind::Member(val, _)
+        | ExprKind::YieldStar(val)
+        | ExprKind::Await(val)
+        | ExprKind::Spread(val) => expr_reads_arguments(val),
+        ExprKind::Yield(val) => val.as_deref().is_some_and(expr_reads_arguments),
+        ExprKind::FctCall(_, params) => params.iter().any(expr_reads_arguments),
+        ExprKind::MethodCall(obj, _, params) => {
+            expr_reads_arguments(obj) || params.iter().any(expr_reads_arguments)
+        }
+        ExprKind::Literal(Literal::Array(elts)) => elts.iter().any(expr_reads_arguments),
+        ExprKind::Literal(Literal::Object(props)) => props.iter().any(|prop| match prop {
+            Prop::Value(key, val) => key_reads_arguments(key) || expr_reads_arguments(val),
+            Prop::Shorthand(name) => name == "arguments",
+            Prop::Method(key, _) | Prop::Getter(key, _) | Prop::Setter(key, _) => {
+                key_reads_arguments(key)
+            }
+        }),
+        ExprKind::Literal(_) => false,
+    }
+}
+
+fn key_reads_arguments(key: &PropKey) -> bool {
+    match key {
+        PropKey::Computed(expr) => expr_reads_arguments(expr),
+        PropKey::Name(_) => false,
+    }
+}
+
+// whether a pipeline body reads its topic. the body of a nested pipeline has a topic of its own
+pub fn uses_topic(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Topic => true,
+        ExprKind::Ident(_) | ExprKind::This | ExprKind::Function(_) => false,
+        ExprKind::Pipe(lhs, _) => uses_topic(lhs),
+        ExprKind::Infix(_, lhs, rhs) => uses_topic(lhs) || uses_topic(rhs),
+        ExprKind::Prefix(_, val)
+        | ExprKind::Parens(val)
+        | ExprKind::Member(val, _)
+        | ExprKind::YieldStar(val)
+        | ExprKind::Await(val)
+        | ExprKind::Spread(val) => uses_topic(val),
+        ExprKind::Yield(val) => val.as_deref().is_some_and(uses_topic),
+        ExprKind::FctCall(_, params) => params.iter().any(uses_topic),
+        ExprKind::MethodCall(obj, _, params) => uses_topic(obj) || params.iter().any(uses_topic),
+        ExprKind::Literal(Literal::Array(elts)) => elts.iter().any(uses_topic),
+        ExprKind::Literal(Literal::Object(props)) => props.iter().any(|prop| match prop {
+            Prop::Value(key, val) => key_uses_topic(key) || uses_topic(val),
+            Prop::Shorthand(_) => false,
+            Prop::Method(key, _) | Prop::Getter(key, _) | Prop::Setter(key, _) => {
+                key_uses_topic(key)
+            }
+        }),
+        ExprKind::Literal(_) => false,
+    }
+}
+
+fn key_uses_topic(key: &PropKey) -> bool {
+    match key {
+        PropKey::Computed(expr) => uses_topic(expr),
+        PropKey::Name(_) => false,
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_expr(f, &self.kind, YIELD, false)
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ExprKind {
     Ident(String),
+    This,
     Literal(Literal),
     Infix(InfixOp, Box<Expr>, Box<Expr>),
     Prefix(PrefixOp, Box<Expr>),
     Parens(Box<Expr>),
     FctCall(String, Vec<Expr>),
+    Member(Box<Expr>, String),
+    MethodCall(Box<Expr>, String, Vec<Expr>),
+    Function(Rc<FunctionDef>),
+    Yield(Option<Box<Expr>>),
+    YieldStar(Box<Expr>),
+    Await(Box<Expr>),
+    Spread(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    // the `%` of a pipeline body
+    Topic,
 }
 
-impl fmt::Display for Expr {
+impl ExprKind {
+    pub fn at(self, span: Span) -> Expr {
+        Expr { kind: self, span }
+    }
+}
+
+impl fmt::Display for ExprKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Expr::Ident(name) => write!(f, "{}", name),
-            Expr::Literal(literal) => write!(f, "{}", literal),
-            Expr::Infix(infix_op, lhs, rhs) => write!(f, "{} {} {}", lhs, infix_op, rhs),
-            Expr::Prefix(prefix_op, lhs) => write!(f, "{} {}", prefix_op, lhs),
-            Expr::Parens(expr) => write!(f, "({})", expr),
-            Expr::FctCall(name, params) => {
-                let params2: Vec<String> = params.iter().map(|elt| format!("{}", elt)).collect();
-                write!(f, "{}({})", name, params2.join(", "))
+        fmt_expr(f, self, YIELD, false)
+    }
+}
+
+// how tightly expressions bind, from the loosest: `a + b * c` is `a + (b * c)`. one looser than
+// its place allows is printed in parentheses, so a tree built in code prints as it is evaluated
+const YIELD: u8 = 0;
+const PIPE: u8 = 1;
+pub(crate) const EQUALITY: u8 = 2;
+pub(crate) const CMP: u8 = 3;
+pub(crate) const SUM: u8 = 4;
+pub(crate) const FACTOR: u8 = 5;
+pub(crate) const POWER: u8 = 6;
+const UNARY: u8 = 7;
+const POSTFIX: u8 = 8;
+
+fn prec(kind: &ExprKind) -> u8 {
+    match kind {
+        ExprKind::Yield(_) | ExprKind::YieldStar(_) | ExprKind::Spread(_) => YIELD,
+        ExprKind::Pipe(..) => PIPE,
+        ExprKind::Infix(op, ..) => op.prec(),
+        ExprKind::Prefix(..) | ExprKind::Await(_) => UNARY,
+        // `-1` is `-` applied to `1`
+        ExprKind::Literal(literal) if literal.is_negative() => UNARY,
+        _ => POSTFIX,
+    }
+}
+
+// `signed` is set for the operand of `-` or `+`, and the target of an assignment: a negative
+// number at their start is read as a literal (`- -1`, `-1 .x = 2;`)
+fn fmt_expr(f: &mut fmt::Formatter, kind: &ExprKind, min_prec: u8, signed: bool) -> fmt::Result {
+    let signed_literal =
+        signed && matches!(kind, ExprKind::Literal(literal) if literal.is_signed());
+    if prec(kind) < min_prec && !signed_literal {
+        write!(f, "(")?;
+        fmt_expr(f, kind, YIELD, false)?;
+        return write!(f, ")");
+    }
+    match kind {
+        ExprKind::Ident(name) => write!(f, "{}", name),
+        ExprKind::This => write!(f, "this"),
+        ExprKind::Literal(literal) => write!(f, "{}", literal),
+        ExprKind::Infix(infix_op, lhs, rhs) => {
+            // comparisons don't chain: `a < b < c` is an error
+            let prec = infix_op.prec();
+            fmt_expr(
+                f,
+                &lhs.kind,
+                if prec == CMP { prec + 1 } else { prec },
+                false,
+            )?;
+            write!(f, " {} ", infix_op)?;
+            fmt_expr(f, &rhs.kind, prec + 1, false)
+        }
+        ExprKind::Prefix(prefix_op, val) => {
+            write!(f, "{}", prefix_op)?;
+            // `--1` would read like a decrement
+            if *prefix_op == PrefixOp::Minus && starts_with_sign(&val.kind) {
+                write!(f, " ")?;
             }
+            fmt_expr(f, &val.kind, POSTFIX, true)
+        }
+        ExprKind::Parens(expr) => write!(f, "({})", expr),
+        ExprKind::FctCall(name, params) => {
+            write!(f, "{}", name)?;
+            fmt_args(f, params)
+        }
+        ExprKind::Member(obj, name) => {
+            fmt_obj(f, &obj.kind, signed)?;
+            write!(f, ".{}", name)
         }
+        ExprKind::MethodCall(obj, name, params) => {
+            fmt_obj(f, &obj.kind, signed)?;
+            write!(f, ".{}", name)?;
+            fmt_args(f, params)
+        }
+        ExprKind::Function(def) => write!(f, "{}", def),
+        ExprKind::Yield(Some(val)) => write!(f, "yield {}", val),
+        ExprKind::Yield(None) => write!(f, "yield"),
+        ExprKind::YieldStar(val) => write!(f, "yield* {}", val),
+        ExprKind::Await(val) => {
+            write!(f, "await ")?;
+            fmt_expr(f, &val.kind, UNARY, false)
+        }
+        ExprKind::Spread(val) => write!(f, "...{}", val),
+        ExprKind::Pipe(lhs, body) => {
+            fmt_expr(f, &lhs.kind, PIPE, false)?;
+            write!(f, " |> ")?;
+            fmt_expr(f, &body.kind, PIPE + 1, false)
+        }
+        ExprKind::Topic => write!(f, "%"),
+    }
+}
+
+// whether the operand of a prefix operator is printed with a `-` first
+fn starts_with_sign(kind: &ExprKind) -> bool {
+    match kind {
+        ExprKind::Literal(literal) => literal.is_signed(),
+        ExprKind::Member(obj, _) | ExprKind::MethodCall(obj, ..) => starts_with_sign(&obj.kind),
+        _ => false,
+    }
+}
+
+// the object of a member access or a method call
+fn fmt_obj(f: &mut fmt::Formatter, kind: &ExprKind, signed: bool) -> fmt::Result {
+    fmt_expr(f, kind, POSTFIX, signed)?;
+    // `1.x` would be the number `1.` followed by `x`
+    match kind {
+        ExprKind::Literal(Literal::Int(n)) if *n >= 0 || signed => write!(f, " "),
+        _ => Ok(()),
     }
 }
 
+fn fmt_args(f: &mut fmt::Formatter, params: &[Expr]) -> fmt::Result {
+    let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+    write!(f, "({})", params.join(", "))
+}
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum Literal {
-    Object(Vec<(String, Expr)>),
+    Object(Vec<Prop>),
     Array(Vec<Expr>),
     Str(String),
+    Int(i64),
     Num(f64),
+    // `123n`
+    BigInt(BigInt),
     Bool(bool),
     Null,
+    Undefined,
 }
 
 impl fmt::Display for Literal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Literal::Object(props) => {
-                let res: Vec<String> = props
-                    .iter()
-                    .map(|(name, value)| format!("\"{}\": {}", name, value))
-                    .collect();
+                let res: Vec<String> = props.iter().map(|prop| format!("{}", prop)).collect();
                 write!(f, "{{{}}}", res.join(", "))
             }
             Literal::Array(elts) => {
                 let res: Vec<String> = elts.iter().map(|elt| format!("{}", elt)).collect();
                 write!(f, "[{}]", res.join(", "))
             }
-            Literal::Str(s) => write!(f, "\"{}\"", s),
-            Literal::Num(n) => write!(f, "{}", n),
+            Literal::Str(s) => write!(f, "{}", quote_str(s)),
+            Literal::Int(n) => write!(f, "{}", n),
+            // `1.0` stays a float, and `-0` stays negative
+            Literal::Num(n) if n.fract() == 0.0 && n.abs() < 1e21 => write!(f, "{}.0", n),
+            Literal::Num(n) => write!(f, "{}", num_to_string(*n)),
+            Literal::BigInt(n) => write!(f, "{}n", n),
             Literal::Bool(b) => write!(f, "{}", b),
             Literal::Null => write!(f, "null"),
+            Literal::Undefined => write!(f, "undefined"),
+        }
+    }
+}
+
+impl Literal {
+    fn is_negative(&self) -> bool {
+        match self {
+            Literal::Int(n) => *n < 0,
+            Literal::Num(n) => n.is_sign_negative() && !n.is_nan(),
+            Literal::BigInt(n) => n.is_negative(),
+            _ => false,
+        }
+    }
+
+    // a negative number written with its sign: `-Infinity` is `-` applied to `Infinity`
+    fn is_signed(&self) -> bool {
+        match self {
+            Literal::Num(n) => self.is_negative() && n.is_finite(),
+            _ => self.is_negative(),
+        }
+    }
+}
+
+// a property of an object literal
+#[derive(PartialEq, Clone, Debug)]
+pub enum Prop {
+    Value(PropKey, Expr),
+    // `{ name }` is `{ name: name }`
+    Shorthand(String),
+    Method(PropKey, Rc<FunctionDef>),
+    Getter(PropKey, Rc<FunctionDef>),
+    Setter(PropKey, Rc<FunctionDef>),
+}
+
+impl fmt::Display for Prop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Prop::Value(key, val) => write!(f, "{}: {}", key, val),
+            Prop::Shorthand(name) => write!(f, "{}", name),
+            Prop::Method(key, def) => {
+                if def.is_async {
+                    write!(f, "async ")?;
+                }
+                if def.is_generator {
+                    write!(f, "*")?;
+                }
+                write!(f, "{}", key)?;
+                def.fmt_rest(f)
+            }
+            Prop::Getter(key, def) => {
+                write!(f, "get {}", key)?;
+                def.fmt_rest(f)
+            }
+            Prop::Setter(key, def) => {
+                write!(f, "set {}", key)?;
+                def.fmt_rest(f)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum PropKey {
+    Name(String),
+    // `[expr]`
+    Computed(Box<Expr>),
+}
+
+impl fmt::Display for PropKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropKey::Name(name) => write!(f, "{}", quote_str(name)),
+            PropKey::Computed(expr) => write!(f, "[{}]", expr),
         }
     }
 }
@@ -95,6 +668,26 @@ pub enum InfixOp {
     Div,
     Pow,
     Modulo,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    StrictEq,
+    StrictNe,
+}
+
+impl InfixOp {
+    pub(crate) fn prec(&self) -> u8 {
+        match self {
+            InfixOp::Eq | InfixOp::Ne | InfixOp::StrictEq | InfixOp::StrictNe => EQUALITY,
+            InfixOp::Lt | InfixOp::Le | InfixOp::Gt | InfixOp::Ge => CMP,
+            InfixOp::Add | InfixOp::Sub => SUM,
+            InfixOp::Mul | InfixOp::Div | InfixOp::Modulo => FACTOR,
+            InfixOp::Pow => POWER,
+        }
+    }
 }
 
 impl fmt::Display for InfixOp {
@@ -106,6 +699,14 @@ impl fmt::Display for InfixOp {
             InfixOp::Div => write!(f, "/"),
             InfixOp::Pow => write!(f, "^"),
             InfixOp::Modulo => write!(f, "%"),
+            InfixOp::Lt => write!(f, "<"),
+            InfixOp::Le => write!(f, "<="),
+            InfixOp::Gt => write!(f, ">"),
+            InfixOp::Ge => write!(f, ">="),
+            InfixOp::Eq => write!(f, "=="),
+            InfixOp::Ne => write!(f, "!="),
+            InfixOp::StrictEq => write!(f, "==="),
+            InfixOp::StrictNe => write!(f, "!=="),
         }
     }
 }
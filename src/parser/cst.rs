@@ -0,0 +1,547 @@
+// a lossless syntax tree, for tools like formatters: every token of the source is in it, whitespace
+// and comments included, so printing it gives back the exact source. the `ast` is a typed view of
+// it, see `SyntaxTree::to_prg`
+use std::fmt;
+use std::rc::Rc;
+
+use crate::parser::ast::{
+    self, DeclKind, Expr, ExprKind, FunctionDef, Literal, Param, PrefixOp, Prg, Prop, PropKey,
+};
+use crate::parser::ast::{Span, Stmt, StmtKind};
+use crate::parser::lexer::{Token, TokenKind};
+use crate::parser::pratt::{infix_op, new_function, parse_number};
+use crate::parser::{check_binding, decode_ident, unescape, Error, Result};
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum NodeKind {
+    // the whole source
+    Prg,
+    // `var x = 1;`
+    VarDecl,
+    // `x = 1;`
+    Reassign,
+    // `obj.x = 1;`
+    SetMember,
+    ExprStmt,
+    Block,
+    // `for...of` and `for...in`
+    For,
+    While,
+    If,
+    Return,
+    FunctionDecl,
+    // a variable
+    Name,
+    This,
+    // `%`, in a pipeline
+    Topic,
+    // numbers, strings, `true`, `null`...
+    Literal,
+    Object,
+    Array,
+    // `a + b`
+    Binary,
+    // `-a`
+    Prefix,
+    Parens,
+    // `f(a)`
+    Call,
+    // `a.b`
+    Member,
+    // `a.b(c)`
+    MethodCall,
+    // a function expression
+    Function,
+    // `yield` and `yield*`
+    Yield,
+    Await,
+    // `...a`
+    Spread,
+    // `a |> f(%)`
+    Pipe,
+    // a function param, with its default
+    Param,
+    // `key: val`
+    PropValue,
+    // `{ name }`
+    PropShorthand,
+    // methods, getters and setters
+    PropMethod,
+    // a property name, or a computed `[key]`
+    PropKey,
+    // tokens which could not be parsed, up to the point where parsing started again
+    Error,
+}
+
+// a node as the parser finds it: a kind, and a range of its tokens, trivia left out
+pub(super) struct RawNode {
+    pub kind: NodeKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    // from the first to the last token which is not trivia, like the spans of the ast
+    pub span: Span,
+    // the trivia around a node belong to its parent
+    pub children: Vec<SyntaxElement>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(Token),
+}
+
+#[derive(Debug)]
+pub struct SyntaxTree<'a> {
+    pub src: &'a str,
+    pub root: SyntaxNode,
+    // the syntax errors: the tokens which couldn't be parsed are in `Error` nodes
+    pub errors: Vec<Error>,
+}
+
+impl SyntaxNode {
+    pub fn text<'a>(&self, src: &'a str) -> &'a str {
+        &src[self.span.start..self.span.end]
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    // the tokens directly in this node, trivia left out
+    pub fn tokens(&self) -> impl Iterator<Item = Token> + '_ {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) if !token.kind.is_trivia() => Some(*token),
+            _ => None,
+        })
+    }
+
+    // the tokens before the first child node: keywords like `async`, `get` or `function`
+    fn leading_tokens(&self) -> impl Iterator<Item = Token> + '_ {
+        self.children
+            .iter()
+            .take_while(|child| matches!(child, SyntaxElement::Token(_)))
+            .filter_map(|child| match child {
+                SyntaxElement::Token(token) if !token.kind.is_trivia() => Some(*token),
+                _ => None,
+            })
+    }
+
+    // all the tokens of the node and of its descendants, trivia included, in order
+    pub fn descendant_tokens(&self) -> Vec<Token> {
+        let mut tokens = vec![];
+        self.push_tokens(&mut tokens);
+        tokens
+    }
+
+    fn push_tokens(&self, tokens: &mut Vec<Token>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.push_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(*token),
+            }
+        }
+    }
+}
+
+// the source, byte for byte
+impl fmt::Display for SyntaxTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.root.descendant_tokens() {
+            write!(f, "{}", token.text(self.src))?
+        }
+        Ok(())
+    }
+}
+
+impl<'a> SyntaxTree<'a> {
+    // `tokens` are all the tokens of the source. the parser counts tokens without the trivia, and
+    // gives the nodes in the order they end: children come before their parent
+    pub(super) fn build(
+        src: &'a str,
+        tokens: Vec<Token>,
+        nodes: Vec<RawNode>,
+        errors: Vec<Error>,
+    ) -> Self {
+        let significant: Vec<usize> = (0..tokens.len())
+            .filter(|idx| !tokens[*idx].kind.is_trivia())
+            .collect();
+        // the nodes without a parent yet, with their range in `tokens`, in order
+        let mut done: Vec<(usize, usize, SyntaxNode)> = vec![];
+        for node in nodes {
+            let start = significant[node.start];
+            let end = significant[node.end - 1] + 1;
+            let first_child = done.partition_point(|(child_start, ..)| *child_start < start);
+            let children = done.split_off(first_child);
+            let span = Span {
+                start: tokens[start].span.start,
+                end: tokens[end - 1].span.end,
+            };
+            done.push((
+                start,
+                end,
+                new_node(node.kind, span, &tokens[..end], start, children),
+            ));
+        }
+        let span = Span {
+            start: 0,
+            end: src.len(),
+        };
+        SyntaxTree {
+            src,
+            root: new_node(NodeKind::Prg, span, &tokens, 0, done),
+            errors,
+        }
+    }
+
+    // the ast of the statements which could be parsed, like `parse_prg_partial` gives: the error
+    // nodes are left out
+    pub fn to_prg(&self) -> Result<Prg> {
+        let mut stmts = vec![];
+        for node in self
+            .root
+            .nodes()
+            .filter(|node| node.kind != NodeKind::Error)
+        {
+            stmts.push(lower_stmt(node, self.src)?);
+        }
+        Ok(Prg { stmts })
+    }
+}
+
+// the tokens from `start` which are not in a child become children of their own
+fn new_node(
+    kind: NodeKind,
+    span: Span,
+    tokens: &[Token],
+    start: usize,
+    children: Vec<(usize, usize, SyntaxNode)>,
+) -> SyntaxNode {
+    let mut elements = vec![];
+    let mut idx = start;
+    for (child_start, child_end, child) in children {
+        elements.extend(
+            tokens[idx..child_start]
+                .iter()
+                .map(|token| SyntaxElement::Token(*token)),
+        );
+        elements.push(SyntaxElement::Node(child));
+        idx = child_end;
+    }
+    elements.extend(
+        tokens[idx..]
+            .iter()
+            .map(|token| SyntaxElement::Token(*token)),
+    );
+    SyntaxNode {
+        kind,
+        span,
+        children: elements,
+    }
+}
+
+fn unexpected(node: &SyntaxNode) -> Error {
+    Error::Unexpected(format!("Unexpected syntax tree node: {:?}", node.kind))
+}
+
+fn next_node<'n>(
+    node: &SyntaxNode,
+    nodes: &mut impl Iterator<Item = &'n SyntaxNode>,
+) -> Result<&'n SyntaxNode> {
+    nodes.next().ok_or_else(|| unexpected(node))
+}
+
+fn next_token(node: &SyntaxNode, tokens: &mut impl Iterator<Item = Token>) -> Result<Token> {
+    tokens.next().ok_or_else(|| unexpected(node))
+}
+
+fn binding(token: Token, src: &str) -> Result<String> {
+    check_binding(decode_ident(token.text(src), token.span)?, token.span)
+}
+
+fn lower_decl_kind(node: &SyntaxNode, token: Token, src: &str) -> Result<DeclKind> {
+    match token.text(src) {
+        "var" => Ok(DeclKind::Var),
+        "let" => Ok(DeclKind::Let),
+        "const" => Ok(DeclKind::Const),
+        _ => Err(unexpected(node)),
+    }
+}
+
+fn lower_stmt(node: &SyntaxNode, src: &str) -> Result<Stmt> {
+    let mut nodes = node.nodes();
+    let mut tokens = node.tokens();
+    let kind = match node.kind {
+        NodeKind::VarDecl => {
+            let kind = lower_decl_kind(node, next_token(node, &mut tokens)?, src)?;
+            let name = binding(next_token(node, &mut tokens)?, src)?;
+            let val = match nodes.next() {
+                Some(val) => Some(Box::new(lower_expr(val, src)?)),
+                None => None,
+            };
+            StmtKind::Assign(kind, name, val)
+        }
+        NodeKind::Reassign => {
+            let name = binding(next_token(node, &mut tokens)?, src)?;
+            StmtKind::Reassign(
+                name,
+                Box::new(lower_expr(next_node(node, &mut nodes)?, src)?),
+            )
+        }
+        NodeKind::SetMember => {
+            let target = lower_expr(next_node(node, &mut nodes)?, src)?;
+            let val = lower_expr(next_node(node, &mut nodes)?, src)?;
+            match target.kind {
+                ExprKind::Member(obj, name) => StmtKind::SetMember(obj, name, Box::new(val)),
+                _ => return Err(unexpected(node)),
+            }
+        }
+        NodeKind::ExprStmt => {
+            StmtKind::Expr(Box::new(lower_expr(next_node(node, &mut nodes)?, src)?))
+        }
+        NodeKind::Block => StmtKind::Block(lower_stmts(nodes, src)?),
+        NodeKind::For => {
+            // `for ( const x of`
+            let mut tokens = tokens.skip(2);
+            let kind = lower_decl_kind(node, next_token(node, &mut tokens)?, src)?;
+            let name = binding(next_token(node, &mut tokens)?, src)?;
+            let is_of = next_token(node, &mut tokens)?.text(src) == "of";
+            let iterable = Box::new(lower_expr(next_node(node, &mut nodes)?, src)?);
+            let body = Box::new(lower_stmt(next_node(node, &mut nodes)?, src)?);
+            match is_of {
+                true => StmtKind::ForOf(kind, name, iterable, body),
+                false => StmtKind::ForIn(kind, name, iterable, body),
+            }
+        }
+        NodeKind::While => {
+            let cond = lower_expr(next_node(node, &mut nodes)?, src)?;
+            let body = lower_stmt(next_node(node, &mut nodes)?, src)?;
+            StmtKind::While(Box::new(cond), Box::new(body))
+        }
+        NodeKind::If => {
+            let cond = lower_expr(next_node(node, &mut nodes)?, src)?;
+            let then = lower_stmt(next_node(node, &mut nodes)?, src)?;
+            let otherwise = match nodes.next() {
+                Some(otherwise) => Some(Box::new(lower_stmt(otherwise, src)?)),
+                None => None,
+            };
+            StmtKind::If(Box::new(cond), Box::new(then), otherwise)
+        }
+        NodeKind::Return => match nodes.next() {
+            Some(val) => StmtKind::Return(Some(Box::new(lower_expr(val, src)?))),
+            None => StmtKind::Return(None),
+        },
+        NodeKind::FunctionDecl => StmtKind::Function(Rc::new(lower_function(node, src)?)),
+        _ => return Err(unexpected(node)),
+    };
+    Ok(kind.at(node.span))
+}
+
+fn lower_stmts<'n>(nodes: impl Iterator<Item = &'n SyntaxNode>, src: &str) -> Result<Vec<Stmt>> {
+    nodes.map(|node| lower_stmt(node, src)).collect()
+}
+
+fn lower_exprs<'n>(nodes: impl Iterator<Item = &'n SyntaxNode>, src: &str) -> Result<Vec<Expr>> {
+    nodes.map(|node| lower_expr(node, src)).collect()
+}
+
+fn lower_expr(node: &SyntaxNode, src: &str) -> Result<Expr> {
+    let mut nodes = node.nodes();
+    let mut tokens = node.tokens();
+    let kind = match node.kind {
+        NodeKind::Name => ExprKind::Ident(binding(next_token(node, &mut tokens)?, src)?),
+        NodeKind::This => ExprKind::This,
+        NodeKind::Topic => ExprKind::Topic,
+        NodeKind::Literal => ExprKind::Literal(lower_literal(node, src)?),
+        NodeKind::Object => {
+            let mut props = vec![];
+            for prop in nodes {
+                props.push(lower_prop(prop, src)?);
+            }
+            ExprKind::Literal(Literal::Object(props))
+        }
+        NodeKind::Array => ExprKind::Literal(Literal::Array(lower_exprs(nodes, src)?)),
+        NodeKind::Binary => {
+            let lhs = lower_expr(next_node(node, &mut nodes)?, src)?;
+            let (infix, _) =
+                infix_op(next_token(node, &mut tokens)?.kind).ok_or_else(|| unexpected(node))?;
+            let rhs = lower_expr(next_node(node, &mut nodes)?, src)?;
+            ExprKind::Infix(infix, Box::new(lhs), Box::new(rhs))
+        }
+        NodeKind::Prefix => {
+            let prefix = match next_token(node, &mut tokens)?.kind {
+                TokenKind::Plus => PrefixOp::Plus,
+                _ => PrefixOp::Minus,
+            };
+            ExprKind::Prefix(
+                prefix,
+                Box::new(lower_expr(next_node(node, &mut nodes)?, src)?),
+            )
+        }
+        NodeKind::Parens => {
+            ExprKind::Parens(Box::new(lower_expr(next_node(node, &mut nodes)?, src)?))
+        }
+        NodeKind::Call => {
+            let name = binding(next_token(node, &mut tokens)?, src)?;
+            ExprKind::FctCall(name, lower_exprs(nodes, src)?)
+        }
+        NodeKind::Member | NodeKind::MethodCall => {
+            let obj = Box::new(lower_expr(next_node(node, &mut nodes)?, src)?);
+            // after the `.`
+            let name_token = next_token(node, &mut tokens.skip(1))?;
+            let name = decode_ident(name_token.text(src), name_token.span)?;
+            match node.kind {
+                NodeKind::Member => ExprKind::Member(obj, name),
+                _ => ExprKind::MethodCall(obj, name, lower_exprs(nodes, src)?),
+            }
+        }
+        NodeKind::Function => ExprKind::Function(Rc::new(lower_function(node, src)?)),
+        NodeKind::Yield => {
+            let delegate = node.tokens().any(|token| token.kind == TokenKind::Star);
+            match (delegate, nodes.next()) {
+                (true, Some(val)) => ExprKind::YieldStar(Box::new(lower_expr(val, src)?)),
+                (false, Some(val)) => ExprKind::Yield(Some(Box::new(lower_expr(val, src)?))),
+                (_, None) => ExprKind::Yield(None),
+            }
+        }
+        NodeKind::Await => {
+            ExprKind::Await(Box::new(lower_expr(next_node(node, &mut nodes)?, src)?))
+        }
+        NodeKind::Spread => {
+            ExprKind::Spread(Box::new(lower_expr(next_node(node, &mut nodes)?, src)?))
+        }
+        NodeKind::Pipe => {
+            let lhs = lower_expr(next_node(node, &mut nodes)?, src)?;
+            let body = lower_expr(next_node(node, &mut nodes)?, src)?;
+            ExprKind::Pipe(Box::new(lhs), Box::new(body))
+        }
+        _ => return Err(unexpected(node)),
+    };
+    Ok(kind.at(node.span))
+}
+
+fn lower_literal(node: &SyntaxNode, src: &str) -> Result<Literal> {
+    let token = next_token(node, &mut node.tokens())?;
+    let text = node.text(src);
+    match (token.kind, text) {
+        // `-1` is a single literal
+        (TokenKind::Minus | TokenKind::Number | TokenKind::BigInt, _) => {
+            let last = node.tokens().last().unwrap_or(token);
+            parse_number(last.kind, text, node.span)
+        }
+        (TokenKind::Str, _) => Ok(Literal::Str(unescape(&text[1..text.len() - 1])?)),
+        (_, "NaN") => Ok(Literal::Num(f64::NAN)),
+        (_, "Infinity") => Ok(Literal::Num(f64::INFINITY)),
+        (_, "true") => Ok(Literal::Bool(true)),
+        (_, "false") => Ok(Literal::Bool(false)),
+        (_, "null") => Ok(Literal::Null),
+        (_, "undefined") => Ok(Literal::Undefined),
+        _ => Err(unexpected(node)),
+    }
+}
+
+// the params and the body, for both functions and methods
+fn lower_function_rest<'n>(
+    def: &mut FunctionDef,
+    nodes: impl Iterator<Item = &'n SyntaxNode>,
+    src: &str,
+) -> Result<()> {
+    for node in nodes {
+        match node.kind {
+            NodeKind::Param => {
+                let name = binding(next_token(node, &mut node.tokens())?, src)?;
+                let default = match node.nodes().next() {
+                    Some(default) => Some(lower_expr(default, src)?),
+                    None => None,
+                };
+                def.params.push(Param { name, default })
+            }
+            NodeKind::Block => def.body = lower_stmts(node.nodes(), src)?,
+            _ => return Err(unexpected(node)),
+        }
+    }
+    def.uses_arguments = ast::reads_arguments(&def.body, &def.params);
+    Ok(())
+}
+
+// `async function* name(a, b) { ... }`
+fn lower_function(node: &SyntaxNode, src: &str) -> Result<FunctionDef> {
+    let mut def = new_function();
+    let mut after_keyword = false;
+    for token in node.leading_tokens() {
+        match (token.kind, token.text(src), after_keyword) {
+            (TokenKind::Star, ..) => def.is_generator = true,
+            (TokenKind::Ident, "function", false) => after_keyword = true,
+            (TokenKind::Ident, _, false) => def.is_async = true,
+            (TokenKind::Ident, _, true) => def.name = Some(binding(token, src)?),
+            _ => {}
+        }
+    }
+    lower_function_rest(&mut def, node.nodes(), src)?;
+    Ok(def)
+}
+
+fn lower_prop(node: &SyntaxNode, src: &str) -> Result<Prop> {
+    let mut nodes = node.nodes();
+    match node.kind {
+        NodeKind::PropValue => {
+            let key = lower_prop_key(next_node(node, &mut nodes)?, src)?;
+            Ok(Prop::Value(
+                key,
+                lower_expr(next_node(node, &mut nodes)?, src)?,
+            ))
+        }
+        NodeKind::PropShorthand => {
+            let key = next_node(node, &mut nodes)?;
+            Ok(Prop::Shorthand(binding(
+                next_token(key, &mut key.tokens())?,
+                src,
+            )?))
+        }
+        NodeKind::PropMethod => {
+            let mut def = new_function();
+            let mut accessor = None;
+            for token in node.leading_tokens() {
+                match (token.kind, token.text(src)) {
+                    (TokenKind::Star, _) => def.is_generator = true,
+                    (_, "async") => def.is_async = true,
+                    (_, word) => accessor = Some(word),
+                }
+            }
+            let key = lower_prop_key(next_node(node, &mut nodes)?, src)?;
+            lower_function_rest(&mut def, nodes, src)?;
+            // `{ f() {} }` is a function named f
+            if let PropKey::Name(name) = &key {
+                def.name = Some(name.clone());
+            }
+            match accessor {
+                Some("get") => Ok(Prop::Getter(key, Rc::new(def))),
+                Some(_) => Ok(Prop::Setter(key, Rc::new(def))),
+                None => Ok(Prop::Method(key, Rc::new(def))),
+            }
+        }
+        _ => Err(unexpected(node)),
+    }
+}
+
+fn lower_prop_key(node: &SyntaxNode, src: &str) -> Result<PropKey> {
+    let token = next_token(node, &mut node.tokens())?;
+    match token.kind {
+        TokenKind::Ident => Ok(PropKey::Name(decode_ident(token.text(src), token.span)?)),
+        TokenKind::Str => {
+            let text = token.text(src);
+            Ok(PropKey::Name(unescape(&text[1..text.len() - 1])?))
+        }
+        _ => Ok(PropKey::Computed(Box::new(lower_expr(
+            next_node(node, &mut node.nodes())?,
+            src,
+        )?))),
+    }
+}
@@ -0,0 +1,781 @@
+// the pest grammar the parser started with. it is slower than the hand-written parser, and its
+// errors are vaguer, but it is short and declarative: it stays as the reference the hand-written
+// parser is tested against
+use pest::error::{ErrorVariant, InputLocation, LineColLocation};
+use pest::iterators::{Pair, Pairs};
+use pest::Parser;
+use std::rc::Rc;
+
+use crate::bigint::BigInt;
+use crate::parser::ast::{
+    self, DeclKind, Expr, ExprKind, FunctionDef, InfixOp, Literal, Param, PrefixOp, Prg, Prop,
+    PropKey, Span, Stmt, StmtKind,
+};
+use crate::parser::{
+    check_binding, decode_ident, locate, unescape, Error, Result, MAX_NESTING, STMT_KEYWORDS,
+};
+
+#[derive(pest_derive::Parser)]
+#[grammar = "parser/lang.pest"]
+struct LangParser;
+
+impl From<pest::error::Error<Rule>> for Error {
+    fn from(err: pest::error::Error<Rule>) -> Self {
+        let (line, col) = match err.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let span = match err.location {
+            InputLocation::Pos(pos) => Span {
+                start: pos,
+                end: pos,
+            },
+            InputLocation::Span((start, end)) => Span { start, end },
+        };
+        let message = match err.variant {
+            ErrorVariant::ParsingError { positives, .. } if !positives.is_empty() => {
+                format!("expected {}", rules_to_string(&positives))
+            }
+            ErrorVariant::ParsingError { negatives, .. } => {
+                format!("unexpected {}", rules_to_string(&negatives))
+            }
+            ErrorVariant::CustomError { message } => message,
+        };
+        Error::Syntax {
+            line,
+            col,
+            span,
+            message,
+        }
+    }
+}
+
+// `expected expr, ident or number`
+fn rules_to_string(rules: &[Rule]) -> String {
+    let names: Vec<String> = rules.iter().map(|rule| format!("{:?}", rule)).collect();
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+fn error_at(span: pest::Span, message: String) -> Error {
+    let (line, col) = span.start_pos().line_col();
+    let span = Span {
+        start: span.start(),
+        end: span.end(),
+    };
+    Error::Syntax {
+        line,
+        col,
+        span,
+        message,
+    }
+}
+
+pub fn parse_literal(str: &str) -> Result<Literal> {
+    parse_literal_unlocated(str).map_err(|err| locate(err, str))
+}
+
+fn parse_literal_unlocated(str: &str) -> Result<Literal> {
+    check_nesting(str)?;
+    let pair = match LangParser::parse(Rule::json, str)?.next() {
+        Some(pair) => pair,
+        None => return Err(Error::Unexpected(String::from("Empty json"))),
+    };
+    let span = pair.as_span();
+    match parse_expr(pair)?.kind {
+        ExprKind::Literal(literal) => Ok(literal),
+        unknown => Err(error_at(span, format!("Unexpected json expr: {}", unknown))),
+    }
+}
+
+pub fn parse_prg(str: &str) -> Result<Prg> {
+    let (prg, mut errors) = parse_prg_partial(str);
+    match errors.is_empty() {
+        true => Ok(prg),
+        false => Err(errors.remove(0)),
+    }
+}
+
+// parse as much as possible, for tooling and to report every error at once: on a syntax error,
+// parsing starts again at the next statement boundary. returns the statements which could be
+// parsed, and all the errors
+pub fn parse_prg_partial(str: &str) -> (Prg, Vec<Error>) {
+    if let Err(err) = check_nesting(str) {
+        return (Prg { stmts: vec![] }, vec![err]);
+    }
+    let mut stmts = vec![];
+    let mut errors = vec![];
+    let mut pos = 0;
+    // blocks opened by a statement with an error, and not closed yet: their `}` is not an error
+    let mut depth = 0;
+    while pos < str.len() {
+        // the part already parsed is blanked out, so offsets stay the same
+        let input = blank_before(str, pos);
+        let err = match LangParser::parse(Rule::main, &input) {
+            Ok(pairs) => {
+                parse_stmts(pairs, &mut stmts, &mut errors);
+                break;
+            }
+            Err(err) => Error::from(err),
+        };
+        // keep the statements before the error
+        let mut end = pos;
+        if let Ok(pairs) = LangParser::parse(Rule::partial_prg, &input) {
+            end = pairs
+                .clone()
+                .last()
+                .map_or(pos, |pair| pair.as_span().end());
+            parse_stmts(pairs, &mut stmts, &mut errors);
+        }
+        let rest = end + str[end..].len() - str[end..].trim_start().len();
+        if depth > 0 && str[rest..].starts_with('}') {
+            depth -= 1;
+            pos = rest + 1;
+            continue;
+        }
+        let err_pos = match &err {
+            Error::Syntax { span, .. } => span.start.max(rest),
+            Error::Unexpected(_) => rest,
+        };
+        errors.push(err);
+        let (next, opened) = resync(str, rest, err_pos);
+        depth += opened;
+        pos = next;
+    }
+    // columns are counted on the real source
+    let errors = errors.into_iter().map(|err| locate(err, str)).collect();
+    (Prg { stmts }, errors)
+}
+
+fn parse_stmts(pairs: Pairs<Rule>, stmts: &mut Vec<Stmt>, errors: &mut Vec<Error>) {
+    for pair in pairs {
+        if pair.as_rule() != Rule::EOI {
+            match parse_stmt(pair) {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => errors.push(err),
+            }
+        }
+    }
+}
+
+// the source, with everything before `pos` replaced by spaces. line breaks are kept
+fn blank_before(str: &str, pos: usize) -> String {
+    let mut res = String::with_capacity(str.len());
+    for c in str[..pos].chars() {
+        match c {
+            '\n' => res.push('\n'),
+            c => res.extend(std::iter::repeat_n(' ', c.len_utf8())),
+        }
+    }
+    res.push_str(&str[pos..]);
+    res
+}
+
+// where to parse again after a syntax error in the statement starting at `start`: after the
+// next `;` or `}`, or at the next keyword starting a statement. also returns the number of
+// blocks left open before that point
+fn resync(str: &str, start: usize, err_pos: usize) -> (usize, usize) {
+    let mut depth = 0;
+    let mut in_str = false;
+    let mut escaped = false;
+    let mut prev = ' ';
+    for (idx, c) in str.char_indices().skip_while(|(idx, _)| *idx < start) {
+        let at_boundary = idx >= err_pos && !in_str;
+        if in_str {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_str = false,
+                _ => {}
+            }
+        } else {
+            match c {
+                '"' => in_str = true,
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                _ => {}
+            }
+        }
+        if at_boundary && (c == ';' || c == '}') {
+            return (idx + 1, depth);
+        }
+        if at_boundary && idx > start && !is_ident_char(prev) && starts_stmt(&str[idx..]) {
+            return (idx, depth);
+        }
+        prev = c;
+    }
+    (str.len(), depth)
+}
+
+fn starts_stmt(str: &str) -> bool {
+    STMT_KEYWORDS.iter().any(|kw| starts_word(str, kw))
+}
+
+fn starts_word(str: &str, word: &str) -> bool {
+    let after = str.strip_prefix(word).and_then(|rest| rest.chars().next());
+    str.starts_with(word) && !after.is_some_and(is_ident_char)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+// pest parses recursively: programs nested too deeply, with brackets or chained `await`s and
+// `yield`s, are rejected before handing them to it
+fn check_nesting(str: &str) -> Result<()> {
+    let mut depth: usize = 0;
+    // `await await x` nests like brackets do
+    let mut chain = 0;
+    let mut in_str = false;
+    let mut escaped = false;
+    let mut prev = ' ';
+    for (idx, c) in str.char_indices() {
+        if in_str {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_str = false,
+                _ => {}
+            }
+        } else if is_ident_char(c) {
+            if !is_ident_char(prev) {
+                let rest = &str[idx..];
+                chain = match starts_word(rest, "await") || starts_word(rest, "yield") {
+                    true => chain + 1,
+                    false => 0,
+                };
+            }
+        } else {
+            match c {
+                '"' => in_str = true,
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if !(c.is_whitespace() || c == '*') {
+                chain = 0;
+            }
+        }
+        if depth + chain > MAX_NESTING {
+            let span = Span {
+                start: idx,
+                end: idx,
+            };
+            let (line, col) = span.line_col(str);
+            return Err(Error::Syntax {
+                line,
+                col,
+                span,
+                message: format!("Nested too deeply: more than {} levels", MAX_NESTING),
+            });
+        }
+        prev = c;
+    }
+    Ok(())
+}
+
+// the position of a node in the source
+fn node_span(pair: &Pair<Rule>) -> Span {
+    let span = pair.as_span();
+    Span {
+        start: span.start(),
+        end: span.end(),
+    }
+}
+
+// `a + b` goes from the start of a to the end of b
+fn join(lhs: &Expr, rhs_end: usize) -> Span {
+    Span {
+        start: lhs.span.start,
+        end: rhs_end,
+    }
+}
+
+fn parse_stmt(pair: Pair<Rule>) -> Result<Stmt> {
+    let span = node_span(&pair);
+    Ok(parse_stmt_kind(pair)?.at(span))
+}
+
+fn parse_stmt_kind(pair: Pair<Rule>) -> Result<StmtKind> {
+    match pair.as_rule() {
+        Rule::assignment => {
+            let span = pair.as_span();
+            let mut inner_rules = pair.into_inner();
+            let kind = parse_decl_kind(inner_rules.next().unwrap())?;
+            let name = parse_binding(inner_rules.next().unwrap())?;
+            let val = match inner_rules.next() {
+                Some(nx_pair) => Some(Box::new(parse_expr(nx_pair)?)),
+                None if kind == DeclKind::Const => {
+                    return Err(error_at(
+                        span,
+                        format!("Missing initializer in const declaration: {}", name),
+                    ))
+                }
+                None => None,
+            };
+            Ok(StmtKind::Assign(kind, name, val))
+        }
+        Rule::reassignment => {
+            let mut inner_rules = pair.into_inner();
+            let name = parse_binding(inner_rules.next().unwrap())?;
+            let val = parse_expr(inner_rules.next().unwrap())?;
+            Ok(StmtKind::Reassign(name, Box::new(val)))
+        }
+        Rule::member_assignment => {
+            let span = pair.as_span();
+            let mut inner_rules = pair.into_inner();
+            let target = parse_expr(inner_rules.next().unwrap())?;
+            let val = parse_expr(inner_rules.next().unwrap())?;
+            match target.kind {
+                ExprKind::Member(obj, name) => Ok(StmtKind::SetMember(obj, name, Box::new(val))),
+                target => Err(error_at(
+                    span,
+                    format!("Invalid assignment target: {}", target),
+                )),
+            }
+        }
+        Rule::expr_stmt => {
+            let expr = parse_expr(pair.into_inner().next().unwrap())?;
+            Ok(StmtKind::Expr(Box::new(expr)))
+        }
+        Rule::block => {
+            let mut stmts = vec![];
+            for nx_pair in pair.into_inner() {
+                stmts.push(parse_stmt(nx_pair)?)
+            }
+            Ok(StmtKind::Block(stmts))
+        }
+        Rule::for_loop => {
+            let mut inner_rules = pair.into_inner();
+            let kind = parse_decl_kind(inner_rules.next().unwrap())?;
+            let name = parse_binding(inner_rules.next().unwrap())?;
+            let loop_kind = inner_rules.next().unwrap().as_rule();
+            let iterable = Box::new(parse_expr(inner_rules.next().unwrap())?);
+            let body = Box::new(parse_stmt(inner_rules.next().unwrap())?);
+            match loop_kind {
+                Rule::kw_of => Ok(StmtKind::ForOf(kind, name, iterable, body)),
+                Rule::kw_in => Ok(StmtKind::ForIn(kind, name, iterable, body)),
+                unknown => Err(Error::Unexpected(format!(
+                    "Unexpected for loop: {:?}",
+                    unknown
+                ))),
+            }
+        }
+        Rule::while_loop => {
+            let mut inner_rules = pair.into_inner();
+            let cond = parse_expr(inner_rules.next().unwrap())?;
+            let body = parse_stmt(inner_rules.next().unwrap())?;
+            Ok(StmtKind::While(Box::new(cond), Box::new(body)))
+        }
+        Rule::if_stmt => {
+            let mut inner_rules = pair.into_inner();
+            let cond = parse_expr(inner_rules.next().unwrap())?;
+            let then = parse_stmt(inner_rules.next().unwrap())?;
+            // skip the else keyword
+            let otherwise = match inner_rules.nth(1) {
+                Some(nx_pair) => Some(Box::new(parse_stmt(nx_pair)?)),
+                None => None,
+            };
+            Ok(StmtKind::If(Box::new(cond), Box::new(then), otherwise))
+        }
+        Rule::return_stmt => {
+            // skip the return keyword
+            let val = match pair.into_inner().nth(1) {
+                Some(nx_pair) => Some(Box::new(parse_expr(nx_pair)?)),
+                None => None,
+            };
+            Ok(StmtKind::Return(val))
+        }
+        Rule::function_decl => Ok(StmtKind::Function(Rc::new(parse_function(pair)?))),
+        unknown => Err(Error::Unexpected(format!(
+            "Unexpected statement: {:?}",
+            unknown
+        ))),
+    }
+}
+
+fn parse_expr(pair: Pair<Rule>) -> Result<Expr> {
+    let span = node_span(&pair);
+    match pair.as_rule() {
+        Rule::sum | Rule::factor | Rule::power | Rule::cmp | Rule::equality => {
+            let mut inner_rules = pair.into_inner();
+            let lhs_pair = inner_rules.next().unwrap();
+            let mut lhs = parse_expr(lhs_pair)?;
+            while let (Some(op_pair), Some(rhs_pair)) = (inner_rules.next(), inner_rules.next()) {
+                let infix = parse_infix_op(op_pair)?;
+                let rhs = parse_expr(rhs_pair)?;
+                let span = join(&lhs, rhs.span.end);
+                lhs = ExprKind::Infix(infix, Box::new(lhs), Box::new(rhs)).at(span)
+            }
+            Ok(lhs)
+        }
+        Rule::pipeline => {
+            let mut inner_rules = pair.into_inner();
+            let mut lhs = parse_expr(inner_rules.next().unwrap())?;
+            for body_pair in inner_rules {
+                let body_span = body_pair.as_span();
+                let body = parse_expr(body_pair)?;
+                // like the hack proposal: a body ignoring the previous step is most likely a mistake
+                if !ast::uses_topic(&body) {
+                    let message =
+                        format!("Pipeline body {} doesn't use the topic reference %", body);
+                    return Err(error_at(body_span, message));
+                }
+                let span = join(&lhs, body.span.end);
+                lhs = ExprKind::Pipe(Box::new(lhs), Box::new(body)).at(span)
+            }
+            Ok(lhs)
+        }
+        Rule::unary => {
+            let mut inner_rules = pair.into_inner();
+            match (inner_rules.next(), inner_rules.next()) {
+                (Some(op_pair), Some(rhs_pair)) => {
+                    let prefix = parse_prefix_op(op_pair)?;
+                    let rhs = parse_expr(rhs_pair)?;
+                    Ok(ExprKind::Prefix(prefix, Box::new(rhs)).at(span))
+                }
+                (Some(lhs_pair), None) => parse_expr(lhs_pair),
+                unknown => Err(Error::Unexpected(format!(
+                    "Unexpected unary: {:?}",
+                    unknown
+                ))),
+            }
+        }
+        Rule::postfix => {
+            let mut inner_rules = pair.into_inner();
+            let mut lhs = parse_expr(inner_rules.next().unwrap())?;
+            for nx_pair in inner_rules {
+                let span = join(&lhs, nx_pair.as_span().end());
+                lhs = match nx_pair.as_rule() {
+                    Rule::method_call => {
+                        let mut inner_rules = nx_pair.into_inner();
+                        let name = parse_ident(inner_rules.next().unwrap())?;
+                        let mut params = vec![];
+                        for param_pair in inner_rules {
+                            params.push(parse_expr(param_pair)?)
+                        }
+                        ExprKind::MethodCall(Box::new(lhs), name, params).at(span)
+                    }
+                    Rule::member => {
+                        let name = parse_ident(nx_pair.into_inner().next().unwrap())?;
+                        ExprKind::Member(Box::new(lhs), name).at(span)
+                    }
+                    unknown => {
+                        return Err(Error::Unexpected(format!(
+                            "Unexpected postfix: {:?}",
+                            unknown
+                        )))
+                    }
+                }
+            }
+            Ok(lhs)
+        }
+        _ => Ok(parse_expr_kind(pair)?.at(span)),
+    }
+}
+
+// the nodes which are not made of other nodes of the same level
+fn parse_expr_kind(pair: Pair<Rule>) -> Result<ExprKind> {
+    match pair.as_rule() {
+        Rule::fct_call => {
+            let mut inner_rules = pair.into_inner();
+            // node: we skip the function_start rule and go directly to the ident inside it
+            let name = parse_binding(inner_rules.next().unwrap().into_inner().next().unwrap())?;
+            let mut params = vec![];
+            for nx_pair in inner_rules {
+                params.push(parse_expr(nx_pair)?)
+            }
+            Ok(ExprKind::FctCall(name, params))
+        }
+        Rule::fct_expr => Ok(ExprKind::Function(Rc::new(parse_function(pair)?))),
+        Rule::yield_expr => {
+            let span = pair.as_span();
+            let mut inner_rules = pair.into_inner().skip(1).peekable();
+            let delegate = inner_rules.next_if(|nx_pair| nx_pair.as_rule() == Rule::gen_star);
+            let val = match inner_rules.next() {
+                Some(nx_pair) => Some(Box::new(parse_expr(nx_pair)?)),
+                None => None,
+            };
+            match (delegate, val) {
+                (Some(_), Some(val)) => Ok(ExprKind::YieldStar(val)),
+                (Some(_), None) => Err(error_at(
+                    span,
+                    String::from("Unexpected yield* without an operand"),
+                )),
+                (None, val) => Ok(ExprKind::Yield(val)),
+            }
+        }
+        // skip the await keyword
+        Rule::await_expr => Ok(ExprKind::Await(Box::new(parse_expr(
+            pair.into_inner().nth(1).unwrap(),
+        )?))),
+        Rule::spread => Ok(ExprKind::Spread(Box::new(parse_expr(
+            pair.into_inner().next().unwrap(),
+        )?))),
+        Rule::ident => Ok(ExprKind::Ident(parse_binding(pair)?)),
+        Rule::this => Ok(ExprKind::This),
+        Rule::topic => Ok(ExprKind::Topic),
+        Rule::literal => Ok(ExprKind::Literal(parse_literal_pair(
+            pair.into_inner().next().unwrap(),
+        )?)),
+        Rule::inparens => Ok(ExprKind::Parens(Box::new(parse_expr(
+            pair.into_inner().next().unwrap(),
+        )?))),
+        unknown => Err(Error::Unexpected(format!(
+            "Unexpected expression: {:?}",
+            unknown
+        ))),
+    }
+}
+
+fn parse_literal_pair(pair: Pair<Rule>) -> Result<Literal> {
+    match pair.as_rule() {
+        Rule::object => {
+            let mut props = vec![];
+            for pair in pair.into_inner() {
+                props.push(parse_prop(pair)?)
+            }
+            Ok(Literal::Object(props))
+        }
+        Rule::array => {
+            let mut fields = vec![];
+            for pair in pair.into_inner() {
+                fields.push(parse_expr(pair)?)
+            }
+            Ok(Literal::Array(fields))
+        }
+        Rule::string => Ok(Literal::Str(unescape(
+            pair.into_inner().next().unwrap().as_str(),
+        )?)),
+        Rule::number => {
+            let str = pair.as_str();
+            // integers stay exact, as long as they fit
+            match str.parse::<i64>() {
+                Ok(n) => Ok(Literal::Int(n)),
+                Err(_) => match str.parse() {
+                    Ok(n) => Ok(Literal::Num(n)),
+                    Err(_) => Err(error_at(
+                        pair.as_span(),
+                        format!("Invalid number literal: {}", str),
+                    )),
+                },
+            }
+        }
+        Rule::bigint => {
+            let str = pair.as_str().trim_end_matches('n');
+            match BigInt::parse(str, 10) {
+                Some(n) => Ok(Literal::BigInt(n)),
+                None => Err(error_at(
+                    pair.as_span(),
+                    format!("Invalid BigInt literal: {}", pair.as_str()),
+                )),
+            }
+        }
+        Rule::nan => Ok(Literal::Num(f64::NAN)),
+        Rule::infinity => Ok(Literal::Num(f64::INFINITY)),
+        Rule::boolean => Ok(Literal::Bool(pair.as_str() == "true")),
+        Rule::null => Ok(Literal::Null),
+        Rule::undefined => Ok(Literal::Undefined),
+        unknown => Err(Error::Unexpected(format!(
+            "Unexpected litteral: {:?}",
+            unknown
+        ))),
+    }
+}
+
+fn parse_prop(pair: Pair<Rule>) -> Result<Prop> {
+    match pair.as_rule() {
+        Rule::ident => Ok(Prop::Shorthand(parse_binding(pair)?)),
+        Rule::key_value => {
+            let mut inner_rules = pair.into_inner();
+            let key = parse_prop_key(inner_rules.next().unwrap())?;
+            let val = parse_expr(inner_rules.next().unwrap())?;
+            Ok(Prop::Value(key, val))
+        }
+        Rule::method_prop | Rule::accessor => {
+            let mut def = FunctionDef {
+                name: None,
+                params: vec![],
+                body: vec![],
+                is_generator: false,
+                is_async: false,
+                uses_arguments: false,
+            };
+            let span = pair.as_span();
+            let mut kind = pair.as_rule();
+            let mut key = None;
+            for nx_pair in pair.into_inner() {
+                match nx_pair.as_rule() {
+                    Rule::gen_star => def.is_generator = true,
+                    Rule::kw_async => def.is_async = true,
+                    Rule::kw_get | Rule::kw_set => kind = nx_pair.as_rule(),
+                    Rule::params | Rule::block => parse_function_part(&mut def, nx_pair)?,
+                    _ => key = Some(parse_prop_key(nx_pair)?),
+                }
+            }
+            let key = key.unwrap();
+            if def.is_async && def.is_generator {
+                return Err(error_at(
+                    span,
+                    format!("Async generators are not supported: {}", key),
+                ));
+            }
+            // `{ f() {} }` is a function named f
+            if let PropKey::Name(name) = &key {
+                def.name = Some(name.clone());
+            }
+            match kind {
+                Rule::kw_get if !def.params.is_empty() => Err(error_at(
+                    span,
+                    format!("Getter {} must not have params", key),
+                )),
+                Rule::kw_set if def.params.len() != 1 => Err(error_at(
+                    span,
+                    format!("Setter {} must have exactly one param", key),
+                )),
+                Rule::kw_get => Ok(Prop::Getter(key, Rc::new(def))),
+                Rule::kw_set => Ok(Prop::Setter(key, Rc::new(def))),
+                _ => Ok(Prop::Method(key, Rc::new(def))),
+            }
+        }
+        unknown => Err(Error::Unexpected(format!(
+            "Unexpected property: {:?}",
+            unknown
+        ))),
+    }
+}
+
+fn parse_prop_key(pair: Pair<Rule>) -> Result<PropKey> {
+    match pair.as_rule() {
+        Rule::ident => Ok(PropKey::Name(parse_ident(pair)?)),
+        Rule::string => Ok(PropKey::Name(unescape(
+            pair.into_inner().next().unwrap().as_str(),
+        )?)),
+        Rule::computed_key => Ok(PropKey::Computed(Box::new(parse_expr(
+            pair.into_inner().next().unwrap(),
+        )?))),
+        unknown => Err(Error::Unexpected(format!(
+            "Unexpected property name: {:?}",
+            unknown
+        ))),
+    }
+}
+
+// an identifier, with its `\u` escapes decoded
+fn parse_ident(pair: Pair<Rule>) -> Result<String> {
+    decode_ident(pair.as_str(), node_span(&pair))
+}
+
+fn parse_binding(pair: Pair<Rule>) -> Result<String> {
+    let span = node_span(&pair);
+    check_binding(parse_ident(pair)?, span)
+}
+
+// function declarations and function expressions share the same shape
+fn parse_function(pair: Pair<Rule>) -> Result<FunctionDef> {
+    let span = pair.as_span();
+    let mut def = FunctionDef {
+        name: None,
+        params: vec![],
+        body: vec![],
+        is_generator: false,
+        is_async: false,
+        uses_arguments: false,
+    };
+    for nx_pair in pair.into_inner() {
+        match nx_pair.as_rule() {
+            Rule::kw_function => {}
+            Rule::kw_async => def.is_async = true,
+            Rule::gen_star => def.is_generator = true,
+            Rule::ident => def.name = Some(parse_binding(nx_pair)?),
+            _ => parse_function_part(&mut def, nx_pair)?,
+        }
+    }
+    if def.is_async && def.is_generator {
+        let name = def.name.as_deref().unwrap_or("anonymous");
+        return Err(error_at(
+            span,
+            format!("Async generators are not supported: {}", name),
+        ));
+    }
+    Ok(def)
+}
+
+// the params and body, for both functions and methods
+fn parse_function_part(def: &mut FunctionDef, pair: Pair<Rule>) -> Result<()> {
+    match pair.as_rule() {
+        Rule::params => {
+            for param_pair in pair.into_inner() {
+                let mut inner_rules = param_pair.into_inner();
+                let name = parse_binding(inner_rules.next().unwrap())?;
+                let default = match inner_rules.next() {
+                    Some(nx_pair) => Some(parse_expr(nx_pair)?),
+                    None => None,
+                };
+                def.params.push(Param { name, default })
+            }
+        }
+        // the body comes last, after the params
+        Rule::block => {
+            for stmt_pair in pair.into_inner() {
+                def.body.push(parse_stmt(stmt_pair)?)
+            }
+            def.uses_arguments = ast::reads_arguments(&def.body, &def.params);
+        }
+        unknown => {
+            return Err(Error::Unexpected(format!(
+                "Unexpected function part: {:?}",
+                unknown
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn parse_decl_kind(pair: Pair<Rule>) -> Result<DeclKind> {
+    match pair.as_str() {
+        "var" => Ok(DeclKind::Var),
+        "let" => Ok(DeclKind::Let),
+        "const" => Ok(DeclKind::Const),
+        unknown => Err(Error::Unexpected(format!(
+            "Unexpected declaration: {}",
+            unknown
+        ))),
+    }
+}
+
+fn parse_infix_op(pair: Pair<Rule>) -> Result<InfixOp> {
+    match pair.as_rule() {
+        Rule::add => Ok(InfixOp::Add),
+        Rule::sub => Ok(InfixOp::Sub),
+        Rule::mul => Ok(InfixOp::Mul),
+        Rule::div => Ok(InfixOp::Div),
+        Rule::pow => Ok(InfixOp::Pow),
+        Rule::modulo => Ok(InfixOp::Modulo),
+        Rule::lt => Ok(InfixOp::Lt),
+        Rule::le => Ok(InfixOp::Le),
+        Rule::gt => Ok(InfixOp::Gt),
+        Rule::ge => Ok(InfixOp::Ge),
+        Rule::eq => Ok(InfixOp::Eq),
+        Rule::ne => Ok(InfixOp::Ne),
+        Rule::strict_eq => Ok(InfixOp::StrictEq),
+        Rule::strict_ne => Ok(InfixOp::StrictNe),
+        unknown => Err(Error::Unexpected(format!(
+            "Unexpected infix: {:?}",
+            unknown
+        ))),
+    }
+}
+
+fn parse_prefix_op(pair: Pair<Rule>) -> Result<PrefixOp> {
+    match pair.as_rule() {
+        Rule::add => Ok(PrefixOp::Plus),
+        Rule::sub => Ok(PrefixOp::Minus),
+        unknown => Err(Error::Unexpected(format!(
+            "Unexpected prefix: {:?}",
+            unknown
+        ))),
+    }
+}
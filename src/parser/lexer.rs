@@ -0,0 +1,311 @@
+use crate::parser::ast::Span;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TokenKind {
+    // identifiers and keywords alike: the parser looks at the text. it can contain `\u` escapes
+    Ident,
+    Number,
+    // `123n`
+    BigInt,
+    // with its quotes, and its escapes still in
+    Str,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Comma,
+    Semi,
+    Colon,
+    Dot,
+    Ellipsis,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    StrictEq,
+    StrictNe,
+    // `|>`
+    Pipe,
+    Whitespace,
+    // `// ...` up to the end of the line, or `/* ... */`
+    Comment,
+    // text which can't start a token, an unterminated string, a bad escape...
+    Invalid(&'static str),
+    Eof,
+}
+
+impl TokenKind {
+    // whitespace and comments: kept in the syntax tree, skipped by the parser
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn text<'a>(&self, src: &'a str) -> &'a str {
+        &src[self.span.start..self.span.end]
+    }
+}
+
+// the tokens of the source, whitespace included: they cover every byte of it, in order
+pub struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Lexer { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest().starts_with(c) {
+            true => {
+                self.pos += c.len_utf8();
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn eat_while(&mut self, pred: impl Fn(char) -> bool) -> usize {
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|c| pred(*c)) {
+            self.pos += c.len_utf8();
+        }
+        self.pos - start
+    }
+
+    fn next_kind(&mut self, c: char) -> TokenKind {
+        const PUNCTS: [(&str, TokenKind); 29] = [
+            ("...", TokenKind::Ellipsis),
+            ("===", TokenKind::StrictEq),
+            ("!==", TokenKind::StrictNe),
+            ("==", TokenKind::Eq),
+            ("!=", TokenKind::Ne),
+            ("<=", TokenKind::Le),
+            (">=", TokenKind::Ge),
+            ("|>", TokenKind::Pipe),
+            ("(", TokenKind::LParen),
+            (")", TokenKind::RParen),
+            ("{", TokenKind::LBrace),
+            ("}", TokenKind::RBrace),
+            ("[", TokenKind::LBracket),
+            ("]", TokenKind::RBracket),
+            (",", TokenKind::Comma),
+            (";", TokenKind::Semi),
+            (":", TokenKind::Colon),
+            (".", TokenKind::Dot),
+            ("=", TokenKind::Assign),
+            ("+", TokenKind::Plus),
+            ("-", TokenKind::Minus),
+            ("*", TokenKind::Star),
+            ("/", TokenKind::Slash),
+            ("%", TokenKind::Percent),
+            ("^", TokenKind::Caret),
+            ("<", TokenKind::Lt),
+            (">", TokenKind::Gt),
+            // lone chars which are not tokens on their own
+            ("!", TokenKind::Invalid("unexpected character")),
+            ("|", TokenKind::Invalid("unexpected character")),
+        ];
+        match c {
+            ' ' | '\t' | '\r' | '\n' => {
+                self.eat_while(|c| matches!(c, ' ' | '\t' | '\r' | '\n'));
+                TokenKind::Whitespace
+            }
+            '"' => self.string(),
+            '/' if self.rest().starts_with("//") => {
+                self.eat_while(|c| c != '\n');
+                TokenKind::Comment
+            }
+            '/' if self.rest().starts_with("/*") => match self.rest()[2..].find("*/") {
+                Some(end) => {
+                    self.pos += end + 4;
+                    TokenKind::Comment
+                }
+                None => {
+                    self.pos = self.src.len();
+                    TokenKind::Invalid("unterminated comment")
+                }
+            },
+            '0'..='9' => self.number(),
+            c if is_ident_start(c) || c == '\\' => self.ident(),
+            _ => match PUNCTS
+                .iter()
+                .find(|(punct, _)| self.rest().starts_with(punct))
+            {
+                Some((punct, kind)) => {
+                    self.pos += punct.len();
+                    *kind
+                }
+                None => {
+                    self.pos += c.len_utf8();
+                    TokenKind::Invalid("unexpected character")
+                }
+            },
+        }
+    }
+
+    fn string(&mut self) -> TokenKind {
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                None => return TokenKind::Invalid("unterminated string"),
+                Some('"') => {
+                    self.pos += 1;
+                    return TokenKind::Str;
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    let valid = match self.peek() {
+                        Some('"' | '\'' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => {
+                            self.pos += 1;
+                            true
+                        }
+                        Some('u') => {
+                            self.pos += 1;
+                            self.eat_while_n(4, |c| c.is_ascii_hexdigit()) == 4
+                        }
+                        _ => false,
+                    };
+                    if !valid {
+                        // skip the rest of the string, so the parser can go on after it
+                        self.eat_while(|c| c != '"' && c != '\n');
+                        self.eat('"');
+                        return TokenKind::Invalid("invalid escape sequence in string");
+                    }
+                }
+                Some(c) => self.pos += c.len_utf8(),
+            }
+        }
+    }
+
+    // at most n chars
+    fn eat_while_n(&mut self, n: usize, pred: impl Fn(char) -> bool) -> usize {
+        let mut count = 0;
+        while let Some(c) = self.peek().filter(|c| count < n && pred(*c)) {
+            self.pos += c.len_utf8();
+            count += 1;
+        }
+        count
+    }
+
+    // `0`, `12`, `1.5`, `1.`, `2e-3`, `12n`. a leading `-` is a token of its own
+    fn number(&mut self) -> TokenKind {
+        if !self.eat('0') {
+            self.eat_while(|c| c.is_ascii_digit());
+        }
+        if self.eat('n') {
+            return TokenKind::BigInt;
+        }
+        if self.eat('.') {
+            self.eat_while(|c| c.is_ascii_digit());
+        }
+        let rest = self.rest().as_bytes();
+        if let [b'e' | b'E', ..] = rest {
+            let sign = matches!(rest.get(1), Some(b'+' | b'-')) as usize;
+            if rest.get(1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1 + sign;
+                self.eat_while(|c| c.is_ascii_digit());
+            }
+        }
+        TokenKind::Number
+    }
+
+    fn ident(&mut self) -> TokenKind {
+        let mut first = true;
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    if !self.ident_escape() {
+                        return TokenKind::Invalid("invalid escape sequence in identifier");
+                    }
+                }
+                Some(c) if first && is_ident_start(c) => self.pos += c.len_utf8(),
+                Some(c) if !first && is_ident_part(c) => self.pos += c.len_utf8(),
+                _ => return TokenKind::Ident,
+            }
+            first = false;
+        }
+    }
+
+    // `a` or `\u{61}`. the parser checks the char is valid where it is
+    fn ident_escape(&mut self) -> bool {
+        self.pos += 1;
+        if !self.eat('u') {
+            return false;
+        }
+        if self.eat('{') {
+            return self.eat_while(|c| c.is_ascii_hexdigit()) > 0 && self.eat('}');
+        }
+        self.eat_while_n(4, |c| c.is_ascii_hexdigit()) == 4
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let c = self.peek()?;
+        let start = self.pos;
+        let kind = self.next_kind(c);
+        Some(Token {
+            kind,
+            span: Span {
+                start,
+                end: self.pos,
+            },
+        })
+    }
+}
+
+// javascript uses ID_Start and ID_Continue. their XID variants only leave out the few chars
+// which change under NFKC normalization, like the `ͺ` subscript iota
+pub fn is_ident_start(c: char) -> bool {
+    unicode_ident::is_xid_start(c) || c == '$' || c == '_'
+}
+
+pub fn is_ident_part(c: char) -> bool {
+    unicode_ident::is_xid_continue(c) || matches!(c, '$' | '\u{200C}' | '\u{200D}')
+}
+
+// the tokens the parser works on: no trivia, and an `Eof` token at the end
+pub fn tokenize(src: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Lexer::new(src)
+        .filter(|token| !token.kind.is_trivia())
+        .collect();
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: Span {
+            start: src.len(),
+            end: src.len(),
+        },
+    });
+    tokens
+}
@@ -0,0 +1,997 @@
+// a hand-written parser: the source is split into tokens first, and binary operators are parsed by
+// precedence climbing. it builds the same tree as the pest grammar, which is kept as the reference
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::bigint::BigInt;
+use crate::parser::ast::{
+    self, DeclKind, Expr, ExprKind, FunctionDef, InfixOp, Literal, Param, PrefixOp, Prg, Prop,
+    PropKey, Span, Stmt, StmtKind, CMP, EQUALITY,
+};
+use crate::parser::cst::{NodeKind, RawNode, SyntaxTree};
+use crate::parser::lexer::{self, Lexer, Token, TokenKind};
+use crate::parser::{
+    check_binding, decode_ident, syntax_error, unescape, Error, Result, MAX_NESTING, STMT_KEYWORDS,
+};
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    // statements and expressions being parsed, see `MAX_NESTING`
+    depth: usize,
+    // statements which were parsed one way, which failed, then another way: they are only tried the
+    // first way once, or nested statements could take exponential time
+    backtracked: HashSet<usize>,
+    // the nodes of the syntax tree, when building one, in the order they end
+    nodes: Option<Vec<RawNode>>,
+}
+
+pub(super) fn infix_op(kind: TokenKind) -> Option<(InfixOp, u8)> {
+    let op = match kind {
+        TokenKind::Eq => InfixOp::Eq,
+        TokenKind::Ne => InfixOp::Ne,
+        TokenKind::StrictEq => InfixOp::StrictEq,
+        TokenKind::StrictNe => InfixOp::StrictNe,
+        TokenKind::Lt => InfixOp::Lt,
+        TokenKind::Le => InfixOp::Le,
+        TokenKind::Gt => InfixOp::Gt,
+        TokenKind::Ge => InfixOp::Ge,
+        TokenKind::Plus => InfixOp::Add,
+        TokenKind::Minus => InfixOp::Sub,
+        TokenKind::Star => InfixOp::Mul,
+        TokenKind::Slash => InfixOp::Div,
+        TokenKind::Percent => InfixOp::Modulo,
+        TokenKind::Caret => InfixOp::Pow,
+        _ => return None,
+    };
+    let prec = op.prec();
+    Some((op, prec))
+}
+
+fn span(start: usize, end: usize) -> Span {
+    Span { start, end }
+}
+
+// the target of `target = val;`: a primary expression, maybe followed by members and method calls
+fn is_postfix(expr: &Expr) -> bool {
+    !matches!(
+        expr.kind,
+        ExprKind::Infix(..)
+            | ExprKind::Prefix(..)
+            | ExprKind::Yield(_)
+            | ExprKind::YieldStar(_)
+            | ExprKind::Await(_)
+            | ExprKind::Spread(_)
+            | ExprKind::Pipe(..)
+    )
+}
+
+pub fn parse_prg_partial(src: &str) -> (Prg, Vec<Error>) {
+    Parser::new(src, false).parse_prg_partial()
+}
+
+pub fn parse_cst(src: &str) -> SyntaxTree<'_> {
+    let mut parser = Parser::new(src, true);
+    let (_, errors) = parser.parse_prg_partial();
+    let nodes = parser.nodes.unwrap_or_default();
+    SyntaxTree::build(src, Lexer::new(src).collect(), nodes, errors)
+}
+
+pub fn parse_literal(src: &str) -> Result<Literal> {
+    let mut parser = Parser::new(src, false);
+    let expr = match parser.parse_literal(true)? {
+        Some(expr) => expr,
+        None => return Err(parser.expected("a json value")),
+    };
+    if parser.kind() != TokenKind::Eof {
+        return Err(parser.expected("the end of the json"));
+    }
+    match expr.kind {
+        ExprKind::Literal(literal) => Ok(literal),
+        unknown => Err(syntax_error(
+            expr.span,
+            format!("Unexpected json expr: {}", unknown),
+        )),
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str, with_cst: bool) -> Self {
+        Parser {
+            src,
+            tokens: lexer::tokenize(src),
+            pos: 0,
+            depth: 0,
+            backtracked: HashSet::new(),
+            nodes: with_cst.then(Vec::new),
+        }
+    }
+
+    fn parse_prg_partial(&mut self) -> (Prg, Vec<Error>) {
+        let mut stmts = vec![];
+        let mut errors = vec![];
+        // blocks opened by a statement with an error, and not closed yet: their `}` is not an error
+        let mut depth = 0;
+        while self.kind() != TokenKind::Eof {
+            let start = self.pos;
+            let node_count = self.node_count();
+            if depth > 0 && self.kind() == TokenKind::RBrace {
+                depth -= 1;
+                self.pos += 1;
+                self.finish(NodeKind::Error, start);
+                continue;
+            }
+            match self.parse_stmt() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => {
+                    let err_pos = match &err {
+                        Error::Syntax { span, .. } => self.token_at(span.start).max(start),
+                        Error::Unexpected(_) => start,
+                    };
+                    errors.push(err);
+                    depth += self.resync(start, err_pos);
+                    // the tokens skipped are kept in the syntax tree, in an error node
+                    self.backtrack(node_count);
+                    self.finish(NodeKind::Error, start);
+                }
+            }
+        }
+        (Prg { stmts }, errors)
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes.as_ref().map_or(0, Vec::len)
+    }
+
+    // forget the nodes of an attempt which failed
+    fn backtrack(&mut self, node_count: usize) {
+        if let Some(nodes) = &mut self.nodes {
+            nodes.truncate(node_count);
+        }
+    }
+
+    // a node of the syntax tree, from the token `start` to the last token eaten
+    fn finish(&mut self, kind: NodeKind, start: usize) {
+        if let Some(nodes) = &mut self.nodes {
+            if start < self.pos {
+                nodes.push(RawNode {
+                    kind,
+                    start,
+                    end: self.pos,
+                });
+            }
+        }
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        span(self.tokens[start].span.start, self.end())
+    }
+
+    fn expr(&mut self, kind: ExprKind, start: usize) -> Expr {
+        let node = match &kind {
+            ExprKind::Ident(_) => NodeKind::Name,
+            ExprKind::This => NodeKind::This,
+            ExprKind::Topic => NodeKind::Topic,
+            ExprKind::Literal(Literal::Object(_)) => NodeKind::Object,
+            ExprKind::Literal(Literal::Array(_)) => NodeKind::Array,
+            ExprKind::Literal(_) => NodeKind::Literal,
+            ExprKind::Infix(..) => NodeKind::Binary,
+            ExprKind::Prefix(..) => NodeKind::Prefix,
+            ExprKind::Parens(_) => NodeKind::Parens,
+            ExprKind::FctCall(..) => NodeKind::Call,
+            ExprKind::Member(..) => NodeKind::Member,
+            ExprKind::MethodCall(..) => NodeKind::MethodCall,
+            ExprKind::Function(_) => NodeKind::Function,
+            ExprKind::Yield(_) | ExprKind::YieldStar(_) => NodeKind::Yield,
+            ExprKind::Await(_) => NodeKind::Await,
+            ExprKind::Spread(_) => NodeKind::Spread,
+            ExprKind::Pipe(..) => NodeKind::Pipe,
+        };
+        self.finish(node, start);
+        kind.at(self.span_from(start))
+    }
+
+    fn peek(&self) -> Token {
+        self.peek_at(0)
+    }
+
+    // the last token is always `Eof`
+    fn peek_at(&self, offset: usize) -> Token {
+        let idx = (self.pos + offset).min(self.tokens.len() - 1);
+        self.tokens[idx]
+    }
+
+    fn kind(&self) -> TokenKind {
+        self.peek().kind
+    }
+
+    // the text of an ident token, or nothing
+    fn word_at(&self, offset: usize) -> &'a str {
+        let token = self.peek_at(offset);
+        match token.kind {
+            TokenKind::Ident => token.text(self.src),
+            _ => "",
+        }
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        self.word_at(0) == word
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.peek();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> bool {
+        match self.kind() == kind {
+            true => {
+                self.bump();
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> Result<Token> {
+        match self.kind() == kind {
+            true => Ok(self.bump()),
+            false => Err(self.expected(what)),
+        }
+    }
+
+    fn expect_ident(&mut self, what: &str) -> Result<Token> {
+        self.expect(TokenKind::Ident, what)
+    }
+
+    // `expected a property name, found `(``
+    fn expected(&self, what: &str) -> Error {
+        let token = self.peek();
+        let text = token.text(self.src);
+        // the first line of a string or of a bad token is enough
+        let text = match text.find('\n') {
+            Some(end) => &text[..end],
+            None => text,
+        };
+        let text = match text.char_indices().nth(40) {
+            Some((end, _)) => format!("{}...", &text[..end]),
+            None => text.to_string(),
+        };
+        let message = match token.kind {
+            TokenKind::Eof => format!("expected {}, found the end of the input", what),
+            TokenKind::Invalid(problem) => format!("{}: `{}`", problem, text),
+            _ => format!("expected {}, found `{}`", what, text),
+        };
+        syntax_error(token.span, message)
+    }
+
+    // index of the first token at or after a byte offset
+    fn token_at(&self, offset: usize) -> usize {
+        self.tokens
+            .partition_point(|token| token.span.start < offset)
+    }
+
+    // skip to the next statement boundary after a syntax error in the statement starting at token
+    // `start`: after the next `;` or `}`, or at the next keyword starting a statement. returns
+    // the number of blocks left open before that point
+    fn resync(&mut self, start: usize, err_pos: usize) -> usize {
+        let mut depth = 0;
+        let mut idx = start;
+        while self.tokens[idx].kind != TokenKind::Eof {
+            let token = self.tokens[idx];
+            match token.kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace if depth > 0 => depth -= 1,
+                _ => {}
+            }
+            if idx >= err_pos {
+                if matches!(token.kind, TokenKind::Semi | TokenKind::RBrace) {
+                    self.pos = idx + 1;
+                    return depth;
+                }
+                let is_keyword =
+                    token.kind == TokenKind::Ident && STMT_KEYWORDS.contains(&token.text(self.src));
+                if idx > start && is_keyword {
+                    self.pos = idx;
+                    return depth;
+                }
+            }
+            idx += 1;
+        }
+        self.pos = idx;
+        depth
+    }
+
+    // the parser is recursive: the depth is limited so nesting too deep is an error, not a stack overflow
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_NESTING {
+            let message = format!("Nested too deeply: more than {} levels", MAX_NESTING);
+            return Err(syntax_error(self.peek().span, message));
+        }
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+
+    fn end(&self) -> usize {
+        match self.pos {
+            0 => 0,
+            pos => self.tokens[pos - 1].span.end,
+        }
+    }
+
+    // whether the next token can start an expression, for the optional value of `return` and `yield`
+    fn starts_expr(&self) -> bool {
+        matches!(
+            self.kind(),
+            TokenKind::Ident
+                | TokenKind::Number
+                | TokenKind::BigInt
+                | TokenKind::Str
+                | TokenKind::LParen
+                | TokenKind::LBracket
+                | TokenKind::LBrace
+                | TokenKind::Minus
+                | TokenKind::Plus
+                | TokenKind::Percent
+        )
+    }
+
+    fn binding(&self, token: Token) -> Result<String> {
+        let name = decode_ident(token.text(self.src), token.span)?;
+        check_binding(name, token.span)
+    }
+
+    fn parse_stmt(&mut self) -> Result<Stmt> {
+        self.nested(|parser| {
+            let start = parser.pos;
+            let kind = parser.parse_stmt_kind()?;
+            let node = match &kind {
+                StmtKind::Assign(..) => NodeKind::VarDecl,
+                StmtKind::Reassign(..) => NodeKind::Reassign,
+                StmtKind::SetMember(..) => NodeKind::SetMember,
+                StmtKind::Expr(_) => NodeKind::ExprStmt,
+                StmtKind::Block(_) => NodeKind::Block,
+                StmtKind::ForOf(..) | StmtKind::ForIn(..) => NodeKind::For,
+                StmtKind::While(..) => NodeKind::While,
+                StmtKind::If(..) => NodeKind::If,
+                StmtKind::Return(_) => NodeKind::Return,
+                StmtKind::Function(_) => NodeKind::FunctionDecl,
+            };
+            parser.finish(node, start);
+            Ok(kind.at(parser.span_from(start)))
+        })
+    }
+
+    fn parse_stmt_kind(&mut self) -> Result<StmtKind> {
+        let next = self.peek_at(1).kind;
+        match (self.kind(), self.word_at(0)) {
+            (TokenKind::Ident, "function" | "async") if self.is_function_decl() => {
+                Ok(StmtKind::Function(Rc::new(self.parse_function()?)))
+            }
+            (TokenKind::Ident, "var" | "let" | "const") => self.parse_assignment(),
+            (TokenKind::Ident, "for") if next == TokenKind::LParen => self.parse_for(),
+            (TokenKind::Ident, "while") if next == TokenKind::LParen => {
+                self.bump();
+                self.bump();
+                let cond = self.parse_expr()?;
+                self.expect(TokenKind::RParen, "`)` after the condition")?;
+                let body = self.parse_stmt()?;
+                Ok(StmtKind::While(Box::new(cond), Box::new(body)))
+            }
+            (TokenKind::Ident, "if") if next == TokenKind::LParen => {
+                self.bump();
+                self.bump();
+                let cond = self.parse_expr()?;
+                self.expect(TokenKind::RParen, "`)` after the condition")?;
+                let then = self.parse_stmt()?;
+                let otherwise = match self.is_word("else") {
+                    true => {
+                        self.bump();
+                        Some(Box::new(self.parse_stmt()?))
+                    }
+                    false => None,
+                };
+                Ok(StmtKind::If(Box::new(cond), Box::new(then), otherwise))
+            }
+            (TokenKind::Ident, "return") => {
+                self.bump();
+                let val = match self.starts_expr() {
+                    true => Some(Box::new(self.parse_expr()?)),
+                    false => None,
+                };
+                self.expect(TokenKind::Semi, "`;` after the return statement")?;
+                Ok(StmtKind::Return(val))
+            }
+            (TokenKind::Ident, _) if next == TokenKind::Assign => {
+                let token = self.bump();
+                let name = self.binding(token)?;
+                self.bump();
+                let val = self.parse_expr()?;
+                self.expect(TokenKind::Semi, "`;` after the assignment")?;
+                Ok(StmtKind::Reassign(name, Box::new(val)))
+            }
+            (TokenKind::LBrace, _) => self.parse_block_or_expr(),
+            _ => self.parse_expr_stmt(),
+        }
+    }
+
+    // `[async] function [*] name`
+    fn is_function_decl(&self) -> bool {
+        let mut offset = 0;
+        if self.word_at(offset) == "async" {
+            offset += 1;
+        }
+        if self.word_at(offset) != "function" {
+            return false;
+        }
+        offset += 1;
+        if self.peek_at(offset).kind == TokenKind::Star {
+            offset += 1;
+        }
+        self.peek_at(offset).kind == TokenKind::Ident
+    }
+
+    fn parse_decl_kind(&mut self) -> Result<DeclKind> {
+        let kind = match self.word_at(0) {
+            "var" => DeclKind::Var,
+            "let" => DeclKind::Let,
+            "const" => DeclKind::Const,
+            _ => return Err(self.expected("`var`, `let` or `const`")),
+        };
+        self.bump();
+        Ok(kind)
+    }
+
+    fn parse_assignment(&mut self) -> Result<StmtKind> {
+        let start = self.pos;
+        let kind = self.parse_decl_kind()?;
+        let token = self.expect_ident("a variable name")?;
+        let name = self.binding(token)?;
+        let val = match self.eat(TokenKind::Assign) {
+            true => Some(Box::new(self.parse_expr()?)),
+            false => None,
+        };
+        self.expect(TokenKind::Semi, "`;` after the declaration")?;
+        if val.is_none() && kind == DeclKind::Const {
+            let message = format!("Missing initializer in const declaration: {}", name);
+            return Err(syntax_error(self.span_from(start), message));
+        }
+        Ok(StmtKind::Assign(kind, name, val))
+    }
+
+    fn parse_for(&mut self) -> Result<StmtKind> {
+        self.bump();
+        self.bump();
+        let kind = self.parse_decl_kind()?;
+        let name = self.expect_ident("a variable name")?;
+        let name = self.binding(name)?;
+        let is_of = match self.word_at(0) {
+            "of" => true,
+            "in" => false,
+            _ => return Err(self.expected("`of` or `in`")),
+        };
+        self.bump();
+        let iterable = Box::new(self.parse_expr()?);
+        self.expect(TokenKind::RParen, "`)` after the loop header")?;
+        let body = Box::new(self.parse_stmt()?);
+        match is_of {
+            true => Ok(StmtKind::ForOf(kind, name, iterable, body)),
+            false => Ok(StmtKind::ForIn(kind, name, iterable, body)),
+        }
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<Stmt>> {
+        self.expect(TokenKind::LBrace, "`{`")?;
+        let mut stmts = vec![];
+        while !self.eat(TokenKind::RBrace) {
+            if self.kind() == TokenKind::Eof {
+                return Err(self.expected("`}` to close the block"));
+            }
+            stmts.push(self.parse_stmt()?);
+        }
+        Ok(stmts)
+    }
+
+    // a `{` starting a statement is a block, unless its content are not statements: then it is an
+    // object literal, like in `{a: 1}.a;`
+    fn parse_block_or_expr(&mut self) -> Result<StmtKind> {
+        let start = self.pos;
+        if self.backtracked.contains(&start) {
+            return self.parse_expr_stmt();
+        }
+        let node_count = self.node_count();
+        let block_err = match self.parse_block() {
+            Ok(stmts) => return Ok(StmtKind::Block(stmts)),
+            Err(err) => err,
+        };
+        self.backtracked.insert(start);
+        self.pos = start;
+        self.backtrack(node_count);
+        // a statement starting with an object is rare: the error in the block is the helpful one
+        self.parse_expr_stmt().map_err(|_| block_err)
+    }
+
+    fn parse_expr_stmt(&mut self) -> Result<StmtKind> {
+        let start = self.pos;
+        let target = match self.kind() {
+            // `-1..x = 2;` sets a member of the number -1
+            TokenKind::Minus if !self.backtracked.contains(&start) => {
+                let node_count = self.node_count();
+                match self.parse_postfix(true) {
+                    Ok(target) if self.kind() == TokenKind::Assign => Some(target),
+                    _ => {
+                        self.backtracked.insert(start);
+                        self.pos = start;
+                        self.backtrack(node_count);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let expr = match target {
+            Some(target) => target,
+            None => self.parse_expr()?,
+        };
+        if self.kind() == TokenKind::Assign && is_postfix(&expr) {
+            self.bump();
+            let val = self.parse_expr()?;
+            self.expect(TokenKind::Semi, "`;` after the assignment")?;
+            return match expr.kind {
+                ExprKind::Member(obj, name) => Ok(StmtKind::SetMember(obj, name, Box::new(val))),
+                target => Err(syntax_error(
+                    self.span_from(start),
+                    format!("Invalid assignment target: {}", target),
+                )),
+            };
+        }
+        // the `;` is optional at the very end: `--expr "2 + 3"`
+        if self.kind() != TokenKind::Eof {
+            self.expect(TokenKind::Semi, "`;` after the expression")?;
+        }
+        Ok(StmtKind::Expr(Box::new(expr)))
+    }
+
+    // function declarations and function expressions share the same shape
+    fn parse_function(&mut self) -> Result<FunctionDef> {
+        let start = self.pos;
+        let mut def = new_function();
+        if self.is_word("async") {
+            self.bump();
+            def.is_async = true;
+        }
+        if !self.is_word("function") {
+            return Err(self.expected("`function`"));
+        }
+        self.bump();
+        def.is_generator = self.eat(TokenKind::Star);
+        if self.kind() == TokenKind::Ident {
+            let token = self.bump();
+            def.name = Some(self.binding(token)?);
+        }
+        self.parse_function_rest(&mut def)?;
+        if def.is_async && def.is_generator {
+            let name = def.name.as_deref().unwrap_or("anonymous");
+            let message = format!("Async generators are not supported: {}", name);
+            return Err(syntax_error(self.span_from(start), message));
+        }
+        Ok(def)
+    }
+
+    // the params and the body, for both functions and methods
+    fn parse_function_rest(&mut self, def: &mut FunctionDef) -> Result<()> {
+        self.expect(TokenKind::LParen, "`(` before the params")?;
+        if !self.eat(TokenKind::RParen) {
+            loop {
+                let start = self.pos;
+                let name = self.expect_ident("a param name")?;
+                let name = self.binding(name)?;
+                let default = match self.eat(TokenKind::Assign) {
+                    true => Some(self.parse_expr()?),
+                    false => None,
+                };
+                def.params.push(Param { name, default });
+                self.finish(NodeKind::Param, start);
+                if !self.eat(TokenKind::Comma) {
+                    self.expect(TokenKind::RParen, "`,` or `)` after the param")?;
+                    break;
+                }
+            }
+        }
+        if self.kind() != TokenKind::LBrace {
+            return Err(self.expected("`{` before the function body"));
+        }
+        let start = self.pos;
+        def.body = self.parse_block()?;
+        self.finish(NodeKind::Block, start);
+        def.uses_arguments = ast::reads_arguments(&def.body, &def.params);
+        Ok(())
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr> {
+        self.nested(|parser| match parser.is_word("yield") {
+            true => parser.parse_yield(),
+            false => parser.parse_pipeline(),
+        })
+    }
+
+    fn parse_yield(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        self.bump();
+        let delegate = self.eat(TokenKind::Star);
+        let val = match self.starts_expr() {
+            true => Some(Box::new(self.parse_expr()?)),
+            false => None,
+        };
+        match (delegate, val) {
+            (true, Some(val)) => Ok(self.expr(ExprKind::YieldStar(val), start)),
+            (true, None) => Err(syntax_error(
+                self.span_from(start),
+                String::from("Unexpected yield* without an operand"),
+            )),
+            (false, val) => Ok(self.expr(ExprKind::Yield(val), start)),
+        }
+    }
+
+    // hack style: `x |> f(%, 2)`, the `%` topic is the value of the previous step
+    fn parse_pipeline(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        let mut lhs = self.parse_binary(EQUALITY)?;
+        while self.eat(TokenKind::Pipe) {
+            let body = self.parse_binary(EQUALITY)?;
+            // like the hack proposal: a body ignoring the previous step is most likely a mistake
+            if !ast::uses_topic(&body) {
+                let message = format!("Pipeline body {} doesn't use the topic reference %", body);
+                return Err(syntax_error(body.span, message));
+            }
+            lhs = self.expr(ExprKind::Pipe(Box::new(lhs), Box::new(body)), start)
+        }
+        Ok(lhs)
+    }
+
+    // the operators binding at least as tightly as `min_prec`. all of them are left associative,
+    // except comparisons, which don't chain: `a < b < c` is an error
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Expr> {
+        let start = self.pos;
+        let mut lhs = self.parse_unary()?;
+        let mut compared = false;
+        while let Some((infix, prec)) = infix_op(self.kind()) {
+            if prec < min_prec || (prec == CMP && compared) {
+                break;
+            }
+            compared |= prec == CMP;
+            self.bump();
+            let rhs = self.parse_binary(prec + 1)?;
+            lhs = self.expr(ExprKind::Infix(infix, Box::new(lhs), Box::new(rhs)), start)
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        let prefix = match self.kind() {
+            TokenKind::Minus => PrefixOp::Minus,
+            TokenKind::Plus => PrefixOp::Plus,
+            _ if self.is_word("await") => {
+                self.bump();
+                let val = self.nested(|parser| parser.parse_unary())?;
+                return Ok(self.expr(ExprKind::Await(Box::new(val)), start));
+            }
+            _ => return self.parse_postfix(false),
+        };
+        self.bump();
+        // the operand can't have a sign of its own, except a number: `- -1`
+        let rhs = self.parse_postfix(true)?;
+        Ok(self.expr(ExprKind::Prefix(prefix, Box::new(rhs)), start))
+    }
+
+    // a primary expression followed by members and method calls: `a.b.c(1).d`
+    fn parse_postfix(&mut self, signed: bool) -> Result<Expr> {
+        let start = self.pos;
+        let mut lhs = self.parse_primary(signed)?;
+        while self.eat(TokenKind::Dot) {
+            let name_token = self.expect_ident("a property name after `.`")?;
+            let name = decode_ident(name_token.text(self.src), name_token.span)?;
+            let kind = match self.eat(TokenKind::LParen) {
+                true => {
+                    ExprKind::MethodCall(Box::new(lhs), name, self.parse_args(TokenKind::RParen)?)
+                }
+                false => ExprKind::Member(Box::new(lhs), name),
+            };
+            lhs = self.expr(kind, start);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_primary(&mut self, signed: bool) -> Result<Expr> {
+        if let Some(literal) = self.parse_literal(signed)? {
+            return Ok(literal);
+        }
+        let token = self.peek();
+        let start = self.pos;
+        let kind = match token.kind {
+            TokenKind::LParen => {
+                self.bump();
+                let expr = self.parse_expr()?;
+                self.expect(TokenKind::RParen, "`)`")?;
+                ExprKind::Parens(Box::new(expr))
+            }
+            TokenKind::Percent => {
+                self.bump();
+                ExprKind::Topic
+            }
+            TokenKind::Ident => match token.text(self.src) {
+                "function" => ExprKind::Function(Rc::new(self.parse_function()?)),
+                "async" if self.word_at(1) == "function" => {
+                    ExprKind::Function(Rc::new(self.parse_function()?))
+                }
+                // javascript operators which aren't supported, rather than reserved words
+                op @ ("typeof" | "void" | "delete" | "new") => {
+                    let message = format!("The `{}` operator is not supported", op);
+                    return Err(syntax_error(token.span, message));
+                }
+                // no space before the `(` of a function call
+                _ if self.peek_at(1).kind == TokenKind::LParen
+                    && self.peek_at(1).span.start == token.span.end =>
+                {
+                    let name = self.binding(token)?;
+                    self.bump();
+                    self.bump();
+                    ExprKind::FctCall(name, self.parse_args(TokenKind::RParen)?)
+                }
+                "this" => {
+                    self.bump();
+                    ExprKind::This
+                }
+                _ => {
+                    self.bump();
+                    ExprKind::Ident(self.binding(token)?)
+                }
+            },
+            _ => return Err(self.expected("an expression")),
+        };
+        Ok(self.expr(kind, start))
+    }
+
+    // a literal, if there is one. `signed` allows a `-` before a number
+    fn parse_literal(&mut self, signed: bool) -> Result<Option<Expr>> {
+        let token = self.peek();
+        let start = self.pos;
+        let literal = match token.kind {
+            TokenKind::Minus if signed => {
+                let number = self.peek_at(1);
+                let is_number = matches!(number.kind, TokenKind::Number | TokenKind::BigInt);
+                if !is_number || number.span.start != token.span.end {
+                    return Ok(None);
+                }
+                self.bump();
+                self.bump();
+                let span = span(token.span.start, number.span.end);
+                parse_number(number.kind, &self.src[span.start..span.end], span)?
+            }
+            TokenKind::Number | TokenKind::BigInt => {
+                self.bump();
+                parse_number(token.kind, token.text(self.src), token.span)?
+            }
+            TokenKind::Str => {
+                self.bump();
+                let text = token.text(self.src);
+                Literal::Str(unescape(&text[1..text.len() - 1])?)
+            }
+            TokenKind::LBracket => {
+                self.bump();
+                Literal::Array(self.parse_args(TokenKind::RBracket)?)
+            }
+            TokenKind::LBrace => self.parse_object()?,
+            TokenKind::Ident => {
+                let literal = match token.text(self.src) {
+                    "NaN" => Literal::Num(f64::NAN),
+                    "Infinity" => Literal::Num(f64::INFINITY),
+                    "true" => Literal::Bool(true),
+                    "false" => Literal::Bool(false),
+                    "null" => Literal::Null,
+                    "undefined" => Literal::Undefined,
+                    _ => return Ok(None),
+                };
+                self.bump();
+                literal
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(self.expr(ExprKind::Literal(literal), start)))
+    }
+
+    // call params or array elements, up to the closing token. the opening one is already eaten
+    fn parse_args(&mut self, close: TokenKind) -> Result<Vec<Expr>> {
+        let mut args = vec![];
+        if self.eat(close) {
+            return Ok(args);
+        }
+        loop {
+            let arg = match self.kind() {
+                TokenKind::Ellipsis => {
+                    let start = self.pos;
+                    self.bump();
+                    let val = self.parse_expr()?;
+                    self.expr(ExprKind::Spread(Box::new(val)), start)
+                }
+                _ => self.parse_expr()?,
+            };
+            args.push(arg);
+            if !self.eat(TokenKind::Comma) {
+                let what = match close {
+                    TokenKind::RBracket => "`,` or `]` after the array element",
+                    _ => "`,` or `)` after the argument",
+                };
+                self.expect(close, what)?;
+                return Ok(args);
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Literal> {
+        self.bump();
+        let mut props = vec![];
+        if self.eat(TokenKind::RBrace) {
+            return Ok(Literal::Object(props));
+        }
+        loop {
+            props.push(self.parse_prop()?);
+            // a trailing comma is fine
+            if self.eat(TokenKind::Comma) && self.eat(TokenKind::RBrace) {
+                return Ok(Literal::Object(props));
+            }
+            if self.tokens[self.pos - 1].kind != TokenKind::Comma {
+                self.expect(TokenKind::RBrace, "`,` or `}` after the property")?;
+                return Ok(Literal::Object(props));
+            }
+        }
+    }
+
+    fn starts_prop_key(&self, offset: usize) -> bool {
+        matches!(
+            self.peek_at(offset).kind,
+            TokenKind::Ident | TokenKind::Str | TokenKind::LBracket
+        )
+    }
+
+    fn parse_prop(&mut self) -> Result<Prop> {
+        let start = self.pos;
+        let prop = self.parse_prop_kind()?;
+        let node = match &prop {
+            Prop::Value(..) => NodeKind::PropValue,
+            Prop::Shorthand(_) => NodeKind::PropShorthand,
+            Prop::Method(..) | Prop::Getter(..) | Prop::Setter(..) => NodeKind::PropMethod,
+        };
+        self.finish(node, start);
+        Ok(prop)
+    }
+
+    // `name: val`, `name(a) { ... }`, `get name() { ... }`, `set name(v) { ... }`, or the `name` shorthand
+    fn parse_prop_kind(&mut self) -> Result<Prop> {
+        let start = self.pos;
+        let mut def = new_function();
+        let accessor = match self.word_at(0) {
+            "get" | "set" if self.starts_prop_key(1) => Some(self.bump().text(self.src)),
+            // `async` alone is a regular name: `{ async() {} }`
+            "async" if self.starts_prop_key(1) || self.peek_at(1).kind == TokenKind::Star => {
+                self.bump();
+                def.is_async = true;
+                None
+            }
+            _ => None,
+        };
+        if accessor.is_none() {
+            def.is_generator = self.eat(TokenKind::Star);
+        }
+        let key_token = self.peek();
+        let key = self.parse_prop_key()?;
+        let is_method = accessor.is_some() || def.is_async || def.is_generator;
+        if !is_method && self.eat(TokenKind::Colon) {
+            return Ok(Prop::Value(key, self.parse_expr()?));
+        }
+        if !is_method && self.kind() != TokenKind::LParen {
+            return match key_token.kind {
+                TokenKind::Ident => Ok(Prop::Shorthand(self.binding(key_token)?)),
+                _ => Err(self.expected("`:` after the property name")),
+            };
+        }
+        self.parse_function_rest(&mut def)?;
+        let span = self.span_from(start);
+        if def.is_async && def.is_generator {
+            return Err(syntax_error(
+                span,
+                format!("Async generators are not supported: {}", key),
+            ));
+        }
+        // `{ f() {} }` is a function named f
+        if let PropKey::Name(name) = &key {
+            def.name = Some(name.clone());
+        }
+        match accessor {
+            Some("get") if !def.params.is_empty() => Err(syntax_error(
+                span,
+                format!("Getter {} must not have params", key),
+            )),
+            Some("set") if def.params.len() != 1 => Err(syntax_error(
+                span,
+                format!("Setter {} must have exactly one param", key),
+            )),
+            Some("get") => Ok(Prop::Getter(key, Rc::new(def))),
+            Some(_) => Ok(Prop::Setter(key, Rc::new(def))),
+            None => Ok(Prop::Method(key, Rc::new(def))),
+        }
+    }
+
+    fn parse_prop_key(&mut self) -> Result<PropKey> {
+        let start = self.pos;
+        let key = self.parse_prop_key_kind()?;
+        self.finish(NodeKind::PropKey, start);
+        Ok(key)
+    }
+
+    fn parse_prop_key_kind(&mut self) -> Result<PropKey> {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::Ident => {
+                self.bump();
+                Ok(PropKey::Name(decode_ident(
+                    token.text(self.src),
+                    token.span,
+                )?))
+            }
+            TokenKind::Str => {
+                self.bump();
+                let text = token.text(self.src);
+                Ok(PropKey::Name(unescape(&text[1..text.len() - 1])?))
+            }
+            TokenKind::LBracket => {
+                self.bump();
+                let key = self.parse_expr()?;
+                self.expect(TokenKind::RBracket, "`]` after the computed property name")?;
+                Ok(PropKey::Computed(Box::new(key)))
+            }
+            _ => Err(self.expected("a property")),
+        }
+    }
+}
+
+pub(super) fn new_function() -> FunctionDef {
+    FunctionDef {
+        name: None,
+        params: vec![],
+        body: vec![],
+        is_generator: false,
+        is_async: false,
+        uses_arguments: false,
+    }
+}
+
+// integers stay exact, as long as they fit
+pub(super) fn parse_number(kind: TokenKind, text: &str, span: Span) -> Result<Literal> {
+    if kind == TokenKind::BigInt {
+        return match BigInt::parse(text.trim_end_matches('n'), 10) {
+            Some(n) => Ok(Literal::BigInt(n)),
+            None => Err(syntax_error(
+                span,
+                format!("Invalid BigInt literal: {}", text),
+            )),
+        };
+    }
+    match text.parse::<i64>() {
+        Ok(n) => Ok(Literal::Int(n)),
+        Err(_) => match text.parse() {
+            Ok(n) => Ok(Literal::Num(n)),
+            Err(_) => Err(syntax_error(
+                span,
+                format!("Invalid number literal: {}", text),
+            )),
+        },
+    }
+}
@@ -1,162 +1,870 @@
 use itertools::Itertools;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::mem;
+use std::rc::Rc;
 
-use crate::parser::ast::{Expr, InfixOp, Literal, Prg, Stmt};
-use value::Value;
-
-use anyhow::{bail, Result};
+use crate::bigint::BigInt;
+use crate::parser;
+use crate::parser::ast::{
+    DeclKind, Expr, ExprKind, FunctionDef, InfixOp, Literal, Param, PrefixOp, Prg, Prop, PropKey,
+    Span, StmtKind,
+};
+pub use builtins::{Builtin, BuiltinFuncSign};
+use frame::{Completion, Frame};
+use generator::Generator;
+pub use json::{parse_json, parse_json5, DuplicateKeys};
+use scope::{Scope, ScopeRef};
+use value::{Closure, GeneratorRef, ObjectRef, Property};
+pub use value::{PromiseRef, Value};
 
 mod builtins;
+mod coerce;
+mod frame;
+mod generator;
+mod iter;
+mod jobs;
+mod json;
+mod methods;
+mod promise;
+mod scope;
 mod value;
 
-// #[derive(Debug, thiserror::Error)]
-// pub enum Error {
-//     #[error("invalid infix {0} {1}")]
-//     Infix(Value, Value),
-//     #[error("invalid string infix {0}")]
-//     InfixStr(InfixOp),
-//     #[error("invalid number of params for {0}. Expected {1}, got {2}")]
-//     FunctionParams(String, usize, usize),
-//     #[error("invalid string infix {0}")]
-//     Function(String),
-//     #[error("parse error {0:?}")]
-//     ParseError(#[from] parser::Error),
-//     #[error("invalid string type {0}")]
-//     InvalidStr(Value),
-//     #[error("invalid float type {0}")]
-//     InvalidNum(Value),
-//     #[error("invalid bool type {0}")]
-//     InvalidBool(Value),
-//     #[error("invalid array type {0}")]
-//     InvalidArray(Value),
-//     #[error("invalid hash type {0}")]
-//     InvalidHash(Value),
-//     #[error("invalid file path {0}")]
-//     InvalidFile(String),
-// }
-//
-// pub type Result<T> = std::result::Result<T, Error>;
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Unknown function: {0}")]
+    UnknownFunction(String),
+    #[error("Unknown method: {name} for {typ}{}", available_methods(.available))]
+    UnknownMethod {
+        typ: String,
+        name: String,
+        available: Vec<String>,
+    },
+    // `required <= given <= max`, there is no max for variadic functions
+    #[error(
+        "invalid number of params for {name}. Expected {}, got {given}",
+        expected_params(*.required, *.max)
+    )]
+    Arity {
+        name: String,
+        required: usize,
+        max: Option<usize>,
+        given: usize,
+    },
+    // only in strict mode, otherwise the operands are converted
+    #[error("invalid operand types for {op}: {}", operands.join(", "))]
+    InvalidOperands { op: String, operands: Vec<String> },
+    // a value which isn't of the expected type: not a function, not iterable, ...
+    #[error("{0}")]
+    Type(String),
+    // a variable which doesn't exist, or can't be assigned
+    #[error("{0}")]
+    Reference(String),
+    #[error("{0}")]
+    Range(String),
+    // valid syntax, which can't run where it is, like a `yield` outside of a generator
+    #[error("{0}")]
+    Syntax(String),
+    // a rejected promise no one handled, with the error which rejected it, if any
+    #[error("Uncaught (in promise) {reason}")]
+    Uncaught {
+        reason: String,
+        error: Option<Rc<Error>>,
+    },
+    #[error("await on a promise which is never settled")]
+    NeverSettled,
+    #[error("Step limit exceeded: more than {0} statements run")]
+    StepLimit(u64),
+    #[error("Cannot read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Invalid json in {path}: {source}")]
+    Json { path: String, source: parser::Error },
+    // a tree the runner doesn't expect, which the parser shouldn't produce
+    #[error("{0}")]
+    Unexpected(String),
+    // an error, with the position of the innermost node which raised it
+    #[error("{1}")]
+    At(Span, Box<Error>),
+}
+
+impl Error {
+    // keep the innermost position: it is the most precise one
+    fn at(self, span: Span) -> Error {
+        match self {
+            Error::At(..) => self,
+            err => Error::At(span, Box::new(err)),
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::At(span, _) => Some(*span),
+            // where the promise was rejected
+            Error::Uncaught {
+                error: Some(err), ..
+            } => err.span(),
+            _ => None,
+        }
+    }
+
+    fn uncaught(reason: &Value, error: Option<Rc<Error>>) -> Error {
+        Error::Uncaught {
+            reason: coerce::to_string(reason),
+            error,
+        }
+    }
+
+    // the error itself, without its position
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::At(_, err) => err,
+            err => err,
+        }
+    }
+}
+
+fn expected_params(required: usize, max: Option<usize>) -> String {
+    match max {
+        Some(max) if max == required => required.to_string(),
+        Some(max) => format!("{} to {}", required, max),
+        None => format!("at least {}", required),
+    }
+}
+
+fn available_methods(available: &[String]) -> String {
+    match available.is_empty() {
+        true => String::from(", which has no methods"),
+        false => format!(". Available methods: {}", available.join(", ")),
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
 
 pub struct Runner {
-    vars: HashMap<String, Value>,
-    builtins: HashMap<String, builtins::Builtin>,
+    globals: ScopeRef,
+    // innermost block scope
+    scope: ScopeRef,
+    // scope of the current function, where `var` declarations go
+    var_scope: ScopeRef,
+    builtins: HashMap<String, Builtin>,
+    methods: methods::Methods,
+    jobs: jobs::Jobs,
+    // rejected promises, checked for a handler once everything has run
+    rejections: Vec<PromiseRef>,
+    // no implicit type conversions: mixing types in an operation is an error
+    pub strict: bool,
+    // nested expressions and function bodies being run, see `MAX_DEPTH`
+    depth: usize,
+    steps: u64,
+    // stop after running that many statements, for programs which might never end. no limit by default
+    pub step_limit: Option<u64>,
+}
+
+// past this depth, running one more nested expression or function body could run out of stack
+pub const MAX_DEPTH: usize = 3000;
+
+// `required <= given <= max`. there is no max for variadic functions
+fn check_arity(name: &str, required: usize, max: Option<usize>, given: usize) -> Result<()> {
+    if given >= required && max.is_none_or(|max| given <= max) {
+        return Ok(());
+    }
+    Err(Error::Arity {
+        name: name.to_string(),
+        required,
+        max,
+        given,
+    })
+}
+
+// params with a default can be left out, and a function reading `arguments` takes any number of them
+fn check_call_arity(name: &str, func: &Value, given: usize) -> Result<()> {
+    match func {
+        Value::Function(closure) => {
+            let def = &closure.def;
+            let max = (!def.uses_arguments).then_some(def.params.len());
+            check_arity(name, def.nb_required(), max, given)
+        }
+        Value::Native(native) => {
+            let builtin = &native.builtin;
+            check_arity(
+                name,
+                builtin.nb_args,
+                Some(builtin.nb_args + builtin.nb_optional),
+                given,
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Runner {
     pub fn new() -> Self {
+        // builtin values, like `Object`, live in a scope of their own, above the globals
+        let builtin_scope = Scope::new(None);
+        for (name, val) in builtins::values() {
+            builtin_scope.borrow_mut().declare(&name, val, true);
+        }
+        let globals = Scope::new(Some(builtin_scope));
         Runner {
-            vars: HashMap::new(),
+            scope: globals.clone(),
+            var_scope: globals.clone(),
+            globals,
             builtins: builtins::new(),
+            methods: methods::new(),
+            jobs: jobs::Jobs::default(),
+            rejections: vec![],
+            strict: false,
+            depth: 0,
+            steps: 0,
+            step_limit: None,
+        }
+    }
+
+    // a host function, called from programs like the builtins (it replaces the builtin of the
+    // same name). to do something asynchronous, it returns a pending promise, see `new_promise`
+    pub fn register_builtin(&mut self, name: &str, builtin: Builtin) {
+        self.builtins.insert(name.to_string(), builtin);
+    }
+
+    // returns the completion value of the program: the value of the last expression statement run.
+    // the program is done once all its jobs (promise callbacks, timers) have run too
+    pub fn run_prg(&mut self, prg: &Prg) -> Result<Value> {
+        if let Some(StmtKind::Expr(expr)) = prg.stmts.first().map(|stmt| &stmt.kind) {
+            if let ExprKind::Literal(Literal::Str(directive)) = &expr.kind {
+                self.strict |= directive == "use strict";
+            }
+        }
+        // awaits at the top level run the jobs until the awaited promise is settled
+        let stmts = generator::lower_stmts(&prg.stmts);
+        let mut frame = Frame::new(self.globals.clone(), self.globals.clone(), true);
+        let mut sent = Value::Undefined;
+        loop {
+            match self.run_frame(&stmts, &mut frame, sent)? {
+                Completion::Await(val) => {
+                    let promise = self.promise_of(val);
+                    sent = match self.run_jobs_until(&promise)? {
+                        promise::Settled::Fulfilled(val) => val,
+                        promise::Settled::Rejected(reason, error) => {
+                            return Err(Error::uncaught(&reason, error))
+                        }
+                    }
+                }
+                Completion::Yield(_) => {
+                    return Err(Error::Syntax(String::from(
+                        "yield is only valid in generator functions",
+                    )))
+                }
+                Completion::Normal | Completion::Return(_) => break,
+            }
+        }
+        self.run_jobs()?;
+        self.check_rejections()?;
+        Ok(frame.completion)
+    }
+
+    pub fn print_vars(&self) {
+        let globals = self.globals.borrow();
+        // leave out the temporaries of top level awaits, see `generator::lower`
+        let names: Vec<&String> = globals
+            .vars
+            .keys()
+            .filter(|name| !name.starts_with('@'))
+            .sorted()
+            .collect();
+        println!("vars ({}):", names.len());
+        for name in names {
+            println!("  {} = {}", name, globals.vars[name]);
+        }
+    }
+
+    // `var` goes to the enclosing function (or global) scope, `let` and `const` stay in the current block
+    fn declare(&mut self, kind: DeclKind, name: &str, val: Value) {
+        match kind {
+            DeclKind::Var => self.var_scope.borrow_mut().declare(name, val, false),
+            DeclKind::Let => self.scope.borrow_mut().declare(name, val, false),
+            DeclKind::Const => self.scope.borrow_mut().declare(name, val, true),
+        }
+    }
+
+    fn new_closure(&mut self, def: &Rc<FunctionDef>) -> Value {
+        // generator and async bodies are rewritten once, when the function is created
+        let def = match def.is_generator || def.is_async {
+            true => Rc::new(generator::lower(def)),
+            false => def.clone(),
+        };
+        Value::Function(Closure {
+            def,
+            scope: self.scope.clone(),
+        })
+    }
+
+    // `this` is the object a method is called on, undefined for plain calls.
+    // the number of params is checked by the caller: callbacks called by the runner itself, like
+    // promise handlers, can ignore their params, as in javascript
+    pub fn call_function(
+        &mut self,
+        closure: &Closure,
+        this: &Value,
+        params: &[Value],
+    ) -> Result<Value> {
+        let def = &closure.def;
+        let scope = Scope::new(Some(closure.scope.clone()));
+        // `this` is a reserved word, so it can't clash with a param
+        scope.borrow_mut().declare("this", this.clone(), true);
+        if def.uses_arguments {
+            scope
+                .borrow_mut()
+                .declare("arguments", Value::Array(params.to_vec()), false);
+        }
+        // defaults are evaluated in the function scope, so they can use the params before them
+        let outer_scope = mem::replace(&mut self.scope, scope.clone());
+        let outer_var_scope = mem::replace(&mut self.var_scope, scope.clone());
+        let res = self.declare_params(&def.params, params);
+        self.scope = outer_scope;
+        self.var_scope = outer_var_scope;
+        res?;
+        let mut frame = Frame::new(scope.clone(), scope, def.is_generator || def.is_async);
+        if def.is_generator {
+            let gen = Generator::new(def.clone(), frame);
+            return Ok(Value::Generator(GeneratorRef(Rc::new(RefCell::new(gen)))));
+        }
+        if def.is_async {
+            return Ok(self.call_async(def.clone(), frame));
+        }
+        match self.run_frame(&def.body, &mut frame, Value::Undefined)? {
+            Completion::Return(val) => Ok(val),
+            _ => Ok(Value::Undefined),
         }
     }
 
-    pub fn run_prg(&mut self, prg: &Prg) -> Result<()> {
-        for stmt in &prg.stmts {
-            self.run_stmt(stmt)?
+    fn declare_params(&mut self, params: &[Param], vals: &[Value]) -> Result<()> {
+        for (idx, param) in params.iter().enumerate() {
+            let val = match (vals.get(idx), &param.default) {
+                (Some(Value::Undefined) | None, Some(default)) => self.eval_expr(default)?,
+                (Some(val), _) => val.clone(),
+                (None, None) => Value::Undefined,
+            };
+            self.scope.borrow_mut().declare(&param.name, val, false);
         }
         Ok(())
     }
 
-    pub fn print_vars(&self) {
-        println!("vars ({}):", self.vars.len());
-        for name in self.vars.keys().sorted() {
-            println!("  {} = {}", name, self.vars[name]);
+    pub fn call_value(&mut self, func: &Value, params: &[Value]) -> Result<Value> {
+        self.call_method(func, &Value::Undefined, params)
+    }
+
+    pub fn call_method(&mut self, func: &Value, this: &Value, params: &[Value]) -> Result<Value> {
+        match func {
+            Value::Function(closure) => self.call_function(closure, this, params),
+            Value::Native(native) => {
+                // like in javascript, missing params are undefined
+                let mut params = params.to_vec();
+                if params.len() < native.builtin.nb_args {
+                    params.resize(native.builtin.nb_args, Value::Undefined);
+                }
+                (native.builtin.func)(self, &params)
+            }
+            _ => Err(Error::Type(format!("{} is not a function", func))),
         }
     }
 
-    fn run_stmt(&mut self, stmt: &Stmt) -> Result<()> {
-        match stmt {
-            Stmt::Assign(name, expr) => {
-                let val = self.eval_expr(expr)?;
-                self.vars.insert(name.clone(), val);
+    // evaluate call params or array elements, expanding spreads
+    fn eval_args(&mut self, exprs: &[Expr]) -> Result<Vec<Value>> {
+        let mut vals = vec![];
+        for expr in exprs.iter() {
+            match &expr.kind {
+                ExprKind::Spread(iterable_expr) => {
+                    let iterable = self.eval_expr(iterable_expr)?;
+                    let mut values = iter::values(&iterable)?;
+                    while let Some(val) = values.next(self)? {
+                        vals.push(val)
+                    }
+                }
+                _ => vals.push(self.eval_expr(expr)?),
             }
         }
-        Ok(())
+        Ok(vals)
     }
 
     fn eval_expr(&mut self, expr: &Expr) -> Result<Value> {
+        self.nested(|runner| runner.eval_expr_kind(&expr.kind))
+            .map_err(|err| err.at(expr.span))
+    }
+
+    // expressions and function bodies run recursively: the depth is limited so a runaway
+    // recursion is an error, not a stack overflow
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Error::Range(String::from(
+                "Maximum call stack size exceeded",
+            )));
+        }
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+
+    // called before running each statement
+    fn count_step(&mut self) -> Result<()> {
+        self.steps += 1;
+        match self.step_limit {
+            Some(limit) if self.steps > limit => Err(Error::StepLimit(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    fn eval_expr_kind(&mut self, expr: &ExprKind) -> Result<Value> {
         match expr {
-            Expr::Literal(literal) => self.eval_literal(literal),
-            Expr::Infix(infix, lhs_expr, rhs_expr) => {
+            ExprKind::Literal(literal) => self.eval_literal(literal),
+            ExprKind::Infix(infix, lhs_expr, rhs_expr) => {
                 let lhs = self.eval_expr(lhs_expr)?;
                 let rhs = self.eval_expr(rhs_expr)?;
                 self.eval_infix(infix, lhs, rhs)
             }
-            Expr::Ident(var) => Ok(self.vars.get(var).unwrap().clone()),
-            Expr::Parens(expr2) => self.eval_expr(expr2),
-            Expr::Prefix(_prefix, _lhs) => Ok(Value::Str(String::from("TODO"))),
-            Expr::FctCall(name, params_expr) => {
-                let mut params = vec![];
-                for param_expr in params_expr.iter() {
-                    params.push(self.eval_expr(param_expr)?)
+            ExprKind::Ident(var) => match scope::lookup(&self.scope, var) {
+                Some(val) => Ok(val),
+                None => Err(Error::Reference(format!("{} is not defined", var))),
+            },
+            // outside of a function, `this` is undefined
+            ExprKind::This => Ok(scope::lookup(&self.scope, "this").unwrap_or(Value::Undefined)),
+            ExprKind::Parens(expr2) => self.eval_expr(expr2),
+            // the body runs in a scope of its own, where `%` is the value of the lhs
+            ExprKind::Pipe(lhs_expr, body) => {
+                let lhs = self.eval_expr(lhs_expr)?;
+                let outer = self.scope.clone();
+                self.scope = Scope::new(Some(outer.clone()));
+                self.scope.borrow_mut().declare("%", lhs, true);
+                let res = self.eval_expr(body);
+                self.scope = outer;
+                res
+            }
+            ExprKind::Topic => match scope::lookup(&self.scope, "%") {
+                Some(val) => Ok(val),
+                None => Err(Error::Reference(String::from(
+                    "The topic reference % is only valid in the body of a pipeline",
+                ))),
+            },
+            ExprKind::Prefix(prefix, rhs_expr) => {
+                let rhs = self.eval_expr(rhs_expr)?;
+                self.eval_prefix(prefix, rhs)
+            }
+            ExprKind::FctCall(name, params_expr) => {
+                let params = self.eval_args(params_expr)?;
+                if let Some(func) = scope::lookup(&self.scope, name) {
+                    check_call_arity(name, &func, params.len())?;
+                    return self.call_value(&func, &params);
                 }
                 match self.builtins.get(name) {
                     Some(builtin) => {
-                        let nb_args = builtin.nb_args;
+                        let max = builtin.nb_args + builtin.nb_optional;
+                        check_arity(name, builtin.nb_args, Some(max), params.len())?;
                         let func = builtin.func;
-                        if params.len() == nb_args {
-                            func(self, &params)
-                        } else {
-                            bail!(
-                                "invalid number of params for {}. Expected {}, got {}",
-                                name,
-                                nb_args,
-                                params.len()
-                            );
-                        }
+                        func(self, &params)
                     }
-                    _ => bail!("Unexpected function: {}", name),
+                    _ => Err(Error::UnknownFunction(name.clone())),
                 }
             }
+            ExprKind::Member(obj_expr, name) => {
+                let obj = self.eval_expr(obj_expr)?;
+                self.get_prop(&obj, name)
+            }
+            ExprKind::MethodCall(obj_expr, name, params_expr) => {
+                let obj = self.eval_expr(obj_expr)?;
+                let params = self.eval_args(params_expr)?;
+                if let Value::Object(props) = &obj {
+                    if props.0.borrow().get(name).is_some() {
+                        let func = self.get_prop(&obj, name)?;
+                        check_call_arity(name, &func, params.len())?;
+                        return self.call_method(&func, &obj, &params);
+                    }
+                }
+                let method = self.lookup_method(&obj, name)?;
+                if method.getter {
+                    let msg = format!(
+                        "{} is a property of {}, not a method",
+                        name,
+                        value::type_of(&obj)
+                    );
+                    return Err(Error::Type(msg));
+                }
+                let full_name = format!("{}.{}", value::type_of(&obj), name);
+                let max = method.nb_args + method.nb_optional;
+                check_arity(&full_name, method.nb_args, Some(max), params.len())?;
+                let func = method.func;
+                func(self, &obj, &params)
+            }
+            ExprKind::Function(def) => Ok(self.new_closure(def)),
+            ExprKind::Yield(_) | ExprKind::YieldStar(_) => Err(Error::Syntax(String::from(
+                "yield is only valid in generator functions",
+            ))),
+            ExprKind::Await(_) => Err(Error::Syntax(String::from(
+                "await is only valid in async functions and at the top level",
+            ))),
+            ExprKind::Spread(_) => Err(Error::Syntax(String::from(
+                "spread is only valid in arrays and function params",
+            ))),
         }
     }
 
-    fn eval_literal(&mut self, literal: &Literal) -> Result<Value> {
-        match literal {
-            Literal::Array(elts) => {
-                let mut parts = vec![];
-                for elt in elts.iter() {
-                    parts.push(self.eval_expr(elt)?);
+    // `obj.name`: an object property, calling its getter if it has one, or a getter method of the type
+    pub fn get_prop(&mut self, obj: &Value, name: &str) -> Result<Value> {
+        if let Value::Object(props) = obj {
+            let prop = props.0.borrow().get(name).cloned();
+            return match prop {
+                Some(Property::Data(val)) => Ok(val),
+                Some(Property::Accessor(Some(getter), _)) => self.call_method(&getter, obj, &[]),
+                Some(Property::Accessor(None, _)) | None => Ok(Value::Undefined),
+            };
+        }
+        let method = self.lookup_method(obj, name)?;
+        if !method.getter {
+            let msg = format!(
+                "{} is a method of {}, not a property",
+                name,
+                value::type_of(obj)
+            );
+            return Err(Error::Type(msg));
+        }
+        let func = method.func;
+        func(self, obj, &[])
+    }
+
+    // `obj.name = val`, calling the setter if the property has one
+    fn set_prop(&mut self, obj: &Value, name: &str, val: Value) -> Result<()> {
+        let props = match obj {
+            Value::Object(props) => props,
+            _ => {
+                return Err(Error::Type(format!(
+                    "Cannot set property {} of {}",
+                    name,
+                    value::type_of(obj)
+                )))
+            }
+        };
+        let prop = props.0.borrow().get(name).cloned();
+        match prop {
+            Some(Property::Accessor(Some(_), None)) if self.strict => {
+                return Err(Error::Type(format!(
+                    "Cannot set property {}, which only has a getter",
+                    name
+                )))
+            }
+            Some(Property::Accessor(_, Some(setter))) => {
+                self.call_method(&setter, obj, &[val])?;
+            }
+            // like javascript, assigning to a getter only property does nothing
+            Some(Property::Accessor(_, None)) => {}
+            // like javascript, writing to a frozen object only fails in strict mode
+            Some(Property::Data(_)) if props.0.borrow().frozen => {
+                if self.strict {
+                    let msg = format!(
+                        "Cannot assign to read only property {} of a frozen object",
+                        name
+                    );
+                    return Err(Error::Type(msg));
                 }
-                Ok(Value::Array(parts))
             }
-            Literal::Object(props) => {
-                let mut parts = vec![];
-                for (name, val) in props.iter() {
-                    parts.push((name.clone(), self.eval_expr(val)?))
+            None if props.0.borrow().frozen => {
+                if self.strict {
+                    let msg = format!("Cannot add property {}, object is not extensible", name);
+                    return Err(Error::Type(msg));
                 }
-                Ok(Value::Object(parts))
             }
+            Some(Property::Data(_)) | None => {
+                props.0.borrow_mut().define(name, Property::Data(val))
+            }
+        }
+        Ok(())
+    }
+
+    fn lookup_method(&self, obj: &Value, name: &str) -> Result<&methods::Method> {
+        let typ = value::type_of(obj);
+        let type_methods = self.methods.get(&typ);
+        match type_methods.and_then(|type_methods| type_methods.get(name)) {
+            Some(method) => Ok(method),
+            None => {
+                let available = type_methods
+                    .map(|type_methods| type_methods.keys().sorted().cloned().collect())
+                    .unwrap_or_default();
+                Err(Error::UnknownMethod {
+                    typ: typ.to_string(),
+                    name: name.to_string(),
+                    available,
+                })
+            }
+        }
+    }
+
+    fn eval_literal(&mut self, literal: &Literal) -> Result<Value> {
+        match literal {
+            Literal::Array(elts) => Ok(Value::Array(self.eval_args(elts)?)),
+            Literal::Object(props) => self.eval_object(props),
             Literal::Str(s) => Ok(Value::Str(s.clone())),
+            Literal::Int(n) => Ok(Value::Int(*n)),
+            Literal::BigInt(n) => Ok(Value::BigInt(n.clone())),
             Literal::Num(n) => Ok(Value::Num(*n)),
             Literal::Bool(b) => Ok(Value::Bool(*b)),
             Literal::Null => Ok(Value::Null),
+            Literal::Undefined => Ok(Value::Undefined),
+        }
+    }
+
+    fn eval_object(&mut self, props: &[Prop]) -> Result<Value> {
+        let obj = ObjectRef::new(vec![]);
+        for prop in props {
+            match prop {
+                Prop::Value(key, val) => {
+                    let name = self.eval_prop_key(key)?;
+                    let val = self.eval_expr(val)?;
+                    obj.0.borrow_mut().define(&name, Property::Data(val));
+                }
+                Prop::Shorthand(name) => {
+                    let val = self.eval_expr_kind(&ExprKind::Ident(name.clone()))?;
+                    obj.0.borrow_mut().define(name, Property::Data(val));
+                }
+                Prop::Method(key, def) => {
+                    let name = self.eval_prop_key(key)?;
+                    let func = self.new_closure(def);
+                    obj.0.borrow_mut().define(&name, Property::Data(func));
+                }
+                // a getter and a setter of the same name make up a single property
+                Prop::Getter(key, def) | Prop::Setter(key, def) => {
+                    let name = self.eval_prop_key(key)?;
+                    let func = Some(self.new_closure(def));
+                    let mut obj = obj.0.borrow_mut();
+                    let (getter, setter) = match obj.get(&name) {
+                        Some(Property::Accessor(getter, setter)) => {
+                            (getter.clone(), setter.clone())
+                        }
+                        _ => (None, None),
+                    };
+                    let accessor = match prop {
+                        Prop::Getter(..) => Property::Accessor(func, setter),
+                        _ => Property::Accessor(getter, func),
+                    };
+                    obj.define(&name, accessor);
+                }
+            }
+        }
+        Ok(Value::Object(obj))
+    }
+
+    // computed keys are converted to strings, like any property name
+    fn eval_prop_key(&mut self, key: &PropKey) -> Result<String> {
+        match key {
+            PropKey::Name(name) => Ok(name.clone()),
+            PropKey::Computed(expr) => {
+                let key = self.eval_expr(expr)?;
+                let key = self.coerce_to_primitive(key)?;
+                Ok(coerce::to_string(&key))
+            }
+        }
+    }
+
+    fn eval_prefix(&mut self, prefix: &PrefixOp, rhs: Value) -> Result<Value> {
+        match (prefix, rhs) {
+            (PrefixOp::Minus, Value::Int(0)) => Ok(Value::Num(-0.0)),
+            (PrefixOp::Minus, Value::Int(n)) => match n.checked_neg() {
+                Some(n) => Ok(Value::Int(n)),
+                None => Ok(Value::Num(-(n as f64))),
+            },
+            (PrefixOp::Minus, Value::Num(n)) => Ok(Value::Num(-n)),
+            (PrefixOp::Minus, Value::BigInt(n)) => Ok(Value::BigInt(n.neg())),
+            (PrefixOp::Plus, rhs @ (Value::Int(_) | Value::Num(_))) => Ok(rhs),
+            (PrefixOp::Plus, Value::BigInt(_)) => Err(Error::Type(String::from(
+                "Cannot convert a BigInt value to a number",
+            ))),
+            (prefix, rhs) if self.strict => Err(Error::InvalidOperands {
+                op: prefix.to_string(),
+                operands: vec![rhs.to_string()],
+            }),
+            (prefix, rhs) => {
+                let rhs = self.coerce_to_primitive(rhs)?;
+                self.eval_prefix(prefix, coerce::to_number(&rhs))
+            }
         }
     }
 
     fn eval_infix(&mut self, infix: &InfixOp, lhs: Value, rhs: Value) -> Result<Value> {
+        match infix {
+            // without conversions, == is ===
+            InfixOp::Eq if !self.strict => return Ok(Value::Bool(self.loose_eq(&lhs, &rhs)?)),
+            InfixOp::Ne if !self.strict => return Ok(Value::Bool(!self.loose_eq(&lhs, &rhs)?)),
+            InfixOp::Eq | InfixOp::StrictEq => {
+                return Ok(Value::Bool(coerce::strict_eq(&lhs, &rhs)))
+            }
+            InfixOp::Ne | InfixOp::StrictNe => {
+                return Ok(Value::Bool(!coerce::strict_eq(&lhs, &rhs)))
+            }
+            _ => {}
+        }
         match (lhs, rhs) {
-            (Value::Num(v1), Value::Num(v2)) => Ok(Value::Num(self.eval_infix_num(infix, v1, v2))),
-            (Value::Str(v1), Value::Str(v2)) => Ok(Value::Str(self.eval_infix_str(infix, v1, v2)?)),
-            (lhs, rhs) => bail!("Unexpected infix: {} {}", lhs, rhs),
+            (Value::Int(v1), Value::Int(v2)) => Ok(self.eval_infix_int(infix, v1, v2)),
+            (lhs @ (Value::Int(_) | Value::Num(_)), rhs @ (Value::Int(_) | Value::Num(_))) => {
+                let v1 = value::as_f64(&lhs)?;
+                let v2 = value::as_f64(&rhs)?;
+                Ok(self.eval_infix_num(infix, v1, v2))
+            }
+            (Value::BigInt(v1), Value::BigInt(v2)) => eval_infix_bigint(infix, &v1, &v2),
+            (Value::Str(v1), Value::Str(v2)) => self.eval_infix_str(infix, v1, v2),
+            (lhs, rhs) if self.strict => Err(Error::InvalidOperands {
+                op: infix.to_string(),
+                operands: vec![lhs.to_string(), rhs.to_string()],
+            }),
+            // as in javascript, BigInts and numbers only mix in comparisons: exact ones for integers
+            (Value::BigInt(v1), Value::Int(v2)) if is_cmp(infix) => {
+                eval_infix_bigint(infix, &v1, &BigInt::from_i64(v2))
+            }
+            (Value::Int(v1), Value::BigInt(v2)) if is_cmp(infix) => {
+                eval_infix_bigint(infix, &BigInt::from_i64(v1), &v2)
+            }
+            (Value::BigInt(v1), Value::Num(v2)) if is_cmp(infix) => {
+                Ok(self.eval_infix_num(infix, v1.to_f64(), v2))
+            }
+            (Value::Num(v1), Value::BigInt(v2)) if is_cmp(infix) => {
+                Ok(self.eval_infix_num(infix, v1, v2.to_f64()))
+            }
+            (Value::BigInt(_), Value::Int(_) | Value::Num(_))
+            | (Value::Int(_) | Value::Num(_), Value::BigInt(_)) => Err(Error::Type(String::from(
+                "Cannot mix BigInt and other types, use explicit conversions",
+            ))),
+            (lhs, rhs) => self.eval_infix_coerce(infix, lhs, rhs),
+        }
+    }
+
+    // mixed types: `+` concatenates as soon as one side is a string, other operators work on numbers
+    fn eval_infix_coerce(&mut self, infix: &InfixOp, lhs: Value, rhs: Value) -> Result<Value> {
+        let lhs = self.coerce_to_primitive(lhs)?;
+        let rhs = self.coerce_to_primitive(rhs)?;
+        match (&lhs, &rhs) {
+            (Value::Str(_), _) | (_, Value::Str(_)) if *infix == InfixOp::Add => Ok(Value::Str(
+                coerce::to_string(&lhs) + &coerce::to_string(&rhs),
+            )),
+            (Value::Str(_), Value::Str(_)) => self.eval_infix(infix, lhs, rhs),
+            _ => self.eval_infix(infix, coerce::to_number(&lhs), coerce::to_number(&rhs)),
+        }
+    }
+
+    // stay exact when possible, fall back to floats on overflow or for fractions
+    fn eval_infix_int(&mut self, infix: &InfixOp, v1: i64, v2: i64) -> Value {
+        let res = match infix {
+            InfixOp::Add => v1.checked_add(v2),
+            InfixOp::Sub => v1.checked_sub(v2),
+            InfixOp::Mul => v1.checked_mul(v2),
+            InfixOp::Div => match v1.checked_rem(v2) {
+                Some(0) => v1.checked_div(v2),
+                _ => None,
+            },
+            InfixOp::Pow => u32::try_from(v2).ok().and_then(|v2| v1.checked_pow(v2)),
+            InfixOp::Modulo => v1.checked_rem(v2),
+            InfixOp::Lt => return Value::Bool(v1 < v2),
+            InfixOp::Le => return Value::Bool(v1 <= v2),
+            InfixOp::Gt => return Value::Bool(v1 > v2),
+            InfixOp::Ge => return Value::Bool(v1 >= v2),
+            InfixOp::Eq | InfixOp::StrictEq => return Value::Bool(v1 == v2),
+            InfixOp::Ne | InfixOp::StrictNe => return Value::Bool(v1 != v2),
+        };
+        match res {
+            // javascript has a negative zero, which integers don't: `0 * -1` is `-0`
+            Some(0) if is_negative_zero(infix, v1, v2) => Value::Num(-0.0),
+            Some(n) => Value::Int(n),
+            None => self.eval_infix_num(infix, v1 as f64, v2 as f64),
         }
     }
 
-    fn eval_infix_num(&mut self, infix: &InfixOp, v1: f64, v2: f64) -> f64 {
+    fn eval_infix_num(&mut self, infix: &InfixOp, v1: f64, v2: f64) -> Value {
         match infix {
-            InfixOp::Add => v1 + v2,
-            InfixOp::Sub => v1 - v2,
-            InfixOp::Mul => v1 * v2,
-            InfixOp::Div => v1 / v2,
-            InfixOp::Pow => f32::powi(v1 as f32, v2 as i32) as f64,
-            InfixOp::Modulo => v1 % v2,
+            InfixOp::Add => Value::Num(v1 + v2),
+            InfixOp::Sub => Value::Num(v1 - v2),
+            InfixOp::Mul => Value::Num(v1 * v2),
+            InfixOp::Div => Value::Num(v1 / v2),
+            InfixOp::Pow => Value::Num(v1.powf(v2)),
+            InfixOp::Modulo => Value::Num(v1 % v2),
+            InfixOp::Lt => Value::Bool(v1 < v2),
+            InfixOp::Le => Value::Bool(v1 <= v2),
+            InfixOp::Gt => Value::Bool(v1 > v2),
+            InfixOp::Ge => Value::Bool(v1 >= v2),
+            InfixOp::Eq | InfixOp::StrictEq => Value::Bool(v1 == v2),
+            InfixOp::Ne | InfixOp::StrictNe => Value::Bool(v1 != v2),
         }
     }
 
-    fn eval_infix_str(&mut self, infix: &InfixOp, v1: String, v2: String) -> Result<String> {
+    fn eval_infix_str(&mut self, infix: &InfixOp, v1: String, v2: String) -> Result<Value> {
         match infix {
-            InfixOp::Add => Ok(format!("{}{}", v1, v2)),
-            unknown => bail!("Unexpected string infix: {}", unknown),
+            InfixOp::Add => Ok(Value::Str(format!("{}{}", v1, v2))),
+            InfixOp::Lt => Ok(Value::Bool(v1 < v2)),
+            InfixOp::Le => Ok(Value::Bool(v1 <= v2)),
+            InfixOp::Gt => Ok(Value::Bool(v1 > v2)),
+            InfixOp::Ge => Ok(Value::Bool(v1 >= v2)),
+            unknown if self.strict => Err(Error::InvalidOperands {
+                op: unknown.to_string(),
+                operands: vec![Value::Str(v1).to_string(), Value::Str(v2).to_string()],
+            }),
+            _ => {
+                let v1 = coerce::to_number(&Value::Str(v1));
+                let v2 = coerce::to_number(&Value::Str(v2));
+                self.eval_infix(infix, v1, v2)
+            }
         }
     }
 }
+
+// when an integer operation giving 0 gives -0 with floats
+fn is_negative_zero(infix: &InfixOp, v1: i64, v2: i64) -> bool {
+    match infix {
+        InfixOp::Mul => v1 < 0 || v2 < 0,
+        InfixOp::Div => v2 < 0,
+        InfixOp::Modulo => v1 < 0,
+        _ => false,
+    }
+}
+
+fn is_cmp(infix: &InfixOp) -> bool {
+    matches!(infix, InfixOp::Lt | InfixOp::Le | InfixOp::Gt | InfixOp::Ge)
+}
+
+// BigInts stay exact: the division truncates, and nothing falls back to floats
+fn eval_infix_bigint(infix: &InfixOp, v1: &BigInt, v2: &BigInt) -> Result<Value> {
+    let too_large = || Error::Range(String::from("Maximum BigInt size exceeded"));
+    let res = match infix {
+        InfixOp::Add => v1.add(v2),
+        InfixOp::Sub => v1.sub(v2),
+        InfixOp::Mul => v1.mul(v2).ok_or_else(too_large)?,
+        InfixOp::Div | InfixOp::Modulo => {
+            let (quotient, remainder) = v1
+                .div_rem(v2)
+                .ok_or_else(|| Error::Range(String::from("Division by zero")))?;
+            match infix {
+                InfixOp::Div => quotient,
+                _ => remainder,
+            }
+        }
+        InfixOp::Pow if v2.is_negative() => {
+            return Err(Error::Range(String::from("Exponent must be non-negative")))
+        }
+        InfixOp::Pow => {
+            // past u64, only 0, 1 and -1 don't overflow, and only the parity matters
+            let exp = v2
+                .to_i64()
+                .map_or(u64::MAX - !v2.is_odd() as u64, |exp| exp as u64);
+            v1.pow(exp).ok_or_else(too_large)?
+        }
+        InfixOp::Lt => return Ok(Value::Bool(v1 < v2)),
+        InfixOp::Le => return Ok(Value::Bool(v1 <= v2)),
+        InfixOp::Gt => return Ok(Value::Bool(v1 > v2)),
+        InfixOp::Ge => return Ok(Value::Bool(v1 >= v2)),
+        InfixOp::Eq | InfixOp::StrictEq => return Ok(Value::Bool(v1 == v2)),
+        InfixOp::Ne | InfixOp::StrictNe => return Ok(Value::Bool(v1 != v2)),
+    };
+    Ok(Value::BigInt(res))
+}
@@ -1,14 +1,18 @@
-use anyhow::Result;
 use std::collections::HashMap;
 use std::fs;
+use std::rc::Rc;
 
 use crate::parser;
-use crate::runner::{value, Runner, Value};
+use crate::runner::json::{self, DuplicateKeys};
+use crate::runner::value::ObjectRef;
+use crate::runner::{value, Error, Result, Runner, Value};
 
 pub type BuiltinFuncSign = fn(&mut Runner, &[Value]) -> Result<Value>;
 
 pub struct Builtin {
     pub nb_args: usize,
+    // trailing params which can be left out
+    pub nb_optional: usize,
     pub func: BuiltinFuncSign,
 }
 
@@ -18,6 +22,7 @@ pub fn new() -> HashMap<String, Builtin> {
         String::from("min"),
         Builtin {
             nb_args: 2,
+            nb_optional: 0,
             func: builtin_min,
         },
     );
@@ -25,6 +30,7 @@ pub fn new() -> HashMap<String, Builtin> {
         String::from("max"),
         Builtin {
             nb_args: 2,
+            nb_optional: 0,
             func: builtin_max,
         },
     );
@@ -32,27 +38,280 @@ pub fn new() -> HashMap<String, Builtin> {
         String::from("load_json"),
         Builtin {
             nb_args: 1,
+            nb_optional: 1,
             func: builtin_load_json,
         },
     );
+    builtins.insert(
+        String::from("load_json5"),
+        Builtin {
+            nb_args: 1,
+            nb_optional: 1,
+            func: builtin_load_json5,
+        },
+    );
+    builtins.insert(
+        String::from("isNaN"),
+        Builtin {
+            nb_args: 1,
+            nb_optional: 0,
+            func: builtin_is_nan,
+        },
+    );
+    builtins.insert(
+        String::from("isFinite"),
+        Builtin {
+            nb_args: 1,
+            nb_optional: 0,
+            func: builtin_is_finite,
+        },
+    );
+    builtins.insert(
+        String::from("setTimeout"),
+        Builtin {
+            nb_args: 1,
+            nb_optional: 1,
+            func: builtin_set_timeout,
+        },
+    );
+    builtins.insert(
+        String::from("clearTimeout"),
+        Builtin {
+            nb_args: 1,
+            nb_optional: 0,
+            func: builtin_clear_timeout,
+        },
+    );
+    builtins.insert(
+        String::from("sleep"),
+        Builtin {
+            nb_args: 1,
+            nb_optional: 0,
+            func: builtin_sleep,
+        },
+    );
+    builtins.insert(
+        String::from("to_json"),
+        Builtin {
+            nb_args: 1,
+            nb_optional: 0,
+            func: builtin_to_json,
+        },
+    );
+    builtins.insert(
+        String::from("deepFreeze"),
+        Builtin {
+            nb_args: 1,
+            nb_optional: 0,
+            func: builtin_deep_freeze,
+        },
+    );
     builtins
 }
 
+// builtins which are values rather than functions, like the `Object` namespace
+pub fn values() -> Vec<(String, Value)> {
+    let native = |name: &str, nb_args: usize, func: BuiltinFuncSign| {
+        let builtin = Builtin {
+            nb_args,
+            nb_optional: 0,
+            func,
+        };
+        let native = value::Native {
+            name: name.to_string(),
+            builtin: Rc::new(builtin),
+        };
+        (name.to_string(), Value::Native(native))
+    };
+    let object = ObjectRef::new(vec![
+        native("freeze", 1, builtin_object_freeze),
+        native("isFrozen", 1, builtin_object_is_frozen),
+    ]);
+    object.0.borrow_mut().frozen = true;
+    vec![(String::from("Object"), Value::Object(object))]
+}
+
 fn builtin_load_json(runner: &mut Runner, params: &[Value]) -> Result<Value> {
-    let path = value::as_string(params.get(0).unwrap())?;
-    let file_content = fs::read_to_string(path)?;
-    let literal = parser::parse_json(file_content.as_str())?;
-    runner.eval_literal(&literal)
+    load_json(runner, params, json::parse_json)
+}
+
+fn builtin_load_json5(runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    load_json(runner, params, json::parse_json5)
+}
+
+fn load_json(
+    runner: &mut Runner,
+    params: &[Value],
+    parse: fn(&str, DuplicateKeys) -> parser::Result<Value>,
+) -> Result<Value> {
+    let path = value::as_string(params.first().unwrap())?;
+    let file_content = fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let duplicates = duplicate_keys(runner, params.get(1))?;
+    parse(&file_content, duplicates).map_err(|source| Error::Json {
+        path: path.clone(),
+        source,
+    })
+}
+
+// `load_json(path, { duplicateKeys: "lastWins" })`, or load_json5: by default, a key given twice is an error
+fn duplicate_keys(runner: &mut Runner, options: Option<&Value>) -> Result<DuplicateKeys> {
+    let policy = match options {
+        None | Some(Value::Undefined) => Value::Undefined,
+        Some(options) => runner.get_prop(options, "duplicateKeys")?,
+    };
+    match policy {
+        Value::Undefined => Ok(DuplicateKeys::Error),
+        Value::Str(s) if s == "error" => Ok(DuplicateKeys::Error),
+        Value::Str(s) if s == "lastWins" => Ok(DuplicateKeys::LastWins),
+        other => Err(Error::Type(format!(
+            "duplicateKeys must be \"error\" or \"lastWins\", not {}",
+            other
+        ))),
+    }
 }
 
 fn builtin_min(_runner: &mut Runner, params: &[Value]) -> Result<Value> {
-    let v1 = value::as_f64(params.get(0).unwrap())?;
+    if let [Value::Int(v1), Value::Int(v2)] = params {
+        return Ok(Value::Int(*v1.min(v2)));
+    }
+    let v1 = value::as_f64(params.first().unwrap())?;
     let v2 = value::as_f64(params.get(1).unwrap())?;
-    Ok(Value::Num(v1.min(v2)))
+    // rust's min ignores NaN, js doesn't
+    match v1.is_nan() || v2.is_nan() {
+        true => Ok(Value::Num(f64::NAN)),
+        false => Ok(Value::Num(v1.min(v2))),
+    }
 }
 
 fn builtin_max(_runner: &mut Runner, params: &[Value]) -> Result<Value> {
-    let v1 = value::as_f64(params.get(0).unwrap())?;
+    if let [Value::Int(v1), Value::Int(v2)] = params {
+        return Ok(Value::Int(*v1.max(v2)));
+    }
+    let v1 = value::as_f64(params.first().unwrap())?;
     let v2 = value::as_f64(params.get(1).unwrap())?;
-    Ok(Value::Num(v1.max(v2)))
+    match v1.is_nan() || v2.is_nan() {
+        true => Ok(Value::Num(f64::NAN)),
+        false => Ok(Value::Num(v1.max(v2))),
+    }
+}
+
+// like the global isNaN: the value is converted to a number first
+fn builtin_is_nan(runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    let n = runner.coerce_to_number(params.first().unwrap())?;
+    Ok(Value::Bool(n.is_nan()))
+}
+
+fn builtin_is_finite(runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    let n = runner.coerce_to_number(params.first().unwrap())?;
+    Ok(Value::Bool(n.is_finite()))
+}
+
+// on the virtual clock, see `jobs::Jobs`
+fn builtin_set_timeout(runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    let func = params.first().unwrap().clone();
+    if !matches!(func, Value::Function(_) | Value::Native(_)) {
+        return Err(Error::Type(format!(
+            "setTimeout callback {} is not a function",
+            func
+        )));
+    }
+    let delay = match params.get(1) {
+        Some(delay) => as_delay(delay)?,
+        None => 0,
+    };
+    let id = runner.set_timer(
+        delay,
+        Box::new(move |runner| runner.call_value(&func, &[]).map(|_| ())),
+    );
+    Ok(Value::Int(id))
+}
+
+fn builtin_clear_timeout(runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    runner.clear_timer(value::as_i64(params.first().unwrap())?);
+    Ok(Value::Undefined)
+}
+
+// a promise resolved once the virtual clock has moved forward
+fn builtin_sleep(runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    let delay = as_delay(params.first().unwrap())?;
+    let promise = runner.new_promise();
+    let res = promise.clone();
+    runner.set_timer(
+        delay,
+        Box::new(move |runner| {
+            runner.resolve_promise(&res, Value::Undefined);
+            Ok(())
+        }),
+    );
+    Ok(Value::Promise(promise))
+}
+
+// in ms. like javascript, a negative delay is no delay, and a delay can't be longer than
+// 2^31 - 1 ms (about 24 days): javascript runs longer ones at once, they are clamped here
+const MAX_DELAY: f64 = 2147483647.0;
+
+fn as_delay(val: &Value) -> Result<u64> {
+    let delay = value::as_f64(val)?;
+    Ok(if delay > 0.0 {
+        delay.min(MAX_DELAY) as u64
+    } else {
+        0
+    })
+}
+
+// like JSON.stringify
+fn builtin_to_json(runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    match runner.stringify_json(params.first().unwrap(), &mut vec![])? {
+        Some(json) => Ok(Value::Str(json)),
+        None => Ok(Value::Undefined),
+    }
+}
+
+// only objects can change: arrays and primitives are values, which are copied
+fn builtin_object_freeze(_runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    let val = params.first().unwrap();
+    if let Value::Object(obj) = val {
+        obj.0.borrow_mut().frozen = true;
+    }
+    Ok(val.clone())
+}
+
+// primitives are frozen, as in javascript. arrays aren't: they can't be changed in place, but
+// a javascript array can, and freezing one isn't tracked
+fn builtin_object_is_frozen(_runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    match params.first().unwrap() {
+        Value::Object(obj) => Ok(Value::Bool(obj.0.borrow().frozen)),
+        Value::Array(_) => Ok(Value::Bool(false)),
+        _ => Ok(Value::Bool(true)),
+    }
+}
+
+// freeze an object, and all the objects reachable from its properties and array items
+fn builtin_deep_freeze(_runner: &mut Runner, params: &[Value]) -> Result<Value> {
+    let val = params.first().unwrap();
+    deep_freeze(val, &mut vec![]);
+    Ok(val.clone())
+}
+
+fn deep_freeze(val: &Value, seen: &mut Vec<ObjectRef>) {
+    match val {
+        Value::Object(obj) => {
+            if seen.contains(obj) {
+                return;
+            }
+            seen.push(obj.clone());
+            obj.0.borrow_mut().frozen = true;
+            let props = obj.0.borrow().props.clone();
+            for (_, prop) in props {
+                if let value::Property::Data(val) = prop {
+                    deep_freeze(&val, seen);
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| deep_freeze(item, seen)),
+        _ => {}
+    }
 }
@@ -0,0 +1,187 @@
+use crate::bigint::BigInt;
+use crate::parser::ast::num_to_string;
+use crate::runner::{value, Error, Result, Runner, Value};
+
+// JS type conversions, as used by the operators when strict mode is off
+
+impl Runner {
+    // objects become strings or numbers, through their own valueOf() or toString() if they have one
+    pub fn coerce_to_primitive(&mut self, val: Value) -> Result<Value> {
+        match &val {
+            Value::Object(_) => {
+                for name in ["valueOf", "toString"] {
+                    let func = self.get_prop(&val, name)?;
+                    if let Value::Function(_) | Value::Native(_) = func {
+                        let res = self.call_method(&func, &val, &[])?;
+                        if is_primitive(&res) {
+                            return Ok(res);
+                        }
+                    }
+                }
+                Ok(Value::Str(to_string(&val)))
+            }
+            Value::Array(_)
+            | Value::Function(_)
+            | Value::Native(_)
+            | Value::Generator(_)
+            | Value::Promise(_) => Ok(Value::Str(to_string(&val))),
+            _ => Ok(val),
+        }
+    }
+
+    // like `Number(val)`, as a float
+    pub fn coerce_to_number(&mut self, val: &Value) -> Result<f64> {
+        match self.coerce_to_primitive(val.clone())? {
+            Value::BigInt(_) => Err(Error::Type(String::from(
+                "Cannot convert a BigInt value to a number",
+            ))),
+            val => value::as_f64(&to_number(&val)),
+        }
+    }
+}
+
+pub fn is_primitive(val: &Value) -> bool {
+    !matches!(
+        val,
+        Value::Object(_)
+            | Value::Array(_)
+            | Value::Function(_)
+            | Value::Native(_)
+            | Value::Generator(_)
+            | Value::Promise(_)
+    )
+}
+
+// like `String(val)`
+pub fn to_string(val: &Value) -> String {
+    match val {
+        Value::Str(s) => s.clone(),
+        Value::Int(n) => n.to_string(),
+        Value::Num(n) => num_to_string(*n),
+        Value::BigInt(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => String::from("null"),
+        Value::Undefined => String::from("undefined"),
+        Value::Array(elts) => {
+            let parts: Vec<String> = elts
+                .iter()
+                .map(|elt| match elt {
+                    Value::Null | Value::Undefined => String::new(),
+                    elt => to_string(elt),
+                })
+                .collect();
+            parts.join(",")
+        }
+        Value::Object(_) => String::from("[object Object]"),
+        Value::Function(closure) => closure.def.to_string(),
+        Value::Native(native) => format!("function {}() {{ [native code] }}", native.name),
+        Value::Generator(_) => String::from("[object Generator]"),
+        Value::Promise(_) => String::from("[object Promise]"),
+    }
+}
+
+// like `Number(val)`: Int when the result is an exact integer, Num otherwise.
+// BigInts stay BigInts, as with the operators of javascript
+pub fn to_number(val: &Value) -> Value {
+    match val {
+        Value::Int(_) | Value::Num(_) | Value::BigInt(_) => val.clone(),
+        Value::Bool(b) => Value::Int(*b as i64),
+        Value::Null => Value::Int(0),
+        Value::Str(s) => str_to_number(s),
+        Value::Array(elts) if elts.is_empty() => Value::Int(0),
+        Value::Array(elts) if elts.len() == 1 => to_number(&Value::Str(to_string(val))),
+        _ => Value::Num(f64::NAN),
+    }
+}
+
+fn str_to_number(s: &str) -> Value {
+    let s = s.trim();
+    if s.is_empty() {
+        return Value::Int(0);
+    }
+    let radix = match s.get(..2) {
+        Some("0x") | Some("0X") => Some(16),
+        Some("0o") | Some("0O") => Some(8),
+        Some("0b") | Some("0B") => Some(2),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        return match i64::from_str_radix(&s[2..], radix) {
+            Ok(n) if !s[2..].starts_with(['+', '-']) => Value::Int(n),
+            _ => Value::Num(f64::NAN),
+        };
+    }
+    match s {
+        "Infinity" | "+Infinity" => return Value::Num(f64::INFINITY),
+        "-Infinity" => return Value::Num(f64::NEG_INFINITY),
+        _ => {}
+    }
+    // rust also accepts things like "inf" or "NaN", js doesn't
+    if !s
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+    {
+        return Value::Num(f64::NAN);
+    }
+    match (s.parse::<i64>(), s.parse::<f64>()) {
+        (Ok(n), _) => Value::Int(n),
+        (_, Ok(n)) => Value::Num(n),
+        _ => Value::Num(f64::NAN),
+    }
+}
+
+// `===`: no conversion, and NaN is not equal to itself
+pub fn strict_eq(v1: &Value, v2: &Value) -> bool {
+    v1 == v2
+}
+
+// what `includes` compares with: `===`, except that NaN is found
+pub fn same_value_zero(v1: &Value, v2: &Value) -> bool {
+    match (v1, v2) {
+        (Value::Num(n1), Value::Num(n2)) if n1.is_nan() && n2.is_nan() => true,
+        _ => strict_eq(v1, v2),
+    }
+}
+
+impl Runner {
+    // `==`
+    pub fn loose_eq(&mut self, v1: &Value, v2: &Value) -> Result<bool> {
+        let res = match (v1, v2) {
+            (Value::Null | Value::Undefined, Value::Null | Value::Undefined) => true,
+            (Value::Null | Value::Undefined, _) | (_, Value::Null | Value::Undefined) => false,
+            // booleans are compared as numbers
+            (Value::Bool(b), v2) if !matches!(v2, Value::Bool(_)) => {
+                return self.loose_eq(&Value::Int(*b as i64), v2)
+            }
+            (v1, Value::Bool(b)) if !matches!(v1, Value::Bool(_)) => {
+                return self.loose_eq(v1, &Value::Int(*b as i64))
+            }
+            (Value::BigInt(n), Value::Int(_) | Value::Num(_) | Value::Str(_)) => bigint_eq(n, v2),
+            (Value::Int(_) | Value::Num(_) | Value::Str(_), Value::BigInt(n)) => bigint_eq(n, v1),
+            (Value::Int(_) | Value::Num(_), Value::Str(_)) => strict_eq(v1, &to_number(v2)),
+            (Value::Str(_), Value::Int(_) | Value::Num(_)) => strict_eq(&to_number(v1), v2),
+            (v1, v2) if is_primitive(v1) != is_primitive(v2) => {
+                let v1 = self.coerce_to_primitive(v1.clone())?;
+                let v2 = self.coerce_to_primitive(v2.clone())?;
+                return self.loose_eq(&v1, &v2);
+            }
+            (v1, v2) => strict_eq(v1, v2),
+        };
+        Ok(res)
+    }
+}
+
+// `1n == 1`, `1n == 1.0` and `1n == "1"`, exactly
+fn bigint_eq(n: &BigInt, val: &Value) -> bool {
+    let other = match val {
+        Value::Int(i) => Some(BigInt::from_i64(*i)),
+        // an integral float prints with all its digits
+        Value::Num(x) if x.is_finite() && x.fract() == 0.0 => {
+            BigInt::parse(&format!("{:.0}", x), 10)
+        }
+        Value::Str(s) if s.trim().is_empty() => Some(BigInt::zero()),
+        Value::Str(s) => BigInt::parse(s.trim(), 10),
+        _ => None,
+    };
+    other.as_ref() == Some(n)
+}
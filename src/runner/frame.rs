@@ -0,0 +1,317 @@
+use std::{mem, slice, vec};
+
+use crate::parser::ast::{DeclKind, Expr, ExprKind, Stmt, StmtKind};
+use crate::runner::iter::{self, ValueIter};
+use crate::runner::scope::{self, Scope, ScopeRef};
+use crate::runner::{value, Error, Result, Runner, Value};
+
+// How a frame stopped running
+pub enum Completion {
+    Normal,
+    Return(Value),
+    Yield(Value),
+    Await(Value),
+}
+
+// What to do with the value sent back in when a frame suspended by a yield or an await is resumed
+enum Pending {
+    // the value of an expression statement, like `await x;`
+    Complete,
+    Declare(DeclKind, String),
+    Reassign(String),
+}
+
+// The execution state of a function body (or of the whole program).
+//
+// Statements are not run recursively: the frame keeps an explicit stack of the
+// blocks and loops it is in, so it can stop at a `yield` and pick up later
+// exactly where it left off: generators stop at a `yield`, async functions at an `await`.
+pub struct Frame {
+    levels: Vec<Level>,
+    scope: ScopeRef,
+    // where `var` declarations go
+    var_scope: ScopeRef,
+    // only a lowered body can suspend, see `generator::lower`
+    can_suspend: bool,
+    pending: Option<Pending>,
+    // value of the last expression statement
+    pub completion: Value,
+}
+
+// One nesting level: a list of statements and the position of the next one to run
+struct Level {
+    // index, in the parent level, of the statement owning this level
+    owner: usize,
+    pos: usize,
+    kind: LevelKind,
+    // scope to restore when leaving the level
+    outer: ScopeRef,
+}
+
+enum LevelKind {
+    Root,
+    Block,
+    Branch(bool),
+    ForOf(Box<dyn ValueIter>),
+    ForIn(vec::IntoIter<String>),
+    While,
+}
+
+impl Frame {
+    pub fn new(scope: ScopeRef, var_scope: ScopeRef, can_suspend: bool) -> Self {
+        Frame {
+            levels: vec![],
+            scope,
+            var_scope,
+            can_suspend,
+            pending: None,
+            completion: Value::Undefined,
+        }
+    }
+}
+
+// the statements of a level, given the statement owning it
+fn children<'a>(stmt: &'a Stmt, kind: &LevelKind) -> &'a [Stmt] {
+    match (&stmt.kind, kind) {
+        (StmtKind::Block(stmts), _) => stmts,
+        (StmtKind::ForOf(.., body), _)
+        | (StmtKind::ForIn(.., body), _)
+        | (StmtKind::While(_, body), _) => slice::from_ref(body),
+        (StmtKind::If(_, then, _), LevelKind::Branch(true)) => slice::from_ref(then),
+        (StmtKind::If(_, _, Some(otherwise)), LevelKind::Branch(false)) => {
+            slice::from_ref(otherwise)
+        }
+        _ => &[],
+    }
+}
+
+// walk down from the body to the statements of the innermost level.
+// also returns the statement owning that level
+fn current<'a>(body: &'a [Stmt], levels: &[Level]) -> (&'a [Stmt], Option<&'a Stmt>) {
+    let mut stmts = body;
+    let mut owner = None;
+    for level in levels.iter().skip(1) {
+        let stmt = &stmts[level.owner];
+        stmts = children(stmt, &level.kind);
+        owner = Some(stmt);
+    }
+    (stmts, owner)
+}
+
+impl Runner {
+    // run a frame until it completes or suspends. `sent` is the value a pending yield or await evaluates to
+    pub fn run_frame(
+        &mut self,
+        body: &[Stmt],
+        frame: &mut Frame,
+        sent: Value,
+    ) -> Result<Completion> {
+        let outer_scope = mem::replace(&mut self.scope, frame.scope.clone());
+        let outer_var_scope = mem::replace(&mut self.var_scope, frame.var_scope.clone());
+        let res = self.nested(|runner| runner.step_frame(body, frame, sent));
+        frame.scope = mem::replace(&mut self.scope, outer_scope);
+        self.var_scope = outer_var_scope;
+        res
+    }
+
+    fn step_frame(&mut self, body: &[Stmt], frame: &mut Frame, sent: Value) -> Result<Completion> {
+        if frame.levels.is_empty() {
+            self.hoist_functions(body);
+            frame.levels.push(Level {
+                owner: 0,
+                pos: 0,
+                kind: LevelKind::Root,
+                outer: self.scope.clone(),
+            });
+        }
+        match frame.pending.take() {
+            Some(Pending::Declare(kind, name)) => self.declare(kind, &name, sent),
+            Some(Pending::Reassign(name)) => scope::assign(&self.scope, &name, sent)?,
+            Some(Pending::Complete) => frame.completion = sent,
+            None => {}
+        }
+
+        loop {
+            let (stmts, owner) = current(body, &frame.levels);
+            let level = frame.levels.last_mut().unwrap();
+            if level.pos < stmts.len() {
+                let idx = level.pos;
+                level.pos += 1;
+                let stmt = &stmts[idx];
+                self.count_step().map_err(|err| err.at(stmt.span))?;
+                if let Some(completion) = self
+                    .step_stmt(stmt, idx, frame)
+                    .map_err(|err| err.at(stmt.span))?
+                {
+                    return Ok(completion);
+                }
+                continue;
+            }
+
+            // we are done with this level: see if it needs to run again
+            let again = match (&mut level.kind, owner.map(|stmt| &stmt.kind)) {
+                (LevelKind::Root, _) => return Ok(Completion::Normal),
+                (LevelKind::ForOf(values), Some(StmtKind::ForOf(kind, name, iterable, _))) => {
+                    match values.next(self).map_err(|err| err.at(iterable.span))? {
+                        Some(val) => {
+                            self.scope = Scope::new(Some(level.outer.clone()));
+                            self.declare(*kind, name, val);
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                (LevelKind::ForIn(keys), Some(StmtKind::ForIn(kind, name, ..))) => {
+                    match keys.next() {
+                        Some(key) => {
+                            self.scope = Scope::new(Some(level.outer.clone()));
+                            self.declare(*kind, name, Value::Str(key));
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                (LevelKind::While, Some(StmtKind::While(cond, _))) => {
+                    let cond = self.eval_expr(cond)?;
+                    value::truthy(&cond)
+                }
+                _ => false,
+            };
+            if again {
+                level.pos = 0;
+            } else {
+                self.scope = level.outer.clone();
+                frame.levels.pop();
+            }
+        }
+    }
+
+    // run one statement. compound statements push a new level instead of running their body
+    fn step_stmt(
+        &mut self,
+        stmt: &Stmt,
+        idx: usize,
+        frame: &mut Frame,
+    ) -> Result<Option<Completion>> {
+        let outer = self.scope.clone();
+        // loops start on an exhausted level, so their first iteration is set up like the next ones
+        let (kind, pos) = match &stmt.kind {
+            StmtKind::Block(stmts) => {
+                self.scope = Scope::new(Some(outer.clone()));
+                self.hoist_functions(stmts);
+                (LevelKind::Block, 0)
+            }
+            StmtKind::ForOf(_, _, iterable_expr, _) => {
+                let iterable = self.eval_expr(iterable_expr)?;
+                (LevelKind::ForOf(iter::values(&iterable)?), 1)
+            }
+            StmtKind::ForIn(_, _, obj_expr, _) => {
+                let obj = self.eval_expr(obj_expr)?;
+                (LevelKind::ForIn(iter::keys(&obj)?.into_iter()), 1)
+            }
+            StmtKind::While(..) => (LevelKind::While, 1),
+            StmtKind::If(cond, _, otherwise) => {
+                let cond = value::truthy(&self.eval_expr(cond)?);
+                if !cond && otherwise.is_none() {
+                    return Ok(None);
+                }
+                (LevelKind::Branch(cond), 0)
+            }
+            StmtKind::Return(val) => {
+                let val = match val {
+                    Some(val) => self.eval_expr(val)?,
+                    None => Value::Undefined,
+                };
+                return Ok(Some(Completion::Return(val)));
+            }
+            StmtKind::Expr(expr) => {
+                if frame.can_suspend {
+                    if let Some(completion) = self.eval_suspend(expr)? {
+                        frame.pending = Some(Pending::Complete);
+                        return Ok(Some(completion));
+                    }
+                }
+                frame.completion = self.eval_expr(expr)?;
+                return Ok(None);
+            }
+            StmtKind::Assign(kind, name, Some(expr)) if frame.can_suspend => {
+                if let Some(completion) = self.eval_suspend(expr)? {
+                    frame.pending = Some(Pending::Declare(*kind, name.clone()));
+                    return Ok(Some(completion));
+                }
+                return self.run_stmt(stmt).map(|_| None);
+            }
+            StmtKind::Reassign(name, expr) if frame.can_suspend => {
+                if let Some(completion) = self.eval_suspend(expr)? {
+                    frame.pending = Some(Pending::Reassign(name.clone()));
+                    return Ok(Some(completion));
+                }
+                return self.run_stmt(stmt).map(|_| None);
+            }
+            _ => return self.run_stmt(stmt).map(|_| None),
+        };
+        frame.levels.push(Level {
+            owner: idx,
+            pos,
+            kind,
+            outer,
+        });
+        Ok(None)
+    }
+
+    // the completion for a `yield x` or `await x`, None for any other expression
+    fn eval_suspend(&mut self, expr: &Expr) -> Result<Option<Completion>> {
+        match &expr.kind {
+            ExprKind::Yield(Some(val)) => Ok(Some(Completion::Yield(self.eval_expr(val)?))),
+            ExprKind::Yield(None) => Ok(Some(Completion::Yield(Value::Undefined))),
+            ExprKind::Await(val) => Ok(Some(Completion::Await(self.eval_expr(val)?))),
+            _ => Ok(None),
+        }
+    }
+
+    // function declarations can be called before they appear in their block
+    fn hoist_functions(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            if let StmtKind::Function(def) = &stmt.kind {
+                if let Some(name) = &def.name {
+                    let val = self.new_closure(def);
+                    self.scope.borrow_mut().declare(name, val, false);
+                }
+            }
+        }
+    }
+
+    // statements which don't need a level of their own
+    pub fn run_stmt(&mut self, stmt: &Stmt) -> Result<()> {
+        match &stmt.kind {
+            StmtKind::Assign(kind, name, expr) => {
+                let val = match expr {
+                    Some(expr) => self.eval_expr(expr)?,
+                    None => Value::Undefined,
+                };
+                self.declare(*kind, name, val);
+            }
+            StmtKind::Reassign(name, expr) => {
+                let val = self.eval_expr(expr)?;
+                scope::assign(&self.scope, name, val)?;
+            }
+            StmtKind::SetMember(obj_expr, name, expr) => {
+                let obj = self.eval_expr(obj_expr)?;
+                let val = self.eval_expr(expr)?;
+                self.set_prop(&obj, name, val)?;
+            }
+            StmtKind::Expr(expr) => {
+                self.eval_expr(expr)?;
+            }
+            // already declared when entering the block
+            StmtKind::Function(_) => {}
+            unknown => {
+                return Err(Error::Unexpected(format!(
+                    "Unexpected statement: {}",
+                    unknown
+                )))
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+use std::mem;
+use std::rc::Rc;
+
+use crate::parser::ast::{
+    DeclKind, Expr, ExprKind, FunctionDef, Literal, Prop, PropKey, Span, Stmt, StmtKind,
+};
+use crate::runner::frame::{Completion, Frame};
+use crate::runner::value::GeneratorRef;
+use crate::runner::{Error, Result, Runner, Value};
+
+pub struct Generator {
+    // the lowered body, see `lower`
+    def: Rc<FunctionDef>,
+    state: State,
+}
+
+enum State {
+    Suspended(Box<Frame>),
+    Running,
+    Done,
+}
+
+impl Generator {
+    pub fn new(def: Rc<FunctionDef>, frame: Frame) -> Self {
+        Generator {
+            def,
+            state: State::Suspended(Box::new(frame)),
+        }
+    }
+}
+
+impl Runner {
+    // resume a generator: returns the next yielded value, and whether the generator is done
+    pub fn resume_generator(&mut self, gen: &GeneratorRef, sent: Value) -> Result<(Value, bool)> {
+        let (def, mut frame) = {
+            let mut gen = gen.0.borrow_mut();
+            match mem::replace(&mut gen.state, State::Running) {
+                State::Suspended(frame) => (gen.def.clone(), frame),
+                State::Running => {
+                    return Err(Error::Type(String::from("Generator is already running")))
+                }
+                State::Done => {
+                    gen.state = State::Done;
+                    return Ok((Value::Undefined, true));
+                }
+            }
+        };
+        let res = self.run_frame(&def.body, &mut frame, sent);
+        let mut gen = gen.0.borrow_mut();
+        match res {
+            Ok(Completion::Yield(val)) => {
+                gen.state = State::Suspended(frame);
+                Ok((val, false))
+            }
+            Ok(Completion::Await(_)) => {
+                gen.state = State::Done;
+                Err(Error::Syntax(String::from(
+                    "await is only valid in async functions and at the top level",
+                )))
+            }
+            Ok(Completion::Return(val)) => {
+                gen.state = State::Done;
+                Ok((val, true))
+            }
+            Ok(Completion::Normal) => {
+                gen.state = State::Done;
+                Ok((Value::Undefined, true))
+            }
+            Err(err) => {
+                gen.state = State::Done;
+                Err(err)
+            }
+        }
+    }
+}
+
+// A frame can only suspend between statements, so a generator body is rewritten
+// until every yield is either a statement of its own (`yield x;`), or the whole
+// right hand side of a declaration or assignment (`let y = yield x;`).
+// `await` suspends too, and is lowered the same way in async functions.
+//
+// Whatever a yield is nested in gets split into temporaries, keeping the
+// left to right evaluation order:
+//     `var s = f(a) + (yield b);`
+// becomes
+//     `let @0 = f(a); let @1 = yield b; var s = @0 + @1;`
+pub fn lower(def: &FunctionDef) -> FunctionDef {
+    FunctionDef {
+        body: lower_stmts(&def.body),
+        ..def.clone()
+    }
+}
+
+// for the program itself, which can await at the top level
+pub fn lower_stmts(stmts: &[Stmt]) -> Vec<Stmt> {
+    let mut lowering = Lowering { nb_tmps: 0 };
+    lowering.stmts(stmts)
+}
+
+struct Lowering {
+    nb_tmps: usize,
+}
+
+impl Lowering {
+    // temporaries can't clash with user variables: `@` is not valid in an ident
+    fn tmp(&mut self) -> String {
+        self.nb_tmps += 1;
+        format!("@{}", self.nb_tmps - 1)
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) -> Vec<Stmt> {
+        let mut out = vec![];
+        for stmt in stmts {
+            self.stmt(stmt, &mut out)
+        }
+        out
+    }
+
+    // a statement which has to stay a single statement, like a loop body
+    fn single(&mut self, stmt: &Stmt) -> Stmt {
+        let mut out = vec![];
+        self.stmt(stmt, &mut out);
+        if out.len() == 1 {
+            out.pop().unwrap()
+        } else {
+            StmtKind::Block(out).at(stmt.span)
+        }
+    }
+
+    // the statements added by the lowering point to the statement they come from
+    fn stmt(&mut self, stmt: &Stmt, out: &mut Vec<Stmt>) {
+        let span = stmt.span;
+        let lowered = match &stmt.kind {
+            StmtKind::Expr(expr) => match &expr.kind {
+                ExprKind::Yield(val) => {
+                    let val = ExprKind::Yield(self.opt_expr(val, out)).at(expr.span);
+                    StmtKind::Expr(Box::new(val))
+                }
+                ExprKind::Await(val) => {
+                    let val = ExprKind::Await(Box::new(self.expr(val, out))).at(expr.span);
+                    StmtKind::Expr(Box::new(val))
+                }
+                ExprKind::YieldStar(val) => {
+                    let val = self.expr(val, out);
+                    self.delegate(val, span)
+                }
+                _ => StmtKind::Expr(Box::new(self.expr(expr, out))),
+            },
+            StmtKind::Assign(kind, name, Some(expr)) => {
+                let expr = self.suspend_expr(expr, out);
+                StmtKind::Assign(*kind, name.clone(), Some(Box::new(expr)))
+            }
+            StmtKind::Assign(_, _, None) => stmt.kind.clone(),
+            StmtKind::Reassign(name, expr) => {
+                let expr = self.suspend_expr(expr, out);
+                StmtKind::Reassign(name.clone(), Box::new(expr))
+            }
+            StmtKind::SetMember(obj, name, val) => {
+                let mut parts = self.exprs(&[obj, val], out).into_iter();
+                let obj = parts.next().unwrap();
+                let val = parts.next().unwrap();
+                StmtKind::SetMember(Box::new(obj), name.clone(), Box::new(val))
+            }
+            StmtKind::Block(stmts) => StmtKind::Block(self.stmts(stmts)),
+            StmtKind::ForOf(kind, name, iterable, body) => {
+                let iterable = self.expr(iterable, out);
+                StmtKind::ForOf(
+                    *kind,
+                    name.clone(),
+                    Box::new(iterable),
+                    Box::new(self.single(body)),
+                )
+            }
+            StmtKind::ForIn(kind, name, obj, body) => {
+                let obj = self.expr(obj, out);
+                StmtKind::ForIn(
+                    *kind,
+                    name.clone(),
+                    Box::new(obj),
+                    Box::new(self.single(body)),
+                )
+            }
+            StmtKind::While(cond, body) if suspends(cond) => {
+                // the condition is evaluated before the loop, then again at the end of each iteration
+                let mut cond_stmts = vec![];
+                let cond_span = cond.span;
+                let cond = self.expr(cond, &mut cond_stmts);
+                let cond_name = self.tmp();
+                out.extend(cond_stmts.iter().cloned());
+                let decl = StmtKind::Assign(
+                    DeclKind::Let,
+                    cond_name.clone(),
+                    Some(Box::new(cond.clone())),
+                );
+                out.push(decl.at(span));
+                let mut body_stmts = vec![self.single(body)];
+                body_stmts.extend(cond_stmts);
+                body_stmts.push(StmtKind::Reassign(cond_name.clone(), Box::new(cond)).at(span));
+                let cond = ExprKind::Ident(cond_name).at(cond_span);
+                StmtKind::While(
+                    Box::new(cond),
+                    Box::new(StmtKind::Block(body_stmts).at(body.span)),
+                )
+            }
+            StmtKind::While(cond, body) => {
+                StmtKind::While(cond.clone(), Box::new(self.single(body)))
+            }
+            StmtKind::If(cond, then, otherwise) => {
+                let cond = self.expr(cond, out);
+                let then = self.single(then);
+                let otherwise = otherwise
+                    .as_ref()
+                    .map(|otherwise| Box::new(self.single(otherwise)));
+                StmtKind::If(Box::new(cond), Box::new(then), otherwise)
+            }
+            StmtKind::Return(val) => StmtKind::Return(self.opt_expr(val, out)),
+            // a nested function's yields belong to that function
+            StmtKind::Function(_) => stmt.kind.clone(),
+        };
+        out.push(lowered.at(span))
+    }
+
+    // `yield* it` yields every value of `it`
+    fn delegate(&mut self, iterable: Expr, span: Span) -> StmtKind {
+        let name = self.tmp();
+        let val = ExprKind::Ident(name.clone()).at(span);
+        let yield_stmt = StmtKind::Expr(Box::new(ExprKind::Yield(Some(Box::new(val))).at(span)));
+        StmtKind::ForOf(
+            DeclKind::Const,
+            name,
+            Box::new(iterable),
+            Box::new(yield_stmt.at(span)),
+        )
+    }
+
+    // the right hand side of a declaration or assignment, which can be a yield or await
+    fn suspend_expr(&mut self, expr: &Expr, out: &mut Vec<Stmt>) -> Expr {
+        match &expr.kind {
+            ExprKind::Yield(val) => ExprKind::Yield(self.opt_expr(val, out)).at(expr.span),
+            ExprKind::Await(val) => ExprKind::Await(Box::new(self.expr(val, out))).at(expr.span),
+            _ => self.expr(expr, out),
+        }
+    }
+
+    fn opt_expr(&mut self, expr: &Option<Box<Expr>>, out: &mut Vec<Stmt>) -> Option<Box<Expr>> {
+        expr.as_ref().map(|expr| Box::new(self.expr(expr, out)))
+    }
+
+    // `let @n = val;`, at the position of the expression it replaces
+    fn assign_tmp(&mut self, val: Expr, out: &mut Vec<Stmt>) -> Expr {
+        let name = self.tmp();
+        let span = val.span;
+        out.push(StmtKind::Assign(DeclKind::Let, name.clone(), Some(Box::new(val))).at(span));
+        ExprKind::Ident(name).at(span)
+    }
+
+    // lower an expression, pushing the statements which have to run before it to `out`
+    fn expr(&mut self, expr: &Expr, out: &mut Vec<Stmt>) -> Expr {
+        if !suspends(expr) {
+            return expr.clone();
+        }
+        let span = expr.span;
+        let lowered = match &expr.kind {
+            ExprKind::Yield(val) => {
+                let val = self.opt_expr(val, out);
+                return self.assign_tmp(ExprKind::Yield(val).at(span), out);
+            }
+            ExprKind::YieldStar(val) => {
+                let val = self.expr(val, out);
+                let delegate = self.delegate(val, span);
+                out.push(delegate.at(span));
+                ExprKind::Literal(Literal::Undefined)
+            }
+            ExprKind::Await(val) => {
+                let val = Box::new(self.expr(val, out));
+                return self.assign_tmp(ExprKind::Await(val).at(span), out);
+            }
+            ExprKind::Infix(infix, lhs, rhs) => {
+                let mut parts = self.exprs(&[lhs, rhs], out).into_iter();
+                let lhs = parts.next().unwrap();
+                let rhs = parts.next().unwrap();
+                ExprKind::Infix(infix.clone(), Box::new(lhs), Box::new(rhs))
+            }
+            // `{ const % = lhs; @r = body; }`, so that the body keeps a scope of its own
+            ExprKind::Pipe(lhs, body) => {
+                let lhs = self.expr(lhs, out);
+                let name = self.tmp();
+                out.push(StmtKind::Assign(DeclKind::Let, name.clone(), None).at(span));
+                let topic =
+                    StmtKind::Assign(DeclKind::Const, String::from("%"), Some(Box::new(lhs)));
+                let mut block = vec![topic.at(span)];
+                let body = self.expr(body, &mut block);
+                block.push(StmtKind::Reassign(name.clone(), Box::new(body)).at(span));
+                out.push(StmtKind::Block(block).at(span));
+                ExprKind::Ident(name)
+            }
+            ExprKind::Prefix(prefix, val) => {
+                ExprKind::Prefix(prefix.clone(), Box::new(self.expr(val, out)))
+            }
+            ExprKind::Parens(val) => ExprKind::Parens(Box::new(self.expr(val, out))),
+            ExprKind::Spread(val) => ExprKind::Spread(Box::new(self.expr(val, out))),
+            ExprKind::Member(obj, name) => {
+                ExprKind::Member(Box::new(self.expr(obj, out)), name.clone())
+            }
+            ExprKind::FctCall(name, params) => {
+                let params: Vec<&Expr> = params.iter().collect();
+                ExprKind::FctCall(name.clone(), self.exprs(&params, out))
+            }
+            ExprKind::MethodCall(obj, name, params) => {
+                let mut parts: Vec<&Expr> = vec![obj];
+                parts.extend(params.iter());
+                let mut parts = self.exprs(&parts, out);
+                let obj = parts.remove(0);
+                ExprKind::MethodCall(Box::new(obj), name.clone(), parts)
+            }
+            ExprKind::Literal(Literal::Array(elts)) => {
+                let elts: Vec<&Expr> = elts.iter().collect();
+                ExprKind::Literal(Literal::Array(self.exprs(&elts, out)))
+            }
+            ExprKind::Literal(Literal::Object(props)) => {
+                // computed keys and values, in evaluation order. shorthands become plain
+                // values, so they can be saved like the rest
+                let mut parts = vec![];
+                for prop in props {
+                    if let Some(key) = computed_key(prop) {
+                        parts.push(key.clone())
+                    }
+                    match prop {
+                        Prop::Value(_, val) => parts.push(val.clone()),
+                        Prop::Shorthand(name) => parts.push(ExprKind::Ident(name.clone()).at(span)),
+                        _ => {}
+                    }
+                }
+                let parts: Vec<&Expr> = parts.iter().collect();
+                let mut parts = self.exprs(&parts, out).into_iter();
+                let mut lowered = vec![];
+                for prop in props {
+                    let mut key = |key: &PropKey| match key {
+                        PropKey::Computed(_) => PropKey::Computed(Box::new(parts.next().unwrap())),
+                        key => key.clone(),
+                    };
+                    let prop = match prop {
+                        Prop::Value(name, _) => Prop::Value(key(name), parts.next().unwrap()),
+                        Prop::Shorthand(name) => {
+                            Prop::Value(PropKey::Name(name.clone()), parts.next().unwrap())
+                        }
+                        Prop::Method(name, def) => Prop::Method(key(name), def.clone()),
+                        Prop::Getter(name, def) => Prop::Getter(key(name), def.clone()),
+                        Prop::Setter(name, def) => Prop::Setter(key(name), def.clone()),
+                    };
+                    lowered.push(prop)
+                }
+                ExprKind::Literal(Literal::Object(lowered))
+            }
+            _ => return expr.clone(),
+        };
+        lowered.at(span)
+    }
+
+    // lower sibling expressions. the ones evaluated before the last yield are saved into
+    // temporaries, so they are not affected by whatever runs while the generator is suspended
+    fn exprs(&mut self, exprs: &[&Expr], out: &mut Vec<Stmt>) -> Vec<Expr> {
+        let last = exprs.iter().rposition(|expr| suspends(expr));
+        let mut lowered = vec![];
+        for (idx, expr) in exprs.iter().enumerate() {
+            let expr = match last {
+                Some(last) if idx < last => {
+                    let expr = self.expr(expr, out);
+                    self.spill(expr, out)
+                }
+                Some(last) if idx == last => self.expr(expr, out),
+                _ => (*expr).clone(),
+            };
+            lowered.push(expr)
+        }
+        lowered
+    }
+
+    fn spill(&mut self, expr: Expr, out: &mut Vec<Stmt>) -> Expr {
+        let span = expr.span;
+        match expr.kind {
+            ExprKind::Literal(Literal::Str(_))
+            | ExprKind::Literal(Literal::Int(_))
+            | ExprKind::Literal(Literal::BigInt(_))
+            | ExprKind::Literal(Literal::Num(_))
+            | ExprKind::Literal(Literal::Bool(_))
+            | ExprKind::Literal(Literal::Null)
+            | ExprKind::Literal(Literal::Undefined) => expr,
+            // a spread is saved as an array, and spread again
+            ExprKind::Spread(val) => {
+                let arr = ExprKind::Literal(Literal::Array(vec![ExprKind::Spread(val).at(span)]))
+                    .at(span);
+                ExprKind::Spread(Box::new(self.spill(arr, out))).at(span)
+            }
+            _ => self.assign_tmp(expr, out),
+        }
+    }
+}
+
+// whether evaluating the expression can suspend the frame
+fn suspends(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Yield(_) | ExprKind::YieldStar(_) | ExprKind::Await(_) => true,
+        ExprKind::Infix(_, lhs, rhs) | ExprKind::Pipe(lhs, rhs) => suspends(lhs) || suspends(rhs),
+        ExprKind::Prefix(_, val)
+        | ExprKind::Parens(val)
+        | ExprKind::Spread(val)
+        | ExprKind::Member(val, _) => suspends(val),
+        ExprKind::FctCall(_, params) => params.iter().any(suspends),
+        ExprKind::MethodCall(obj, _, params) => suspends(obj) || params.iter().any(suspends),
+        ExprKind::Literal(Literal::Array(elts)) => elts.iter().any(suspends),
+        ExprKind::Literal(Literal::Object(props)) => props.iter().any(|prop| {
+            let val = match prop {
+                Prop::Value(_, val) => Some(val),
+                _ => None,
+            };
+            computed_key(prop).into_iter().chain(val).any(suspends)
+        }),
+        ExprKind::Literal(_)
+        | ExprKind::Ident(_)
+        | ExprKind::This
+        | ExprKind::Topic
+        | ExprKind::Function(_) => false,
+    }
+}
+
+fn computed_key(prop: &Prop) -> Option<&Expr> {
+    match prop {
+        Prop::Value(PropKey::Computed(key), _)
+        | Prop::Method(PropKey::Computed(key), _)
+        | Prop::Getter(PropKey::Computed(key), _)
+        | Prop::Setter(PropKey::Computed(key), _) => Some(key),
+        _ => None,
+    }
+}
@@ -0,0 +1,117 @@
+use crate::runner::value::{self, GeneratorRef, Property};
+use crate::runner::{Error, Result, Runner, Value};
+
+// the iterator protocol: anything `for...of` can walk through.
+// new iterable types only need to implement this trait and be returned by `values`
+pub trait ValueIter {
+    fn next(&mut self, runner: &mut Runner) -> Result<Option<Value>>;
+}
+
+struct ArrayIter {
+    elts: Vec<Value>,
+    idx: usize,
+}
+
+impl ValueIter for ArrayIter {
+    fn next(&mut self, _runner: &mut Runner) -> Result<Option<Value>> {
+        let elt = self.elts.get(self.idx).cloned();
+        self.idx += 1;
+        Ok(elt)
+    }
+}
+
+struct StrIter {
+    chars: Vec<char>,
+    idx: usize,
+}
+
+impl ValueIter for StrIter {
+    fn next(&mut self, _runner: &mut Runner) -> Result<Option<Value>> {
+        let c = self.chars.get(self.idx).map(|c| Value::Str(c.to_string()));
+        self.idx += 1;
+        Ok(c)
+    }
+}
+
+struct GeneratorIter {
+    gen: GeneratorRef,
+}
+
+impl ValueIter for GeneratorIter {
+    fn next(&mut self, runner: &mut Runner) -> Result<Option<Value>> {
+        match runner.resume_generator(&self.gen, Value::Undefined)? {
+            (_, true) => Ok(None),
+            (val, false) => Ok(Some(val)),
+        }
+    }
+}
+
+// any object with a `next()` method returning `{ value, done }` is an iterator
+struct ObjectIter {
+    obj: Value,
+    next: Value,
+}
+
+impl ValueIter for ObjectIter {
+    fn next(&mut self, runner: &mut Runner) -> Result<Option<Value>> {
+        let res = runner.call_method(&self.next, &self.obj, &[])?;
+        if !matches!(res, Value::Object(_)) {
+            return Err(Error::Type(format!(
+                "Iterator result {} is not an object",
+                res
+            )));
+        }
+        match value::truthy(&runner.get_prop(&res, "done")?) {
+            true => Ok(None),
+            false => Ok(Some(runner.get_prop(&res, "value")?)),
+        }
+    }
+}
+
+// the values of an iterable, for `for...of` and spreads
+pub fn values(val: &Value) -> Result<Box<dyn ValueIter>> {
+    match val {
+        Value::Array(elts) => Ok(Box::new(ArrayIter {
+            elts: elts.clone(),
+            idx: 0,
+        })),
+        Value::Str(s) => Ok(Box::new(StrIter {
+            chars: s.chars().collect(),
+            idx: 0,
+        })),
+        Value::Generator(gen) => Ok(Box::new(GeneratorIter { gen: gen.clone() })),
+        Value::Object(props) => match props.0.borrow().get("next") {
+            Some(Property::Data(next @ Value::Function(_))) => Ok(Box::new(ObjectIter {
+                obj: val.clone(),
+                next: next.clone(),
+            })),
+            _ => Err(Error::Type(String::from(
+                "Object is not iterable, it has no next() method",
+            ))),
+        },
+        _ => Err(Error::Type(format!(
+            "{} is not iterable",
+            value::type_of(val)
+        ))),
+    }
+}
+
+// the enumerable keys, in insertion order, for `for...in`
+pub fn keys(val: &Value) -> Result<Vec<String>> {
+    match val {
+        Value::Object(props) => Ok(props
+            .0
+            .borrow()
+            .props
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()),
+        Value::Array(elts) => Ok((0..elts.len()).map(|idx| idx.to_string()).collect()),
+        Value::Str(s) => Ok((0..s.chars().count()).map(|idx| idx.to_string()).collect()),
+        Value::Null | Value::Undefined => Ok(vec![]),
+        _ => Err(Error::Type(format!(
+            "{} has no enumerable keys",
+            value::type_of(val)
+        ))),
+    }
+}
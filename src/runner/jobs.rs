@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use crate::runner::promise::Settled;
+use crate::runner::value::PromiseRef;
+use crate::runner::{Error, Result, Runner};
+
+pub type Job = Box<dyn FnOnce(&mut Runner) -> Result<()>>;
+
+// The job queues. Time is virtual: nothing ever waits, the clock jumps straight to
+// the next timer once there are no microtasks left. So runs are deterministic.
+#[derive(Default)]
+pub struct Jobs {
+    microtasks: VecDeque<Job>,
+    timers: Vec<Timer>,
+    // in ms
+    pub clock: u64,
+    nb_timers: i64,
+}
+
+struct Timer {
+    at: u64,
+    id: i64,
+    job: Job,
+}
+
+impl Runner {
+    pub fn queue_microtask(&mut self, job: Job) {
+        self.jobs.microtasks.push_back(job)
+    }
+
+    // run `job` once the clock has moved `delay` ms forward. returns the id of the timer
+    pub fn set_timer(&mut self, delay: u64, job: Job) -> i64 {
+        self.jobs.nb_timers += 1;
+        let id = self.jobs.nb_timers;
+        // a host can give any delay: the clock stops at its maximum rather than overflow
+        let at = self.jobs.clock.saturating_add(delay);
+        self.jobs.timers.push(Timer { at, id, job });
+        id
+    }
+
+    pub fn clear_timer(&mut self, id: i64) {
+        self.jobs.timers.retain(|timer| timer.id != id)
+    }
+
+    // run jobs until there are none left
+    pub fn run_jobs(&mut self) -> Result<()> {
+        while self.run_next_job()? {}
+        Ok(())
+    }
+
+    // run jobs until the promise is settled, for awaits at the top level of the program
+    pub fn run_jobs_until(&mut self, promise: &PromiseRef) -> Result<Settled> {
+        let res = Rc::new(RefCell::new(None));
+        let res2 = res.clone();
+        self.subscribe(
+            promise,
+            Box::new(move |_runner, settled| {
+                *res2.borrow_mut() = Some(settled);
+                Ok(())
+            }),
+        );
+        loop {
+            if let Some(settled) = res.borrow_mut().take() {
+                return Ok(settled);
+            }
+            if !self.run_next_job()? {
+                return Err(Error::NeverSettled);
+            }
+        }
+    }
+
+    // microtasks go first, then the earliest timer. returns false when there is nothing left to run
+    fn run_next_job(&mut self) -> Result<bool> {
+        if let Some(job) = self.jobs.microtasks.pop_front() {
+            job(self)?;
+            return Ok(true);
+        }
+        let next = (self.jobs.timers.iter().enumerate())
+            .min_by_key(|(_, timer)| (timer.at, timer.id))
+            .map(|(idx, _)| idx);
+        match next {
+            Some(idx) => {
+                let timer = self.jobs.timers.remove(idx);
+                self.jobs.clock = timer.at;
+                (timer.job)(self)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
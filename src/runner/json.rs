@@ -0,0 +1,612 @@
+use std::collections::HashMap;
+
+use crate::bigint::BigInt;
+use crate::parser::ast::{num_to_string, quote_str, Span};
+use crate::parser::lexer::{is_ident_part, is_ident_start};
+use crate::parser::{self, MAX_NESTING};
+use crate::runner::value::ObjectRef;
+use crate::runner::{Error, Result, Runner, Value};
+
+impl Runner {
+    // like JSON.stringify: getters are called, and there is no json for undefined and functions,
+    // which are left out of objects and are null in arrays. NaN and Infinity, which json
+    // can't represent either, are null too.
+    // `seen` holds the objects being converted, to report cycles
+    pub fn stringify_json(
+        &mut self,
+        val: &Value,
+        seen: &mut Vec<ObjectRef>,
+    ) -> Result<Option<String>> {
+        let json = match val {
+            Value::Undefined | Value::Function(_) | Value::Native(_) | Value::Generator(_) => {
+                return Ok(None)
+            }
+            Value::Null => String::from("null"),
+            Value::Bool(b) => b.to_string(),
+            Value::Int(n) => n.to_string(),
+            // javascript throws, but the json of a large id is what it was loaded from
+            Value::BigInt(n) => n.to_string(),
+            Value::Num(n) if n.is_finite() => num_to_string(*n),
+            Value::Num(_) => String::from("null"),
+            Value::Str(s) => quote_str(s),
+            // no enumerable properties
+            Value::Promise(_) => String::from("{}"),
+            Value::Array(elts) => {
+                let mut parts = vec![];
+                for elt in elts {
+                    parts.push(
+                        self.stringify_json(elt, seen)?
+                            .unwrap_or_else(|| String::from("null")),
+                    );
+                }
+                format!("[{}]", parts.join(","))
+            }
+            Value::Object(obj) => {
+                if seen.contains(obj) {
+                    return Err(Error::Type(String::from(
+                        "Converting circular structure to JSON",
+                    )));
+                }
+                seen.push(obj.clone());
+                let names: Vec<String> = obj
+                    .0
+                    .borrow()
+                    .props
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                let mut parts = vec![];
+                for name in names {
+                    let prop = self.get_prop(val, &name)?;
+                    if let Some(json) = self.stringify_json(&prop, seen)? {
+                        parts.push(format!("{}:{}", quote_str(&name), json));
+                    }
+                }
+                seen.pop();
+                format!("{{{}}}", parts.join(","))
+            }
+        };
+        Ok(Some(json))
+    }
+}
+
+// what to do with a key given twice in an object: json allows it, but doesn't say what it means
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DuplicateKeys {
+    #[default]
+    Error,
+    // like JSON.parse: the last value, at the place of the first
+    LastWins,
+}
+
+// strict json (rfc 8259), straight to values: no comments, trailing commas, unquoted keys or
+// expressions. a byte order mark at the start is skipped
+pub fn parse_json(src: &str, duplicates: DuplicateKeys) -> parser::Result<Value> {
+    parse(src, duplicates, false)
+}
+
+// json5 (https://spec.json5.org), and so json with comments too: comments, trailing commas,
+// single quotes, unquoted keys, hex numbers, `+1`, `.5`, `Infinity` and `NaN`. the same json
+// gives the same values as `parse_json`
+pub fn parse_json5(src: &str, duplicates: DuplicateKeys) -> parser::Result<Value> {
+    parse(src, duplicates, true)
+}
+
+fn parse(src: &str, duplicates: DuplicateKeys, json5: bool) -> parser::Result<Value> {
+    let src = src.strip_prefix('\u{feff}').unwrap_or(src);
+    let mut parser = JsonParser {
+        src,
+        pos: 0,
+        depth: 0,
+        duplicates,
+        json5,
+    };
+    parser.whitespace()?;
+    let val = parser.value()?;
+    parser.whitespace()?;
+    if parser.pos < src.len() {
+        return Err(parser.expected(&format!("the end of the {}", parser.name())));
+    }
+    Ok(val)
+}
+
+struct JsonParser<'a> {
+    src: &'a str,
+    // a byte offset
+    pos: usize,
+    // nested arrays and objects, see `MAX_NESTING`
+    depth: usize,
+    duplicates: DuplicateKeys,
+    json5: bool,
+}
+
+impl JsonParser<'_> {
+    fn name(&self) -> &'static str {
+        match self.json5 {
+            true => "json5",
+            false => "json",
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        self.pos += found as usize;
+        found
+    }
+
+    fn whitespace(&mut self) -> parser::Result<()> {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                _ if !self.json5 => return Ok(()),
+                Some(b'/') if self.src[self.pos..].starts_with("//") => {
+                    let rest = &self.src[self.pos..];
+                    self.pos += rest
+                        .find(['\n', '\r', '\u{2028}', '\u{2029}'])
+                        .unwrap_or(rest.len());
+                }
+                Some(b'/') if self.src[self.pos..].starts_with("/*") => {
+                    match self.src[self.pos + 2..].find("*/") {
+                        Some(len) => self.pos += len + 4,
+                        None => {
+                            return Err(self.error(
+                                self.pos,
+                                self.pos + 2,
+                                String::from("Unterminated comment"),
+                            ))
+                        }
+                    }
+                }
+                // vertical tab, form feed, no-break space, byte order mark, line separators...
+                // but not the next line char
+                _ => match self.src[self.pos..].chars().next() {
+                    Some(c) if c.is_whitespace() && c != '\u{85}' || c == '\u{feff}' => {
+                        self.pos += c.len_utf8()
+                    }
+                    _ => return Ok(()),
+                },
+            }
+        }
+    }
+
+    fn error(&self, start: usize, end: usize, message: String) -> parser::Error {
+        let span = Span { start, end };
+        let (line, col) = span.line_col(self.src);
+        parser::Error::Syntax {
+            line,
+            col,
+            span,
+            message,
+        }
+    }
+
+    // what is found is a word, like `undefined` or an unquoted key, or a single char
+    fn expected(&self, what: &str) -> parser::Error {
+        let rest = &self.src[self.pos..];
+        let found: String = match rest.chars().next() {
+            None => {
+                return self.error(
+                    self.pos,
+                    self.pos,
+                    format!("expected {}, found the end of the input", what),
+                )
+            }
+            Some(c) if c.is_alphanumeric() || c == '_' => rest
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .take(40)
+                .collect(),
+            Some(c) => c.to_string(),
+        };
+        let message = format!("expected {}, found `{}`", what, found);
+        self.error(self.pos, self.pos + found.len(), message)
+    }
+
+    fn value(&mut self) -> parser::Result<Value> {
+        match self.peek() {
+            Some(b'{') => self.nested(|parser| parser.object()),
+            Some(b'[') => self.nested(|parser| parser.array()),
+            Some(b'"') => Ok(Value::Str(self.string()?)),
+            Some(b'\'') if self.json5 => Ok(Value::Str(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'+' | b'.') if self.json5 => self.number(),
+            _ => {
+                for (word, val) in [
+                    ("true", Value::Bool(true)),
+                    ("false", Value::Bool(false)),
+                    ("null", Value::Null),
+                ] {
+                    if self.src[self.pos..].starts_with(word) {
+                        self.pos += word.len();
+                        return Ok(val);
+                    }
+                }
+                if self.json5 {
+                    if let Some(n) = self.special_number() {
+                        return Ok(Value::Num(n));
+                    }
+                }
+                Err(self.expected(&format!("a {} value", self.name())))
+            }
+        }
+    }
+
+    // the parser is recursive: the depth is limited so nesting too deep is an error, not a stack overflow
+    fn nested(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> parser::Result<Value>,
+    ) -> parser::Result<Value> {
+        if self.depth >= MAX_NESTING {
+            let message = format!("Nested too deeply: more than {} levels", MAX_NESTING);
+            return Err(self.error(self.pos, self.pos + 1, message));
+        }
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+
+    fn array(&mut self) -> parser::Result<Value> {
+        self.pos += 1;
+        self.whitespace()?;
+        let mut elts = vec![];
+        if self.eat(b']') {
+            return Ok(Value::Array(elts));
+        }
+        loop {
+            elts.push(self.value()?);
+            self.whitespace()?;
+            if self.eat(b']') {
+                return Ok(Value::Array(elts));
+            }
+            if !self.eat(b',') {
+                return Err(self.expected("`,` or `]` after the array element"));
+            }
+            self.whitespace()?;
+            // a trailing comma
+            if self.json5 && self.eat(b']') {
+                return Ok(Value::Array(elts));
+            }
+        }
+    }
+
+    fn object(&mut self) -> parser::Result<Value> {
+        self.pos += 1;
+        self.whitespace()?;
+        let mut props: Vec<(String, Value)> = vec![];
+        // the index of each key in `props`
+        let mut keys: HashMap<String, usize> = HashMap::new();
+        if self.eat(b'}') {
+            return Ok(Value::Object(ObjectRef::new(props)));
+        }
+        loop {
+            let start = self.pos;
+            let key = match self.peek() {
+                Some(b'"') => self.string()?,
+                Some(b'\'') if self.json5 => self.string()?,
+                _ if self.json5 => self.ident()?,
+                _ => return Err(self.expected("a string key")),
+            };
+            let end = self.pos;
+            self.whitespace()?;
+            if !self.eat(b':') {
+                return Err(self.expected("`:` after the key"));
+            }
+            self.whitespace()?;
+            let val = self.value()?;
+            match keys.get(&key) {
+                Some(&idx) if self.duplicates == DuplicateKeys::LastWins => props[idx].1 = val,
+                Some(_) => {
+                    return Err(self.error(
+                        start,
+                        end,
+                        format!("Duplicate key {}", quote_str(&key)),
+                    ))
+                }
+                None => {
+                    keys.insert(key.clone(), props.len());
+                    props.push((key, val));
+                }
+            }
+            self.whitespace()?;
+            if self.eat(b'}') {
+                return Ok(Value::Object(ObjectRef::new(props)));
+            }
+            if !self.eat(b',') {
+                return Err(self.expected("`,` or `}` after the property"));
+            }
+            self.whitespace()?;
+            if self.json5 && self.eat(b'}') {
+                return Ok(Value::Object(ObjectRef::new(props)));
+            }
+        }
+    }
+
+    // an unquoted json5 key: an identifier name, reserved words included, with maybe `\u` escapes
+    fn ident(&mut self) -> parser::Result<String> {
+        let mut res = String::new();
+        loop {
+            let start = self.pos;
+            let c = match self.src[self.pos..].chars().next() {
+                Some('\\') if self.src[self.pos + 1..].starts_with('u') => {
+                    self.pos += 2;
+                    char::from_u32(self.hex_escape(start, 4)?)
+                }
+                Some(c) => {
+                    self.pos += c.len_utf8();
+                    Some(c)
+                }
+                None => None,
+            };
+            match c {
+                Some(c)
+                    if res.is_empty() && is_ident_start(c)
+                        || !res.is_empty() && is_ident_part(c) =>
+                {
+                    res.push(c)
+                }
+                _ => {
+                    self.pos = start;
+                    break;
+                }
+            }
+        }
+        if res.is_empty() {
+            return Err(self.expected("a key"));
+        }
+        Ok(res)
+    }
+
+    // json strings are double quoted, json5 ones can be single quoted too
+    fn string(&mut self) -> parser::Result<String> {
+        let start = self.pos;
+        let quote = self.src.as_bytes()[start];
+        self.pos += 1;
+        let mut res = String::new();
+        loop {
+            // the plain chars up to the next quote, backslash or control char. json5 only forbids line breaks
+            let rest = &self.src.as_bytes()[self.pos..];
+            let len = rest
+                .iter()
+                .position(|&c| {
+                    c == quote
+                        || c == b'\\'
+                        || c < 0x20 && (!self.json5 || c == b'\n' || c == b'\r')
+                })
+                .unwrap_or(rest.len());
+            res.push_str(&self.src[self.pos..self.pos + len]);
+            self.pos += len;
+            match self.peek() {
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(res);
+                }
+                Some(b'\\') => res.extend(self.escape()?),
+                Some(_) if self.json5 => {
+                    let message = String::from("Line breaks must be escaped in json5 strings");
+                    return Err(self.error(self.pos, self.pos + 1, message));
+                }
+                Some(_) => {
+                    let message =
+                        String::from("Control characters must be escaped in json strings");
+                    return Err(self.error(self.pos, self.pos + 1, message));
+                }
+                None => {
+                    return Err(self.error(start, self.pos, String::from("Unterminated string")))
+                }
+            }
+        }
+    }
+
+    // `\n`, `\"`, `é`, or a utf-16 surrogate pair: `😀`.
+    // json5 has the javascript ones too: `\'`, `\v`, `\0`, `\x41`, `\q` for `q`, and a backslash
+    // before a line break continues the string on the next line
+    fn escape(&mut self) -> parser::Result<Option<char>> {
+        let start = self.pos;
+        self.pos += 1;
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let unit = self.hex_escape(start, 4)?;
+                if (0xD800..0xDC00).contains(&unit) && self.src[self.pos..].starts_with("\\u") {
+                    let pos = self.pos;
+                    self.pos += 2;
+                    let low = self.hex_escape(pos, 4)?;
+                    if (0xDC00..0xE000).contains(&low) {
+                        let code = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+                        return Ok(char::from_u32(code));
+                    }
+                    self.pos = pos;
+                }
+                // a lone surrogate can't be represented in a rust string
+                return Ok(Some(
+                    char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER),
+                ));
+            }
+            Some(b'x') if self.json5 => {
+                self.pos += 1;
+                return Ok(char::from_u32(self.hex_escape(start, 2)?));
+            }
+            Some(b'\'') if self.json5 => '\'',
+            Some(b'v') if self.json5 => '\u{b}',
+            Some(b'0')
+                if self.json5
+                    && !matches!(self.src.as_bytes().get(self.pos + 1), Some(b'0'..=b'9')) =>
+            {
+                '\0'
+            }
+            Some(b'\r') if self.json5 => {
+                self.pos += 1;
+                self.eat(b'\n');
+                return Ok(None);
+            }
+            Some(b'\n') if self.json5 => {
+                self.pos += 1;
+                return Ok(None);
+            }
+            Some(b'0'..=b'9') | None => return Err(self.invalid_escape(start)),
+            Some(_) if self.json5 => {
+                let c = self.src[self.pos..].chars().next().unwrap();
+                self.pos += c.len_utf8();
+                return Ok(Some(c).filter(|c| !matches!(c, '\u{2028}' | '\u{2029}')));
+            }
+            Some(_) => return Err(self.invalid_escape(start)),
+        };
+        self.pos += 1;
+        Ok(Some(c))
+    }
+
+    fn invalid_escape(&self, start: usize) -> parser::Error {
+        let len = self.src[self.pos..]
+            .chars()
+            .next()
+            .map_or(0, char::len_utf8);
+        let message = format!(
+            "Invalid escape in a {} string: `{}`",
+            self.name(),
+            &self.src[start..self.pos + len]
+        );
+        self.error(start, self.pos + len, message)
+    }
+
+    // the `len` hex digits of a `\u` or `\x` escape
+    fn hex_escape(&mut self, start: usize, len: usize) -> parser::Result<u32> {
+        let hex = &self.src.as_bytes()[self.pos..];
+        let found = hex
+            .iter()
+            .take(len)
+            .take_while(|c| c.is_ascii_hexdigit())
+            .count();
+        if found < len {
+            let end = self.pos + found;
+            let kind = if len == 4 { "unicode" } else { "hex" };
+            let message = format!("Invalid {} escape: `{}`", kind, &self.src[start..end]);
+            return Err(self.error(start, end, message));
+        }
+        let hex = &self.src[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(u32::from_str_radix(hex, 16).unwrap())
+    }
+
+    // `-12`, `0.5`, `1e-3`: no leading zeros, `+`, `.5`, `1.`, hex, NaN or Infinity, unless it is json5.
+    // integers stay exact: those which don't fit in an Int are BigInts
+    fn number(&mut self) -> parser::Result<Value> {
+        let start = self.pos;
+        let negative = self.eat(b'-');
+        if !negative && self.json5 {
+            self.eat(b'+');
+        }
+        if self.json5 {
+            if let Some(n) = self.special_number() {
+                return Ok(Value::Num(if negative { -n } else { n }));
+            }
+            if let Some(b'x' | b'X') = self
+                .src
+                .as_bytes()
+                .get(self.pos + 1)
+                .filter(|_| self.peek() == Some(b'0'))
+            {
+                return self.hex(negative);
+            }
+        }
+        let int_start = self.pos;
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.digits(),
+            // `.5`
+            Some(b'.') if self.json5 => {}
+            _ => return Err(self.expected("a digit")),
+        }
+        if let Some(b'0'..=b'9') = self.peek() {
+            let message = format!("Leading zeros are not allowed in {} numbers", self.name());
+            return Err(self.error(start, self.pos + 1, message));
+        }
+        let mut is_int = true;
+        if self.eat(b'.') {
+            is_int = false;
+            let has_int = self.pos - 1 > int_start;
+            match self.peek() {
+                Some(b'0'..=b'9') => self.digits(),
+                // `1.`
+                _ if self.json5 && has_int => {}
+                _ => return Err(self.expected("a digit after `.`")),
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            is_int = false;
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.expected("a digit in the exponent"));
+            }
+            self.digits();
+        }
+        let text = &self.src[start..self.pos];
+        if is_int {
+            if let Ok(n) = text.parse::<i64>() {
+                return Ok(Value::Int(n));
+            }
+            if let Some(n) = BigInt::parse(text, 10) {
+                return Ok(Value::BigInt(n));
+            }
+        }
+        // always valid: too large is Infinity
+        Ok(Value::Num(text.parse().unwrap_or(f64::NAN)))
+    }
+
+    // json5's `Infinity` and `NaN`
+    fn special_number(&mut self) -> Option<f64> {
+        for (word, n) in [("Infinity", f64::INFINITY), ("NaN", f64::NAN)] {
+            if self.src[self.pos..].starts_with(word) {
+                self.pos += word.len();
+                return Some(n);
+            }
+        }
+        None
+    }
+
+    // `0x1F`: an integer, exact like the others
+    fn hex(&mut self, negative: bool) -> parser::Result<Value> {
+        self.pos += 2;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.expected("a hex digit"));
+        }
+        let hex = &self.src[start..self.pos];
+        let n = match BigInt::parse(hex, 16) {
+            Some(n) if negative => n.neg(),
+            Some(n) => n,
+            None => {
+                let message = String::from("Maximum BigInt size exceeded");
+                return Err(self.error(start, self.pos, message));
+            }
+        };
+        match n.to_i64() {
+            Some(n) => Ok(Value::Int(n)),
+            None => Ok(Value::BigInt(n)),
+        }
+    }
+
+    fn digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use crate::parser::ast::num_to_string;
+use crate::runner::coerce;
+use crate::runner::value::{self, Type};
+use crate::runner::{Error, Result, Runner, Value};
+
+// receiver, then params
+pub type MethodFuncSign = fn(&mut Runner, &Value, &[Value]) -> Result<Value>;
+
+pub struct Method {
+    pub nb_args: usize,
+    // trailing params which can be left out
+    pub nb_optional: usize,
+    // a getter is accessed without parens, like `arr.length`
+    pub getter: bool,
+    pub func: MethodFuncSign,
+}
+
+pub type Methods = HashMap<Type, HashMap<String, Method>>;
+
+fn method(nb_args: usize, func: MethodFuncSign) -> Method {
+    optional_method(nb_args, 0, func)
+}
+
+fn optional_method(nb_args: usize, nb_optional: usize, func: MethodFuncSign) -> Method {
+    Method {
+        nb_args,
+        nb_optional,
+        getter: false,
+        func,
+    }
+}
+
+fn getter(func: MethodFuncSign) -> Method {
+    Method {
+        nb_args: 0,
+        nb_optional: 0,
+        getter: true,
+        func,
+    }
+}
+
+pub fn new() -> Methods {
+    let mut methods = Methods::new();
+
+    let mut str_methods = HashMap::<String, Method>::new();
+    str_methods.insert(String::from("length"), getter(str_length));
+    str_methods.insert(String::from("toUpperCase"), method(0, str_to_upper_case));
+    str_methods.insert(String::from("toLowerCase"), method(0, str_to_lower_case));
+    str_methods.insert(String::from("trim"), method(0, str_trim));
+    str_methods.insert(String::from("split"), optional_method(0, 1, str_split));
+    str_methods.insert(String::from("includes"), method(1, str_includes));
+    str_methods.insert(String::from("startsWith"), method(1, str_starts_with));
+    str_methods.insert(String::from("endsWith"), method(1, str_ends_with));
+    str_methods.insert(String::from("indexOf"), method(1, str_index_of));
+    str_methods.insert(String::from("repeat"), method(1, str_repeat));
+    str_methods.insert(String::from("toString"), method(0, to_string));
+    methods.insert(Type::Str, str_methods);
+
+    let mut num_methods = HashMap::<String, Method>::new();
+    num_methods.insert(String::from("toFixed"), optional_method(0, 1, num_to_fixed));
+    num_methods.insert(String::from("toString"), method(0, to_string));
+    methods.insert(Type::Num, num_methods);
+
+    let mut int_methods = HashMap::<String, Method>::new();
+    int_methods.insert(String::from("toFixed"), optional_method(0, 1, num_to_fixed));
+    int_methods.insert(String::from("toString"), method(0, to_string));
+    methods.insert(Type::Int, int_methods);
+
+    let mut bigint_methods = HashMap::<String, Method>::new();
+    bigint_methods.insert(String::from("toString"), method(0, to_string));
+    methods.insert(Type::BigInt, bigint_methods);
+
+    let mut array_methods = HashMap::<String, Method>::new();
+    array_methods.insert(String::from("length"), getter(array_length));
+    array_methods.insert(String::from("join"), optional_method(0, 1, array_join));
+    array_methods.insert(String::from("includes"), method(1, array_includes));
+    array_methods.insert(String::from("indexOf"), method(1, array_index_of));
+    array_methods.insert(String::from("concat"), method(1, array_concat));
+    array_methods.insert(String::from("reverse"), method(0, array_reverse));
+    array_methods.insert(String::from("toString"), method(0, to_string));
+    methods.insert(Type::Array, array_methods);
+
+    let mut bool_methods = HashMap::<String, Method>::new();
+    bool_methods.insert(String::from("toString"), method(0, to_string));
+    methods.insert(Type::Bool, bool_methods);
+
+    let mut generator_methods = HashMap::<String, Method>::new();
+    generator_methods.insert(String::from("next"), optional_method(0, 1, generator_next));
+    methods.insert(Type::Generator, generator_methods);
+
+    let mut promise_methods = HashMap::<String, Method>::new();
+    promise_methods.insert(String::from("then"), optional_method(1, 1, promise_then));
+    promise_methods.insert(String::from("catch"), method(1, promise_catch));
+    methods.insert(Type::Promise, promise_methods);
+
+    methods
+}
+
+fn to_string(_runner: &mut Runner, this: &Value, _params: &[Value]) -> Result<Value> {
+    Ok(Value::Str(coerce::to_string(this)))
+}
+
+fn str_length(_runner: &mut Runner, this: &Value, _params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    Ok(Value::Int(s.chars().count() as i64))
+}
+
+fn str_to_upper_case(_runner: &mut Runner, this: &Value, _params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    Ok(Value::Str(s.to_uppercase()))
+}
+
+fn str_to_lower_case(_runner: &mut Runner, this: &Value, _params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    Ok(Value::Str(s.to_lowercase()))
+}
+
+fn str_trim(_runner: &mut Runner, this: &Value, _params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    Ok(Value::Str(s.trim().to_string()))
+}
+
+fn str_split(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    // without a separator, the whole string is the only part
+    let sep = match params.first() {
+        Some(sep) => value::as_string(sep)?,
+        None => return Ok(Value::Array(vec![Value::Str(s.clone())])),
+    };
+    let parts = if sep.is_empty() {
+        s.chars().map(|c| Value::Str(c.to_string())).collect()
+    } else {
+        s.split(sep.as_str())
+            .map(|part| Value::Str(part.to_string()))
+            .collect()
+    };
+    Ok(Value::Array(parts))
+}
+
+fn str_includes(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    let needle = value::as_string(params.first().unwrap())?;
+    Ok(Value::Bool(s.contains(needle.as_str())))
+}
+
+fn str_starts_with(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    let prefix = value::as_string(params.first().unwrap())?;
+    Ok(Value::Bool(s.starts_with(prefix.as_str())))
+}
+
+fn str_ends_with(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    let suffix = value::as_string(params.first().unwrap())?;
+    Ok(Value::Bool(s.ends_with(suffix.as_str())))
+}
+
+fn str_index_of(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    let needle = value::as_string(params.first().unwrap())?;
+    let idx = match s.find(needle.as_str()) {
+        // byte offset -> char offset
+        Some(byte_idx) => s[..byte_idx].chars().count() as i64,
+        None => -1,
+    };
+    Ok(Value::Int(idx))
+}
+
+// in bytes, like the limit of javascript engines
+const MAX_STRING_LEN: usize = 1 << 29;
+
+fn str_repeat(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let s = value::as_string(this)?;
+    let count = value::as_i64(params.first().unwrap())?;
+    match usize::try_from(count) {
+        Ok(count) if s.len().saturating_mul(count) <= MAX_STRING_LEN => {
+            Ok(Value::Str(s.repeat(count)))
+        }
+        Ok(_) => Err(Error::Range(String::from("Invalid string length"))),
+        Err(_) => Err(Error::Range(format!("invalid repeat count {}", count))),
+    }
+}
+
+fn num_to_fixed(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let n = value::as_f64(this)?;
+    let digits = match params.first() {
+        Some(digits) => value::as_i64(digits)?,
+        None => 0,
+    };
+    if !(0..=100).contains(&digits) {
+        return Err(Error::Range(format!(
+            "toFixed() digits argument must be between 0 and 100, got {}",
+            digits
+        )));
+    }
+    // like javascript, large numbers keep their exponent
+    if !n.is_finite() || n.abs() >= 1e21 {
+        return Ok(Value::Str(num_to_string(n)));
+    }
+    Ok(Value::Str(to_fixed(n, digits as usize)))
+}
+
+// rust rounds ties to even, javascript away from zero: round the exact decimal value by hand.
+// a f64 has at most 1074 digits after the point
+fn to_fixed(n: f64, digits: usize) -> String {
+    let exact = format!("{:.1074}", n.abs());
+    let point = exact.find('.').unwrap();
+    let mut res: Vec<u8> = exact.as_bytes()[..point + 1 + digits].to_vec();
+    if exact.as_bytes()[point + 1 + digits] >= b'5' {
+        // add one to the last digit, with the carry
+        let mut idx = res.len();
+        loop {
+            if idx == 0 {
+                res.insert(0, b'1');
+                break;
+            }
+            idx -= 1;
+            match res[idx] {
+                b'.' => {}
+                b'9' => res[idx] = b'0',
+                digit => {
+                    res[idx] = digit + 1;
+                    break;
+                }
+            }
+        }
+    }
+    if digits == 0 {
+        res.pop();
+    }
+    let sign = if n < 0.0 { "-" } else { "" };
+    format!("{}{}", sign, String::from_utf8(res).unwrap())
+}
+
+fn array_length(_runner: &mut Runner, this: &Value, _params: &[Value]) -> Result<Value> {
+    let arr = value::as_vec(this)?;
+    Ok(Value::Int(arr.len() as i64))
+}
+
+fn array_join(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let arr = value::as_vec(this)?;
+    let sep = match params.first() {
+        Some(sep) => value::as_string(sep)?,
+        None => ",",
+    };
+    let parts: Vec<String> = arr
+        .iter()
+        .map(|elt| match elt {
+            Value::Null | Value::Undefined => String::new(),
+            elt => coerce::to_string(elt),
+        })
+        .collect();
+    Ok(Value::Str(parts.join(sep)))
+}
+
+fn array_includes(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let arr = value::as_vec(this)?;
+    let needle = params.first().unwrap();
+    Ok(Value::Bool(
+        arr.iter().any(|elt| coerce::same_value_zero(elt, needle)),
+    ))
+}
+
+fn array_index_of(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let arr = value::as_vec(this)?;
+    let needle = params.first().unwrap();
+    let idx = match arr.iter().position(|elt| elt == needle) {
+        Some(idx) => idx as i64,
+        None => -1,
+    };
+    Ok(Value::Int(idx))
+}
+
+fn array_concat(_runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let mut arr = value::as_vec(this)?.clone();
+    match params.first().unwrap() {
+        Value::Array(other) => arr.extend(other.iter().cloned()),
+        other => arr.push(other.clone()),
+    }
+    Ok(Value::Array(arr))
+}
+
+fn array_reverse(_runner: &mut Runner, this: &Value, _params: &[Value]) -> Result<Value> {
+    let mut arr = value::as_vec(this)?.clone();
+    arr.reverse();
+    Ok(Value::Array(arr))
+}
+
+// the param is what the paused yield evaluates to
+fn generator_next(runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    match this {
+        Value::Generator(gen) => {
+            let sent = params.first().cloned().unwrap_or(Value::Undefined);
+            let (val, done) = runner.resume_generator(gen, sent)?;
+            Ok(value::iter_result(val, done))
+        }
+        _ => Err(Error::Type(format!("invalid generator type {}", this))),
+    }
+}
+
+fn promise_then(runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let promise = value::as_promise(this)?;
+    let on_fulfilled = params.first().cloned();
+    let on_rejected = params.get(1).cloned();
+    Ok(Value::Promise(runner.then(
+        promise,
+        on_fulfilled,
+        on_rejected,
+    )))
+}
+
+fn promise_catch(runner: &mut Runner, this: &Value, params: &[Value]) -> Result<Value> {
+    let promise = value::as_promise(this)?;
+    let on_rejected = params.first().cloned();
+    Ok(Value::Promise(runner.then(promise, None, on_rejected)))
+}
@@ -0,0 +1,210 @@
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+
+use crate::parser::ast::FunctionDef;
+use crate::runner::frame::{Completion, Frame};
+use crate::runner::value::PromiseRef;
+use crate::runner::{Error, Result, Runner, Value};
+
+// run once a promise is settled, always as a microtask
+pub type Reaction = Box<dyn FnOnce(&mut Runner, Settled) -> Result<()>>;
+
+#[derive(Clone)]
+pub enum Settled {
+    Fulfilled(Value),
+    // the reason handlers get, and the error which caused the rejection, if any: reported when
+    // no one handles it, with its type and position
+    Rejected(Value, Option<Rc<Error>>),
+}
+
+pub enum State {
+    Pending(Vec<Reaction>),
+    Settled(Settled),
+}
+
+pub struct Promise {
+    pub state: State,
+    // whether anything waits on the promise: a rejection no one handles is an error
+    handled: bool,
+}
+
+// Promises can be created and settled from rust, so a builtin (see `Runner::register_builtin`)
+// can return a pending promise and resolve it later, for example from a timer:
+//
+//     let promise = runner.new_promise();
+//     let res = promise.clone();
+//     runner.set_timer(100, Box::new(move |runner| Ok(runner.resolve_promise(&res, val))));
+//     Ok(Value::Promise(promise))
+//
+// or once the program has run, followed by `run_jobs` to run what waits on it
+impl Runner {
+    pub fn new_promise(&mut self) -> PromiseRef {
+        PromiseRef(Rc::new(RefCell::new(Promise {
+            state: State::Pending(vec![]),
+            handled: false,
+        })))
+    }
+
+    // a promise resolved with another promise follows it
+    pub fn resolve_promise(&mut self, promise: &PromiseRef, val: Value) {
+        match val {
+            Value::Promise(inner) if inner == *promise => {
+                let err = Error::Type(String::from("Chaining cycle detected for promise"));
+                self.reject_with_error(promise, err)
+            }
+            Value::Promise(inner) => {
+                let promise = promise.clone();
+                self.subscribe(
+                    &inner,
+                    Box::new(move |runner, settled| {
+                        runner.settle(&promise, settled);
+                        Ok(())
+                    }),
+                )
+            }
+            val => self.settle(promise, Settled::Fulfilled(val)),
+        }
+    }
+
+    pub fn reject_promise(&mut self, promise: &PromiseRef, reason: Value) {
+        self.settle(promise, Settled::Rejected(reason, None))
+    }
+
+    // the reason is the message of the error
+    pub fn reject_with_error(&mut self, promise: &PromiseRef, err: Error) {
+        let reason = Value::Str(err.to_string());
+        self.settle(promise, Settled::Rejected(reason, Some(Rc::new(err))))
+    }
+
+    // settling an already settled promise does nothing
+    fn settle(&mut self, promise: &PromiseRef, settled: Settled) {
+        let reactions = {
+            let mut inner = promise.0.borrow_mut();
+            let reactions = match &mut inner.state {
+                State::Pending(reactions) => mem::take(reactions),
+                State::Settled(_) => return,
+            };
+            inner.state = State::Settled(settled.clone());
+            reactions
+        };
+        if let Settled::Rejected(..) = settled {
+            self.rejections.push(promise.clone());
+        }
+        for reaction in reactions {
+            let settled = settled.clone();
+            self.queue_microtask(Box::new(move |runner| reaction(runner, settled)));
+        }
+    }
+
+    pub fn subscribe(&mut self, promise: &PromiseRef, reaction: Reaction) {
+        let mut inner = promise.0.borrow_mut();
+        inner.handled = true;
+        match &mut inner.state {
+            State::Pending(reactions) => reactions.push(reaction),
+            State::Settled(settled) => {
+                let settled = settled.clone();
+                drop(inner);
+                self.queue_microtask(Box::new(move |runner| reaction(runner, settled)));
+            }
+        }
+    }
+
+    // `promise.then(f)` and `promise.catch(f)`: a new promise, resolved with what the handler
+    // returns. without a handler for the outcome, it is passed through
+    pub fn then(
+        &mut self,
+        promise: &PromiseRef,
+        on_fulfilled: Option<Value>,
+        on_rejected: Option<Value>,
+    ) -> PromiseRef {
+        let res = self.new_promise();
+        let res2 = res.clone();
+        self.subscribe(
+            promise,
+            Box::new(move |runner, settled| {
+                let handler = match settled {
+                    Settled::Fulfilled(_) => on_fulfilled,
+                    Settled::Rejected(..) => on_rejected,
+                };
+                match (handler, settled) {
+                    (Some(handler), Settled::Fulfilled(val) | Settled::Rejected(val, _)) => {
+                        match runner.call_value(&handler, &[val]) {
+                            Ok(val) => runner.resolve_promise(&res2, val),
+                            Err(err) => runner.reject_with_error(&res2, err),
+                        }
+                    }
+                    (None, settled) => runner.settle(&res2, settled),
+                }
+                Ok(())
+            }),
+        );
+        res
+    }
+
+    // what `await val` waits on: a value which isn't a promise is already fulfilled
+    pub fn promise_of(&mut self, val: Value) -> PromiseRef {
+        match val {
+            Value::Promise(promise) => promise,
+            val => {
+                let promise = self.new_promise();
+                self.resolve_promise(&promise, val);
+                promise
+            }
+        }
+    }
+
+    // an async function runs until its first await, then each await resumes it in a microtask
+    pub fn call_async(&mut self, def: Rc<FunctionDef>, frame: Frame) -> Value {
+        let promise = self.new_promise();
+        let sent = Settled::Fulfilled(Value::Undefined);
+        self.resume_async(def, Box::new(frame), promise.clone(), sent);
+        Value::Promise(promise)
+    }
+
+    fn resume_async(
+        &mut self,
+        def: Rc<FunctionDef>,
+        mut frame: Box<Frame>,
+        promise: PromiseRef,
+        sent: Settled,
+    ) {
+        let sent = match sent {
+            Settled::Fulfilled(val) => val,
+            // there is no try/catch: awaiting a rejected promise rejects the whole function
+            rejected @ Settled::Rejected(..) => return self.settle(&promise, rejected),
+        };
+        match self.run_frame(&def.body, &mut frame, sent) {
+            Ok(Completion::Await(val)) => {
+                let awaited = self.promise_of(val);
+                self.subscribe(
+                    &awaited,
+                    Box::new(move |runner, settled| {
+                        runner.resume_async(def, frame, promise, settled);
+                        Ok(())
+                    }),
+                )
+            }
+            Ok(Completion::Return(val)) => self.resolve_promise(&promise, val),
+            Ok(Completion::Normal) => self.resolve_promise(&promise, Value::Undefined),
+            Ok(Completion::Yield(_)) => {
+                let err = Error::Syntax(String::from("yield is only valid in generator functions"));
+                self.reject_with_error(&promise, err)
+            }
+            Err(err) => self.reject_with_error(&promise, err),
+        }
+    }
+
+    // rejections no one waited on, once everything has run
+    pub fn check_rejections(&mut self) -> Result<()> {
+        for promise in mem::take(&mut self.rejections) {
+            let promise = promise.0.borrow();
+            if let (false, State::Settled(Settled::Rejected(reason, error))) =
+                (promise.handled, &promise.state)
+            {
+                return Err(Error::uncaught(reason, error.clone()));
+            }
+        }
+        Ok(())
+    }
+}
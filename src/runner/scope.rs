@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::runner::{Error, Result, Value};
+
+pub type ScopeRef = Rc<RefCell<Scope>>;
+
+// a set of bindings, chained to the enclosing scope
+pub struct Scope {
+    pub vars: HashMap<String, Value>,
+    consts: HashSet<String>,
+    parent: Option<ScopeRef>,
+}
+
+impl Scope {
+    pub fn new(parent: Option<ScopeRef>) -> ScopeRef {
+        Rc::new(RefCell::new(Scope {
+            vars: HashMap::new(),
+            consts: HashSet::new(),
+            parent,
+        }))
+    }
+
+    pub fn declare(&mut self, name: &str, val: Value, is_const: bool) {
+        if is_const {
+            self.consts.insert(name.to_string());
+        } else {
+            self.consts.remove(name);
+        }
+        self.vars.insert(name.to_string(), val);
+    }
+}
+
+pub fn lookup(scope: &ScopeRef, name: &str) -> Option<Value> {
+    let scope = scope.borrow();
+    match scope.vars.get(name) {
+        Some(val) => Some(val.clone()),
+        None => scope
+            .parent
+            .as_ref()
+            .and_then(|parent| lookup(parent, name)),
+    }
+}
+
+// update an existing binding, in the closest scope which declares it
+pub fn assign(scope: &ScopeRef, name: &str, val: Value) -> Result<()> {
+    let mut scope = scope.borrow_mut();
+    if scope.vars.contains_key(name) {
+        if scope.consts.contains(name) {
+            return Err(Error::Reference(format!(
+                "Assignment to constant variable: {}",
+                name
+            )));
+        }
+        scope.vars.insert(name.to_string(), val);
+        return Ok(());
+    }
+    match &scope.parent {
+        Some(parent) => assign(parent, name, val),
+        None => Err(Error::Reference(format!(
+            "Assignment to undeclared variable: {}",
+            name
+        ))),
+    }
+}
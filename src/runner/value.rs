@@ -1,75 +1,366 @@
-use anyhow::{bail, Result};
+use std::cell::RefCell;
 use std::fmt;
+use std::rc::Rc;
 
-#[derive(PartialEq, Clone, Debug)]
+use crate::bigint::BigInt;
+use crate::parser::ast::{num_to_string, quote_str, FunctionDef};
+use crate::runner::builtins::Builtin;
+use crate::runner::generator::Generator;
+use crate::runner::promise::{Promise, Settled, State};
+use crate::runner::scope::ScopeRef;
+use crate::runner::{Error, Result};
+
+#[derive(Clone, Debug)]
 pub enum Value {
-    Object(Vec<(String, Value)>),
+    Object(ObjectRef),
     Array(Vec<Value>),
     Str(String),
+    // exact integers, promoted to Num only when an operation can't stay exact
+    Int(i64),
     Num(f64),
+    // `123n`, and json integers which don't fit in an Int. operations on them stay exact
+    BigInt(BigInt),
     Bool(bool),
     Null,
+    Undefined,
+    Function(Closure),
+    Native(Native),
+    Generator(GeneratorRef),
+    Promise(PromiseRef),
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Object(v1), Value::Object(v2)) => v1 == v2,
+            (Value::Array(v1), Value::Array(v2)) => v1 == v2,
+            (Value::Str(v1), Value::Str(v2)) => v1 == v2,
+            (Value::Int(v1), Value::Int(v2)) => v1 == v2,
+            (Value::Num(v1), Value::Num(v2)) => v1 == v2,
+            // 1 and 1.0 are the same number
+            (Value::Int(i), Value::Num(n)) | (Value::Num(n), Value::Int(i)) => int_eq_f64(*i, *n),
+            (Value::BigInt(v1), Value::BigInt(v2)) => v1 == v2,
+            (Value::Bool(v1), Value::Bool(v2)) => v1 == v2,
+            (Value::Null, Value::Null) => true,
+            (Value::Undefined, Value::Undefined) => true,
+            (Value::Function(v1), Value::Function(v2)) => v1 == v2,
+            (Value::Native(v1), Value::Native(v2)) => v1 == v2,
+            (Value::Generator(v1), Value::Generator(v2)) => v1 == v2,
+            (Value::Promise(v1), Value::Promise(v2)) => v1 == v2,
+            _ => false,
+        }
+    }
+}
+
+// exact comparison: large ints are not rounded to the closest f64
+pub fn int_eq_f64(i: i64, n: f64) -> bool {
+    n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 && n as i64 == i
+}
+
+// objects are shared: a change through any reference is seen by all of them
+#[derive(Clone)]
+pub struct ObjectRef(pub Rc<RefCell<Object>>);
+
+impl ObjectRef {
+    pub fn new(props: Vec<(String, Value)>) -> Self {
+        let props = props
+            .into_iter()
+            .map(|(name, val)| (name, Property::Data(val)))
+            .collect();
+        ObjectRef(Rc::new(RefCell::new(Object {
+            props,
+            frozen: false,
+        })))
+    }
+}
+
+// objects are only equal to themselves
+impl PartialEq for ObjectRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Object")
+    }
+}
+
+pub struct Object {
+    // in insertion order
+    pub props: Vec<(String, Property)>,
+    // no property can be added or changed, see `Runner::set_prop`
+    pub frozen: bool,
+}
+
+#[derive(Clone)]
+pub enum Property {
+    Data(Value),
+    // getter and setter functions
+    Accessor(Option<Value>, Option<Value>),
+}
+
+impl Object {
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        self.props
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, prop)| prop)
+    }
+
+    // redefining a property keeps its original position
+    pub fn define(&mut self, name: &str, prop: Property) {
+        match self.props.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = prop,
+            None => self.props.push((name.to_string(), prop)),
+        }
+    }
+}
+
+// a user function, along with the scope it was defined in
+#[derive(Clone)]
+pub struct Closure {
+    pub def: Rc<FunctionDef>,
+    pub scope: ScopeRef,
+}
+
+// functions are only equal to themselves
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.def, &other.def) && Rc::ptr_eq(&self.scope, &other.scope)
+    }
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Closure({:?})", self.def.name)
+    }
+}
+
+// a function implemented in rust, which can be used as a value, like `Object.freeze`
+#[derive(Clone)]
+pub struct Native {
+    pub name: String,
+    pub builtin: Rc<Builtin>,
+}
+
+impl PartialEq for Native {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.builtin, &other.builtin)
+    }
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Native({})", self.name)
+    }
+}
+
+// generators are shared: calling next() on any copy advances all of them
+#[derive(Clone)]
+pub struct GeneratorRef(pub Rc<RefCell<Generator>>);
+
+impl PartialEq for GeneratorRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for GeneratorRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Generator")
+    }
+}
+
+#[derive(Clone)]
+pub struct PromiseRef(pub Rc<RefCell<Promise>>);
+
+impl PartialEq for PromiseRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for PromiseRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Promise")
+    }
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Value::Object(props) => {
-                let res: Vec<String> = props
-                    .iter()
-                    .map(|(name, value)| format!("\"{}\": {}", name, value))
-                    .collect();
-                write!(f, "{{{}}}", res.join(", "))
+        fmt_value(f, self, &mut vec![])
+    }
+}
+
+// `seen` holds the objects being printed, to stop on cycles
+fn fmt_value(f: &mut fmt::Formatter, val: &Value, seen: &mut Vec<ObjectRef>) -> fmt::Result {
+    match val {
+        // like JSON.stringify: undefined members are left out, and are null in arrays.
+        // accessors are not called, they are shown like node does
+        Value::Object(obj) => {
+            if seen.contains(obj) {
+                return write!(f, "[Circular]");
+            }
+            seen.push(obj.clone());
+            write!(f, "{{")?;
+            let mut first = true;
+            for (name, prop) in obj.0.borrow().props.iter() {
+                if let Property::Data(Value::Undefined) = prop {
+                    continue;
+                }
+                if !first {
+                    write!(f, ", ")?;
+                }
+                first = false;
+                write!(f, "{}: ", quote_str(name))?;
+                match prop {
+                    Property::Data(val) => fmt_value(f, val, seen)?,
+                    Property::Accessor(Some(_), Some(_)) => write!(f, "[Getter/Setter]")?,
+                    Property::Accessor(Some(_), None) => write!(f, "[Getter]")?,
+                    Property::Accessor(None, _) => write!(f, "[Setter]")?,
+                }
             }
-            Value::Array(elts) => {
-                let res: Vec<String> = elts.iter().map(|elt| format!("{}", elt)).collect();
-                write!(f, "[{}]", res.join(", "))
+            seen.pop();
+            write!(f, "}}")
+        }
+        Value::Array(elts) => {
+            write!(f, "[")?;
+            for (idx, elt) in elts.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, ", ")?;
+                }
+                match elt {
+                    Value::Undefined => write!(f, "null")?,
+                    elt => fmt_value(f, elt, seen)?,
+                }
             }
-            Value::Str(s) => write!(f, "\"{}\"", s),
-            Value::Num(n) => write!(f, "{}", n),
-            Value::Bool(b) => write!(f, "{}", b),
-            Value::Null => write!(f, "null"),
+            write!(f, "]")
         }
+        Value::Str(s) => write!(f, "{}", quote_str(s)),
+        Value::Int(n) => write!(f, "{}", n),
+        Value::Num(n) => write!(f, "{}", num_to_string(*n)),
+        // like node
+        Value::BigInt(n) => write!(f, "{}n", n),
+        Value::Bool(b) => write!(f, "{}", b),
+        Value::Null => write!(f, "null"),
+        Value::Undefined => write!(f, "undefined"),
+        Value::Function(closure) => match &closure.def.name {
+            Some(name) => write!(f, "[Function: {}]", name),
+            None => write!(f, "[Function (anonymous)]"),
+        },
+        Value::Native(native) => write!(f, "[Function: {}]", native.name),
+        Value::Generator(_) => write!(f, "[object Generator]"),
+        // like node
+        Value::Promise(promise) => match &promise.0.borrow().state {
+            State::Pending(_) => write!(f, "Promise {{ <pending> }}"),
+            State::Settled(Settled::Fulfilled(val)) => {
+                write!(f, "Promise {{ ")?;
+                fmt_value(f, val, seen)?;
+                write!(f, " }}")
+            }
+            State::Settled(Settled::Rejected(reason, _)) => {
+                write!(f, "Promise {{ <rejected> ")?;
+                fmt_value(f, reason, seen)?;
+                write!(f, " }}")
+            }
+        },
     }
 }
 
-#[derive(PartialEq, Clone, Debug)]
-#[allow(dead_code)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum Type {
     Object,
     Array,
     Str,
+    Int,
     Num,
+    BigInt,
     Bool,
     Null,
+    Undefined,
+    Function,
+    Generator,
+    Promise,
 }
 
-#[allow(dead_code)]
 impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Type::Object => write!(f, "Object"),
             Type::Array => write!(f, "Array"),
             Type::Str => write!(f, "Str"),
+            Type::Int => write!(f, "Int"),
             Type::Num => write!(f, "Num"),
+            Type::BigInt => write!(f, "BigInt"),
             Type::Bool => write!(f, "Bool"),
             Type::Null => write!(f, "Null"),
+            Type::Undefined => write!(f, "Undefined"),
+            Type::Function => write!(f, "Function"),
+            Type::Generator => write!(f, "Generator"),
+            Type::Promise => write!(f, "Promise"),
         }
     }
 }
 
+pub fn type_of(val: &Value) -> Type {
+    match val {
+        Value::Object(_) => Type::Object,
+        Value::Array(_) => Type::Array,
+        Value::Str(_) => Type::Str,
+        Value::Int(_) => Type::Int,
+        Value::Num(_) => Type::Num,
+        Value::BigInt(_) => Type::BigInt,
+        Value::Bool(_) => Type::Bool,
+        Value::Null => Type::Null,
+        Value::Undefined => Type::Undefined,
+        Value::Function(_) | Value::Native(_) => Type::Function,
+        Value::Generator(_) => Type::Generator,
+        Value::Promise(_) => Type::Promise,
+    }
+}
+
+pub fn truthy(val: &Value) -> bool {
+    match val {
+        Value::Str(s) => !s.is_empty(),
+        Value::Int(n) => *n != 0,
+        Value::Num(n) => *n != 0.0 && !n.is_nan(),
+        Value::BigInt(n) => !n.is_zero(),
+        Value::Bool(b) => *b,
+        Value::Null | Value::Undefined => false,
+        _ => true,
+    }
+}
+
+// the `{ value, done }` object returned by iterators
+pub fn iter_result(val: Value, done: bool) -> Value {
+    Value::Object(ObjectRef::new(vec![
+        (String::from("value"), val),
+        (String::from("done"), Value::Bool(done)),
+    ]))
+}
+
 pub fn as_string(val: &Value) -> Result<&String> {
     match val {
         Value::Str(str) => Ok(str),
-        _ => bail!("invalid string type {}", val),
+        _ => Err(Error::Type(format!("invalid string type {}", val))),
     }
 }
 
-#[allow(dead_code)]
 pub fn as_f64(val: &Value) -> Result<f64> {
     match val {
+        Value::Int(num) => Ok(*num as f64),
         Value::Num(num) => Ok(*num),
-        _ => bail!("invalid number type {}", val),
+        _ => Err(Error::Type(format!("invalid number type {}", val))),
+    }
+}
+
+pub fn as_i64(val: &Value) -> Result<i64> {
+    match val {
+        Value::Int(num) => Ok(*num),
+        Value::Num(num) if num.fract() == 0.0 && num.abs() < i64::MAX as f64 => Ok(*num as i64),
+        _ => Err(Error::Type(format!("invalid integer type {}", val))),
     }
 }
 
@@ -77,22 +368,20 @@ pub fn as_f64(val: &Value) -> Result<f64> {
 pub fn as_bool(val: &Value) -> Result<bool> {
     match val {
         Value::Bool(bool) => Ok(*bool),
-        _ => bail!("invalid bool type {}", val),
+        _ => Err(Error::Type(format!("invalid bool type {}", val))),
     }
 }
 
-#[allow(dead_code)]
-pub fn as_vec(val: &Value) -> Result<&Vec<Value>> {
+pub fn as_promise(val: &Value) -> Result<&PromiseRef> {
     match val {
-        Value::Array(arr) => Ok(arr),
-        _ => bail!("invalid array type {}", val),
+        Value::Promise(promise) => Ok(promise),
+        _ => Err(Error::Type(format!("invalid promise type {}", val))),
     }
 }
 
-#[allow(dead_code)]
-pub fn as_hash(val: &Value) -> Result<&Vec<(String, Value)>> {
+pub fn as_vec(val: &Value) -> Result<&Vec<Value>> {
     match val {
-        Value::Object(obj) => Ok(obj),
-        _ => bail!("invalid object type {}", val),
+        Value::Array(arr) => Ok(arr),
+        _ => Err(Error::Type(format!("invalid array type {}", val))),
     }
 }
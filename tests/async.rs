@@ -0,0 +1,152 @@
+// promises, async/await, and the job queue with its virtual clock. builtins registered from rust
+// can return promises they settle later
+mod common;
+use common::{assert_cases, eval, eval_err};
+use js_light::parser;
+use js_light::runner::{Builtin, Runner, Value};
+
+#[test]
+fn async_functions() {
+    assert_cases(&[
+        (
+            "async function f(x) { return x * 2; } var p = f(2); [p, await p]",
+            "[Promise { 4 }, 4]",
+        ),
+        // an await-only statement gives its value too
+        ("await 5", "5"),
+        ("async function f() { return 2; } 1; await f();", "2"),
+        (
+            "var log = \"\"; async function f() { log = log + \"a\"; await sleep(10); log = log + \"c\"; }
+             f(); log = log + \"b\"; await sleep(20); log",
+            "\"abc\"",
+        ),
+        (
+            "var r; sleep(5).then(function (v) { r = \"done\"; }); await sleep(10); r",
+            "\"done\"",
+        ),
+        (
+            "async function f() { return x; } f().catch(function (e) { return 1; })",
+            "Promise { 1 }",
+        ),
+    ]);
+}
+
+#[test]
+fn virtual_clock() {
+    // timers run in the order of their time, not of their creation, and nothing actually waits
+    let src = "var log = \"\";
+        setTimeout(function () { log = log + \"b\"; }, 20);
+        setTimeout(function () { log = log + \"a\"; }, 10);
+        var id = setTimeout(function () { log = log + \"x\"; }, 15);
+        clearTimeout(id);
+        await sleep(1000000); log";
+    assert_eq!(eval(src), "\"ab\"");
+    // microtasks run before the next timer
+    let src = "var log = \"\";
+        setTimeout(function () { log = log + \"t\"; }, 0);
+        async function f() {}
+        f().then(function () { log = log + \"m\"; });
+        await sleep(1); log";
+    assert_eq!(eval(src), "\"mt\"");
+    // delays are at most 2^31 - 1 ms, so the clock never overflows
+    let cases = [
+        ("await sleep(5); setTimeout(function () {}, 1e300); 1", "1"),
+        ("await sleep(1e300); await sleep(1e300); 2", "2"),
+        (
+            "var log = \"\"; setTimeout(function () { log = log + \"b\"; }, Infinity);
+            setTimeout(function () { log = log + \"a\"; }, 2147483646); await sleep(1e300); log",
+            "\"ab\"",
+        ),
+    ];
+    assert_cases(&cases);
+}
+
+#[test]
+fn rejections() {
+    assert_eq!(
+        eval_err("async function f() { return x; } await f()"),
+        "Uncaught (in promise) x is not defined"
+    );
+    // a rejection no one handles is an error once everything has run
+    assert_eq!(
+        eval_err("async function f() { await 1; nope(); } f(); 1"),
+        "Uncaught (in promise) Unknown function: nope"
+    );
+}
+
+// `fetch(x)` gives a promise resolved with `x * 2` 10ms later
+fn fetch(runner: &mut Runner, params: &[Value]) -> js_light::runner::Result<Value> {
+    let promise = runner.new_promise();
+    let res = promise.clone();
+    let val = match params[0] {
+        Value::Int(n) => Value::Int(n * 2),
+        _ => Value::Null,
+    };
+    runner.set_timer(
+        10,
+        Box::new(move |runner| {
+            runner.resolve_promise(&res, val);
+            Ok(())
+        }),
+    );
+    Ok(Value::Promise(promise))
+}
+
+// `pending()` gives a promise which only the host settles
+fn pending(runner: &mut Runner, _params: &[Value]) -> js_light::runner::Result<Value> {
+    Ok(Value::Promise(runner.new_promise()))
+}
+
+fn host_runner() -> Runner {
+    let mut runner = Runner::new();
+    let builtin = |nb_args, func| Builtin {
+        nb_args,
+        nb_optional: 0,
+        func,
+    };
+    runner.register_builtin("fetch", builtin(1, fetch));
+    runner.register_builtin("pending", builtin(0, pending));
+    runner
+}
+
+fn run_with(runner: &mut Runner, src: &str) -> Value {
+    let prg = parser::parse_prg(src).unwrap();
+    runner
+        .run_prg(&prg)
+        .unwrap_or_else(|err| panic!("{:?} fails: {}", src, err))
+}
+
+#[test]
+fn host_promises() {
+    let mut runner = host_runner();
+    let val = run_with(&mut runner, "var a = await fetch(21); [a, await fetch(a)]");
+    assert_eq!(val.to_string(), "[42, 84]");
+
+    // settled by the host after the run
+    let src = "var out = \"none\"; var p = pending(); p.then(function (v) { out = v; }); p";
+    let Value::Promise(promise) = run_with(&mut runner, src) else {
+        panic!("not a promise")
+    };
+    assert_eq!(run_with(&mut runner, "out").to_string(), "\"none\"");
+    runner.resolve_promise(&promise, Value::Str(String::from("late")));
+    runner.run_jobs().unwrap();
+    assert_eq!(run_with(&mut runner, "out").to_string(), "\"late\"");
+    assert_eq!(
+        run_with(&mut runner, "p").to_string(),
+        "Promise { \"late\" }"
+    );
+
+    // a host timer can be set at any delay
+    for _ in 0..2 {
+        runner.set_timer(u64::MAX, Box::new(|_runner| Ok(())));
+    }
+    runner.run_jobs().unwrap();
+}
+
+#[test]
+fn never_settled() {
+    let mut runner = host_runner();
+    let prg = parser::parse_prg("await pending()").unwrap();
+    let err = runner.run_prg(&prg).unwrap_err();
+    assert_eq!(err.to_string(), "await on a promise which is never settled");
+}
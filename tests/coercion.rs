@@ -0,0 +1,77 @@
+// operators convert their operands like javascript does, unless the program is strict
+mod common;
+use common::{assert_cases, eval, eval_err};
+
+#[test]
+fn conversions() {
+    assert_cases(&[
+        ("\"port: \" + 8080", "\"port: 8080\""),
+        ("1 + \"2\"", "\"12\""),
+        ("\"5\" * \"2\"", "10"),
+        ("\"3\" - 1", "2"),
+        ("\" 4 \" / 2", "2"),
+        ("\"x\" * 2", "NaN"),
+        ("true + 1", "2"),
+        ("null + 1", "1"),
+        ("[1, 2] + \"\"", "\"1,2\""),
+        ("({}) + \"\"", "\"[object Object]\""),
+        ("\"\" + 1.5 + true + null", "\"1.5truenull\""),
+        (
+            "var o = { valueOf() { return 42; } }; [o + 1, o * 2]",
+            "[43, 84]",
+        ),
+        (
+            "var o = { toString() { return \"x\"; } }; \"a\" + o",
+            "\"ax\"",
+        ),
+        ("\"b\" > \"a\"", "true"),
+        ("\"10\" < 9", "false"),
+    ]);
+}
+
+#[test]
+fn equality() {
+    assert_cases(&[
+        (
+            "[1 == \"1\", 1 === \"1\", 1 === 1.0]",
+            "[true, false, true]",
+        ),
+        (
+            "[0 == false, \"\" == 0, null == 0, null == undefined]",
+            "[true, true, false, true]",
+        ),
+        (
+            "[NaN == NaN, NaN === NaN, 1 != \"1\", 1 !== \"1\"]",
+            "[false, false, false, true]",
+        ),
+        // arrays are values, compared item by item, and objects are compared by identity
+        ("[[1] === [1], [1] === [2]]", "[true, false]"),
+        (
+            "var o = {}; [o === o, {} === {}, o == {}]",
+            "[true, false, false]",
+        ),
+        // SameValueZero finds NaN, strict equality doesn't
+        (
+            "[[NaN].includes(NaN), [NaN].indexOf(NaN), [1].includes(\"1\")]",
+            "[true, -1, false]",
+        ),
+    ]);
+}
+
+#[test]
+fn strict_mode() {
+    assert_eq!(
+        eval_err("\"use strict\"; 1 + \"a\""),
+        "invalid operand types for +: 1, \"a\""
+    );
+    assert_eq!(
+        eval_err("\"use strict\"; null * 2"),
+        "invalid operand types for *: null, 2"
+    );
+    // `==` is `===`
+    assert_eq!(
+        eval("\"use strict\"; [1 == \"1\", 1 == 1.0]"),
+        "[false, true]"
+    );
+    assert_eq!(eval("\"use strict\"; \"a\" + \"b\""), "\"ab\"");
+}
@@ -0,0 +1,586 @@
+// what the integration tests share: programs to parse, a generator of random ones, and a way to run them
+#![allow(dead_code)]
+use js_light::parser;
+use js_light::runner::{self, Runner, Value};
+
+pub const PROGRAMS: &[&str] = &[
+    "",
+    "  ",
+    "1",
+    "1;",
+    "2 + 3 * 4",
+    "var x = 10; var z = x + 25; z * 2",
+    "var x = 10;\nvar y = (2 + 3) * 6 + x;\n",
+    "var obj = { name, greetings, res: x + y, [prefix + \"_id\"]: 42 };",
+    "var counter = { count: 0, inc() { this.count = this.count + 1; }, get double() { return this.count * 2; } };",
+    "let total = 0; for (const x of [1, 2, 3]) { total = total + x; }",
+    "for (const key in obj) { print(key); }",
+    "function add(a, b = 1) { return a + b; }",
+    "function* range(n) { let i = 0; while (i < n) { yield i; i = i + 1; } }",
+    "var squares = [...range(10)];",
+    "async function f() { await sleep(1); return await g(); }",
+    "async function* f() {}",
+    "var f = async function() {}; var g = function* named() { yield* other(); };",
+    "x |> f(%) |> % + 1;",
+    "x |> f(1);",
+    "a + b |> % % 7",
+    "id |> await fetch(%);",
+    "a < b == c < d;",
+    "a < b < c;",
+    "a == b != c === d !== e;",
+    "2 ^ 3 ^ 2;",
+    "1 - 2 - 3 * 4 / 5 % 6;",
+    "-1; - 1; --1; - -1; -x; +x; -(x); -x.y.z(1);",
+    "- - 1;",
+    "-1..x = 2;",
+    "-1..x;",
+    "1..toFixed(2);",
+    "1.5.toFixed(2);",
+    "1.; 1.e5; 2e-3; 2E+3; 0; 0.5; 12n; -12n; 0n;",
+    "99999999999999999999; 99999999999999999999n;",
+    "9223372036854775807; -9223372036854775808; 9223372036854775808;",
+    "01;",
+    "1e;",
+    "NaN; Infinity; -Infinity; true; false; null; undefined;",
+    "NaN = 1;",
+    "var undefined = 1;",
+    "var class = 1;",
+    "this.x = 1; this;",
+    "this = 1;",
+    "f() = 1;",
+    "a.b.c() = 1;",
+    "a.b.c = 1;",
+    "a.b = c.d = 1;",
+    "(a).b = 1;",
+    "x = 1",
+    "x = 1;",
+    "const x;",
+    "const x = 1;",
+    "let x; var y;",
+    "var;",
+    "var x = ;",
+    "{}",
+    "{};",
+    "{ a: 1 };",
+    "{ a: 1 }",
+    "{ a: 1 }.a;",
+    "{ var x = 1; { let y = x; } }",
+    "{ var = 1; }",
+    "{ a }",
+    "{ a; }",
+    "{ a }; b;",
+    "if (a) b; else c;",
+    "if (a) { b; } else if (c) { d; } else { e; }",
+    "if (a) b;\nc;",
+    "if(a)b;",
+    "iffy(a);",
+    "while (x < 10) x = x + 1;",
+    "while(x) {}",
+    "for (let i of range(3)) {}",
+    "for (var k in {a: 1}) print(k);",
+    "for (x of y) {}",
+    "for (const x from y) {}",
+    "return;",
+    "return 1;",
+    "return (1);",
+    "return",
+    "function f() { return }",
+    "function() {}",
+    "function () {};",
+    "(function () {});",
+    "function f(a, b,) {}",
+    "function f(a b) {}",
+    "function f(a = 1, b = a * 2) { return arguments; }",
+    "f(1, 2, ...rest);",
+    "f (1);",
+    "f(1,);",
+    "[1, 2,];",
+    "[];",
+    "[...a, ...b];",
+    "[1, [2, [3]]];",
+    "({});",
+    "({ a: 1, b, c() {}, get d() { return 1; }, set d(v) {}, async e() {}, *f() {}, async *g() {} });",
+    "({ get() {}, set: 1, async: 2, get, set });",
+    "({ async() {} });",
+    "({ get x(a) {} });",
+    "({ set x() {} });",
+    "({ get [k]() {}, \"str key\": 1, [a + b]: 2 });",
+    "({ a: 1, });",
+    "({ , });",
+    "({ a b });",
+    "({ async x });",
+    "({ get \"a\": 1 });",
+    "({ 1: 2 });",
+    "\"str\"; \"esc \\\" \\\\ \\/ \\b \\f \\n \\r \\t \\u00e9\"; \"\\ud83d\\ude00\";",
+    "\"bad \\q escape\";",
+    "\"unterminated;",
+    "'single';",
+    "var café = 1; var $el = 2; var _x = 3;",
+    "var \\u0061 = 1; a;",
+    "var \\u{62} = 1;",
+    "var \\u0031 = 1;",
+    "var v\\u0061r = 1;",
+    "v\\u0061r x = 1;",
+    "variable = 1;",
+    "obj.default; obj.class.new;",
+    "obj.\\u0061;",
+    "obj.;",
+    "obj .  x  . y ( 1 ) ;",
+    "x.y\n.z;",
+    "yield;",
+    "yield 1;",
+    "yield* g();",
+    "yield*;",
+    "yield (1);",
+    "yield yield 1;",
+    "await x;",
+    "await await x;",
+    "await(x);",
+    "await;",
+    "await - 1;",
+    "async;",
+    "async(1);",
+    "f(function(a) { return a; });",
+    "f(async function() {}, function*() {});",
+    "%;",
+    "x |> %.trim();",
+    "x |> [%, %];",
+    "x |> { a: % };",
+    "a |> b |> %;",
+    "1 +;",
+    "1 + + 2;",
+    "(1;",
+    "(1));",
+    "[1;",
+    "f(;",
+    "a.b(;",
+    "};",
+    "}",
+    "var x = 1 var y = 2;",
+    "var x = 1; var y = ; var z = 3;",
+    "var x = 1 @ 2;",
+    "x # y;",
+    "a ! b;",
+    "a | b;",
+    "a || b;",
+    "a != b;",
+    "a\t=\r\n1\t;",
+    "a\u{a0}= 1;",
+    "1 === 1;",
+    "1 ==== 1;",
+    "1 <= 2; 1 >= 2; 1 < 2; 1 > 2;",
+    "1 <== 2;",
+    "a.b.c.d.e.f.g(1).h(2).i;",
+    "f(a)(b);",
+    "f(a).b(c)(d);",
+    "(f)(a);",
+    "x = function() {};",
+    "x = {a: 1};",
+    "{a: 1, b: {c: 2}};",
+    "{ if (a) { b; } }",
+    "{ { a: 1 }; }",
+    "{ x = 1 }",
+    "f(x) { }",
+    "...a;",
+    "[..a];",
+    // comments
+    "// a comment",
+    "/* a comment */",
+    "var x = 1; // the end",
+    "var x = 1; // the end\n",
+    "var /* name */ x = /* val */ 1 /* end */;",
+    "if (a) b; // c\nelse d;",
+    "f(a, // first\n b);",
+    "/** doc */ function f() { /* nothing */ }",
+    "var o = { a: 1, // a\n /* b */ b: 2 };",
+    "1 /* / */ / 2",
+    "1 // 2 */ 3",
+    "/* unterminated",
+    "var x = 1 /* unterminated",
+    "/* * / */ 1",
+    "/*/ 1",
+];
+
+// xorshift: enough randomness to generate programs, without a dependency
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    pub fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+// random programs: mostly valid ones, with random spacing
+pub struct Gen {
+    pub rng: Rng,
+    pub out: String,
+}
+
+const NAMES: &[&str] = &[
+    "a", "b", "x", "foo", "café", "$el", "_", "get", "set", "async", "of", "this", "NaN", "var",
+];
+const LITERALS: &[&str] = &[
+    "1",
+    "0",
+    "-1",
+    "1.5",
+    "2e3",
+    "1.",
+    "12n",
+    "\"s\"",
+    "\"\\n\"",
+    "true",
+    "null",
+    "undefined",
+    "NaN",
+    "Infinity",
+];
+const INFIX: &[&str] = &[
+    "+", "-", "*", "/", "%", "^", "<", "<=", ">", ">=", "==", "!=", "===", "!==", "|>",
+];
+
+impl Gen {
+    fn space(&mut self) {
+        let space = self.rng.pick(&[
+            "", "", " ", " ", "\n", "  \t", " // c\n", "/* c */", "/**/ ",
+        ]);
+        self.out.push_str(space);
+    }
+
+    fn token(&mut self, token: &str) {
+        self.out.push_str(token);
+        self.space();
+    }
+
+    pub fn stmts(&mut self, depth: usize) {
+        for _ in 0..self.rng.below(4) {
+            self.stmt(depth);
+        }
+    }
+
+    fn block(&mut self, depth: usize) {
+        self.token("{");
+        self.stmts(depth + 1);
+        self.token("}");
+    }
+
+    fn stmt(&mut self, depth: usize) {
+        if depth > 3 {
+            return self.simple_stmt(depth);
+        }
+        match self.rng.below(12) {
+            0 => {
+                self.token("if");
+                self.token("(");
+                self.expr(depth + 1);
+                self.token(")");
+                self.stmt(depth + 1);
+                if self.rng.below(2) == 0 {
+                    self.token(" else ");
+                    self.stmt(depth + 1);
+                }
+            }
+            1 => {
+                self.token("while");
+                self.token("(");
+                self.expr(depth + 1);
+                self.token(")");
+                self.stmt(depth + 1);
+            }
+            2 => {
+                self.token("for");
+                self.token("(");
+                let kind = self.rng.pick(&["var ", "let ", "const "]);
+                self.token(kind);
+                let name = self.rng.pick(NAMES);
+                self.token(name);
+                let of = self.rng.pick(&[" of ", " in "]);
+                self.token(of);
+                self.expr(depth + 1);
+                self.token(")");
+                self.stmt(depth + 1);
+            }
+            3 => self.block(depth),
+            4 => self.function(depth, true),
+            _ => self.simple_stmt(depth),
+        }
+    }
+
+    fn simple_stmt(&mut self, depth: usize) {
+        match self.rng.below(6) {
+            0 => {
+                let kind = self.rng.pick(&["var ", "let ", "const "]);
+                self.token(kind);
+                let name = self.rng.pick(NAMES);
+                self.token(name);
+                if self.rng.below(4) > 0 {
+                    self.token("=");
+                    self.expr(depth + 1);
+                }
+            }
+            1 => {
+                let name = self.rng.pick(NAMES);
+                self.token(name);
+                self.token("=");
+                self.expr(depth + 1);
+            }
+            2 => {
+                self.postfix(depth + 1);
+                self.token("=");
+                self.expr(depth + 1);
+            }
+            3 => {
+                self.token("return ");
+                if self.rng.below(2) == 0 {
+                    self.expr(depth + 1);
+                }
+            }
+            _ => self.expr(depth + 1),
+        }
+        self.token(";");
+    }
+
+    fn function(&mut self, depth: usize, named: bool) {
+        if self.rng.below(4) == 0 {
+            self.token("async ");
+        }
+        self.token("function");
+        if self.rng.below(3) == 0 {
+            self.token("*");
+        }
+        if named || self.rng.below(2) == 0 {
+            self.out.push(' ');
+            let name = self.rng.pick(NAMES);
+            self.token(name);
+        }
+        self.token("(");
+        for i in 0..self.rng.below(3) {
+            if i > 0 {
+                self.token(",");
+            }
+            let name = self.rng.pick(NAMES);
+            self.token(name);
+            if self.rng.below(3) == 0 {
+                self.token("=");
+                self.expr(depth + 1);
+            }
+        }
+        self.token(")");
+        self.block(depth);
+    }
+
+    fn expr(&mut self, depth: usize) {
+        match self.rng.below(10) {
+            0 if depth < 4 => {
+                self.token("yield");
+                if self.rng.below(2) == 0 {
+                    self.token("*");
+                }
+                if self.rng.below(2) == 0 {
+                    self.out.push(' ');
+                    self.expr(depth + 1);
+                }
+            }
+            1..=4 if depth < 4 => {
+                self.unary(depth + 1);
+                for _ in 0..self.rng.below(3) {
+                    let op = self.rng.pick(INFIX);
+                    self.token(op);
+                    self.unary(depth + 1);
+                }
+            }
+            _ => self.unary(depth + 1),
+        }
+    }
+
+    fn unary(&mut self, depth: usize) {
+        match self.rng.below(8) {
+            0 => {
+                let sign = self.rng.pick(&["-", "+"]);
+                self.token(sign);
+                self.postfix(depth);
+            }
+            1 if depth < 5 => {
+                self.token("await ");
+                self.unary(depth + 1);
+            }
+            _ => self.postfix(depth),
+        }
+    }
+
+    fn postfix(&mut self, depth: usize) {
+        self.primary(depth);
+        for _ in 0..self.rng.below(3) {
+            self.token(".");
+            let name = self.rng.pick(NAMES);
+            self.token(name);
+            if self.rng.below(2) == 0 {
+                self.args(depth, ")");
+            }
+        }
+    }
+
+    fn args(&mut self, depth: usize, close: &str) {
+        self.token(if close == ")" { "(" } else { "[" });
+        for i in 0..self.rng.below(3) {
+            if i > 0 {
+                self.token(",");
+            }
+            if self.rng.below(5) == 0 {
+                self.token("...");
+            }
+            self.expr(depth + 1);
+        }
+        self.token(close);
+    }
+
+    fn primary(&mut self, depth: usize) {
+        if depth > 6 {
+            let literal = self.rng.pick(LITERALS);
+            return self.token(literal);
+        }
+        match self.rng.below(10) {
+            0 => {
+                self.token("(");
+                self.expr(depth + 1);
+                self.token(")");
+            }
+            1 => self.args(depth, "]"),
+            2 => self.object(depth),
+            3 => {
+                let name = self.rng.pick(NAMES);
+                self.out.push_str(name);
+                self.args(depth, ")");
+            }
+            4 => self.function(depth, false),
+            5 => self.token("%"),
+            6 | 7 => {
+                let name = self.rng.pick(NAMES);
+                self.token(name);
+            }
+            _ => {
+                let literal = self.rng.pick(LITERALS);
+                self.token(literal);
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) {
+        self.token("{");
+        for i in 0..self.rng.below(4) {
+            if i > 0 {
+                self.token(",");
+            }
+            match self.rng.below(6) {
+                0 => {
+                    let name = self.rng.pick(NAMES);
+                    self.token(name);
+                }
+                1 => {
+                    let prefix = self
+                        .rng
+                        .pick(&["", "get ", "set ", "async ", "*", "async *"]);
+                    self.token(prefix);
+                    self.prop_key(depth);
+                    self.token("(");
+                    if self.rng.below(2) == 0 {
+                        let name = self.rng.pick(NAMES);
+                        self.token(name);
+                    }
+                    self.token(")");
+                    self.block(depth);
+                }
+                _ => {
+                    self.prop_key(depth);
+                    self.token(":");
+                    self.expr(depth + 1);
+                }
+            }
+        }
+        if self.rng.below(4) == 0 {
+            self.token(",");
+        }
+        self.token("}");
+    }
+
+    fn prop_key(&mut self, depth: usize) {
+        match self.rng.below(4) {
+            0 => self.token("\"k\""),
+            1 => {
+                self.token("[");
+                self.expr(depth + 1);
+                self.token("]");
+            }
+            _ => {
+                let name = self.rng.pick(NAMES);
+                self.token(name);
+            }
+        }
+    }
+}
+
+// a valid program with a few bytes removed, or a token inserted somewhere
+pub fn mutate(rng: &mut Rng, src: &str) -> String {
+    let boundaries: Vec<usize> = (0..=src.len())
+        .filter(|idx| src.is_char_boundary(*idx))
+        .collect();
+    let start = boundaries[rng.below(boundaries.len())];
+    match rng.below(2) {
+        0 => {
+            let rest: Vec<usize> = boundaries
+                .into_iter()
+                .filter(|idx| *idx >= start)
+                .take(4)
+                .collect();
+            let end = rest[rng.below(rest.len())];
+            format!("{}{}", &src[..start], &src[end..])
+        }
+        _ => {
+            let token = rng.pick(&[
+                "{", "}", "(", ")", ";", ",", "-", "=", "yield", "var", ".", "\"", "1", "@",
+            ]);
+            format!("{}{}{}", &src[..start], token, &src[start..])
+        }
+    }
+}
+
+// run a program: its completion value, or the error it stops with. it has to parse
+pub fn run(src: &str) -> runner::Result<Value> {
+    let prg =
+        parser::parse_prg(src).unwrap_or_else(|err| panic!("{:?} doesn't parse: {}", src, err));
+    Runner::new().run_prg(&prg)
+}
+
+// the value of the program, as --expr prints it (but with quotes for strings)
+pub fn eval(src: &str) -> String {
+    match run(src) {
+        Ok(val) => val.to_string(),
+        Err(err) => panic!("{:?} fails: {}", src, err),
+    }
+}
+
+// the error a program stops with
+pub fn eval_err(src: &str) -> String {
+    match run(src) {
+        Ok(val) => panic!("{:?} gives {}", src, val),
+        Err(err) => err.inner().to_string(),
+    }
+}
+
+// checks the value of each program
+pub fn assert_cases(cases: &[(&str, &str)]) {
+    for (src, expected) in cases {
+        assert_eq!(eval(src), *expected, "running {:?}", src);
+    }
+}
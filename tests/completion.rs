@@ -0,0 +1,56 @@
+// a program's value is the value of its last expression statement, which --expr prints
+use std::process::Command;
+
+mod common;
+use common::assert_cases;
+
+#[test]
+fn completion_value() {
+    assert_cases(&[
+        ("2 + 3 * 4", "14"),
+        ("var x = 10; var z = x + 25; z * 2;", "70"),
+        // declarations and assignments have no value
+        ("var x = 1;", "undefined"),
+        ("1; var x = 2; x = 3;", "1"),
+        ("1; function f() {}", "1"),
+        // the last statement run, in blocks and loops too
+        ("1; if (true) { 2; } else { 3; }", "2"),
+        ("let i = 0; while (i < 3) { i = i + 1; i * 10; }", "30"),
+        ("5; while (false) { 6; }", "5"),
+        ("\"a\"", "\"a\""),
+        ("", "undefined"),
+    ]);
+}
+
+fn cli(expr: &str) -> (bool, String, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_js-light"))
+        .arg(format!("--expr={}", expr))
+        .output()
+        .unwrap();
+    (
+        out.status.success(),
+        String::from_utf8_lossy(&out.stdout).into_owned(),
+        String::from_utf8_lossy(&out.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn expr_mode() {
+    assert_eq!(
+        cli("2 + 3 * 4"),
+        (true, String::from("14\n"), String::new())
+    );
+    // a string is printed as is, for shell pipelines
+    assert_eq!(
+        cli("\"a\" + \"b\""),
+        (true, String::from("ab\n"), String::new())
+    );
+    let (success, stdout, stderr) = cli("1 +");
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert!(
+        stderr.starts_with("Error parsing prg: <expr>:1:4"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,110 @@
+// the syntax tree gives back the source byte for byte, and the same ast as the parser
+use js_light::parser;
+use js_light::parser::cst::{NodeKind, SyntaxElement, SyntaxNode};
+use js_light::parser::lexer::TokenKind;
+
+mod common;
+use common::{mutate, Gen, Rng, PROGRAMS};
+
+// the children of a node are in order, and inside its span
+fn check_node(node: &SyntaxNode, src: &str) {
+    let mut pos = node.span.start;
+    for child in &node.children {
+        let span = match child {
+            SyntaxElement::Node(child) => {
+                check_node(child, src);
+                child.span
+            }
+            SyntaxElement::Token(token) => token.span,
+        };
+        if node.kind != NodeKind::Prg {
+            assert!(
+                span.start >= node.span.start && span.end <= node.span.end,
+                "{:?} outside its parent",
+                child
+            );
+        }
+        assert!(span.start >= pos, "{:?} out of order in {:?}", child, src);
+        pos = span.end;
+    }
+}
+
+fn same_as_the_source(src: &str) {
+    let tree = parser::parse_cst(src);
+    assert_eq!(tree.to_string(), src);
+    check_node(&tree.root, src);
+    let (prg, errors) = parser::parse_prg_partial(src);
+    assert_eq!(
+        format!("{:?}", tree.to_prg().expect(src)),
+        format!("{:?}", prg),
+        "different trees for {:?}",
+        src
+    );
+    assert_eq!(format!("{:?}", tree.errors), format!("{:?}", errors));
+}
+
+#[test]
+fn round_trip() {
+    for src in PROGRAMS {
+        same_as_the_source(src);
+    }
+    same_as_the_source(include_str!("../resources/ex1.js_new"));
+    for line in include_str!("../README.md").lines() {
+        if let Some(code) = line.strip_prefix("- ") {
+            same_as_the_source(code);
+        }
+    }
+}
+
+#[test]
+fn round_trip_random_programs() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for seed in 1..=300u64 {
+        let mut gen = Gen {
+            rng: Rng(seed.wrapping_mul(0x2545_f491_4f6c_dd1d)),
+            out: String::new(),
+        };
+        gen.stmts(0);
+        same_as_the_source(&gen.out);
+        for _ in 0..3 {
+            same_as_the_source(&mutate(&mut rng, &gen.out));
+        }
+    }
+}
+
+#[test]
+fn trivia_belong_to_the_parent() {
+    let src = "var x = f(a, // first\n b); /* end */";
+    let tree = parser::parse_cst(src);
+    let kinds = |node: &SyntaxNode| -> Vec<TokenKind> {
+        node.children
+            .iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Token(token) => Some(token.kind),
+                SyntaxElement::Node(_) => None,
+            })
+            .collect()
+    };
+    assert_eq!(
+        kinds(&tree.root),
+        [TokenKind::Whitespace, TokenKind::Comment]
+    );
+    let decl = tree.root.nodes().next().unwrap();
+    assert_eq!(decl.kind, NodeKind::VarDecl);
+    assert_eq!(decl.text(src), "var x = f(a, // first\n b);");
+    let call = decl.nodes().next().unwrap();
+    assert_eq!(call.kind, NodeKind::Call);
+    assert!(kinds(call).contains(&TokenKind::Comment));
+    let args: Vec<&str> = call.nodes().map(|arg| arg.text(src)).collect();
+    assert_eq!(args, ["a", "b"]);
+}
+
+#[test]
+fn errors_are_nodes() {
+    let src = "var x = ;\nvar y = 2;";
+    let tree = parser::parse_cst(src);
+    let kinds: Vec<NodeKind> = tree.root.nodes().map(|node| node.kind).collect();
+    assert_eq!(kinds, [NodeKind::Error, NodeKind::VarDecl]);
+    assert_eq!(tree.errors.len(), 1);
+    assert_eq!(tree.to_string(), src);
+}
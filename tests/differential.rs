@@ -0,0 +1,135 @@
+// the hand-written parser must build the same trees as the pest grammar, and reject the same programs
+use js_light::parser::lexer::Lexer;
+use js_light::parser::{self, grammar};
+
+mod common;
+use common::{mutate, Gen, Rng, PROGRAMS};
+
+// pest puts the whitespace and comments after some nodes in their span, like the newline after
+// `if (a) b;`: the parser doesn't, spans end with the last token of their node
+fn trim_spans(src: &str, tree: String) -> String {
+    let mut res = String::with_capacity(tree.len());
+    let mut rest = tree.as_str();
+    while let Some(idx) = rest.find("Span { start: ") {
+        let (before, span) = rest.split_at(idx);
+        res.push_str(before);
+        let end = span.find(" }").unwrap() + 2;
+        let numbers: Vec<usize> = span["Span { start: ".len()..end - 2]
+            .split(", end: ")
+            .map(|n| n.parse().unwrap())
+            .collect();
+        let trimmed = Lexer::new(&src[numbers[0]..numbers[1]])
+            .filter(|token| !token.kind.is_trivia())
+            .last()
+            .map_or(numbers[0], |token| numbers[0] + token.span.end);
+        res.push_str(&format!(
+            "Span {{ start: {}, end: {} }}",
+            numbers[0], trimmed
+        ));
+        rest = &span[end..];
+    }
+    res.push_str(rest);
+    res
+}
+
+fn same_prg(src: &str) {
+    match (parser::parse_prg(src), grammar::parse_prg(src)) {
+        (Ok(new), Ok(old)) => {
+            let old = trim_spans(src, format!("{:?}", old));
+            assert_eq!(format!("{:?}", new), old, "different trees for {:?}", src)
+        }
+        (Err(_), Err(_)) => {}
+        (new, old) => panic!(
+            "{:?}: the parser gives {:?}, the grammar {:?}",
+            src, new, old
+        ),
+    }
+}
+
+fn same_json(src: &str) {
+    match (parser::parse_literal(src), grammar::parse_literal(src)) {
+        (Ok(new), Ok(old)) => assert_eq!(
+            format!("{:?}", new),
+            format!("{:?}", old),
+            "different json for {:?}",
+            src
+        ),
+        (Err(_), Err(_)) => {}
+        (new, old) => panic!(
+            "{:?}: the parser gives {:?}, the grammar {:?}",
+            src, new, old
+        ),
+    }
+}
+
+const JSON: &[&str] = &[
+    "{}",
+    "[]",
+    "1",
+    "-1",
+    "- 1",
+    "1.5e3",
+    "-0",
+    "12n",
+    "\"str\"",
+    "true",
+    "null",
+    "undefined",
+    "NaN",
+    "-Infinity",
+    " { \"a\": [1, 2, {\"b\": null}], \"c\": \"\\u00e9\" } ",
+    "{\"a\": 1,}",
+    "[1, 2,]",
+    "{a: 1}",
+    "{\"a\": x}",
+    "{\"a\": 1 + 2}",
+    "{\"a\": f()}",
+    "[-1, - 1]",
+    "1 2",
+    "",
+    "{",
+    "[1",
+    "\"unterminated",
+];
+
+#[test]
+fn same_as_the_grammar() {
+    for src in PROGRAMS {
+        same_prg(src);
+    }
+}
+
+#[test]
+fn same_json_as_the_grammar() {
+    for src in JSON {
+        same_json(src);
+    }
+}
+
+#[test]
+fn same_examples_as_the_grammar() {
+    same_prg(include_str!("../resources/ex1.js_new"));
+    // the snippets of the readme
+    for line in include_str!("../README.md").lines() {
+        if let Some(code) = line.strip_prefix("- ") {
+            same_prg(code);
+            same_prg(&format!("{};", code));
+        }
+    }
+}
+
+#[test]
+fn same_random_programs_as_the_grammar() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for seed in 1..=500u64 {
+        let mut gen = Gen {
+            rng: Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15)),
+            out: String::new(),
+        };
+        gen.stmts(0);
+        same_prg(&gen.out);
+        for _ in 0..3 {
+            same_prg(&mutate(&mut rng, &gen.out));
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// errors are typed: embedding code matches on variants, not on messages
+mod common;
+use common::run;
+use js_light::parser;
+use js_light::runner::Error;
+
+fn run_err(src: &str) -> Error {
+    match run(src) {
+        Ok(val) => panic!("{:?} gives {}", src, val),
+        Err(Error::At(_, err)) => *err,
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn arity() {
+    let cases = [
+        ("min(1)", "min", 2, Some(2), 1, "Expected 2, got 1"),
+        (
+            "function f(a, b = 1) {} f()",
+            "f",
+            1,
+            Some(2),
+            0,
+            "Expected 1 to 2, got 0",
+        ),
+        (
+            "function f(a) { return arguments; } f()",
+            "f",
+            1,
+            None,
+            0,
+            "Expected at least 1, got 0",
+        ),
+        (
+            "\"a\".repeat()",
+            "Str.repeat",
+            1,
+            Some(1),
+            0,
+            "Expected 1, got 0",
+        ),
+    ];
+    for (src, name, required, max, given, message) in cases {
+        let err = run_err(src);
+        match &err {
+            Error::Arity {
+                name: n,
+                required: r,
+                max: m,
+                given: g,
+            } => assert_eq!((n.as_str(), *r, *m, *g), (name, required, max, given)),
+            err => panic!("{:?} gives {:?}", src, err),
+        }
+        assert_eq!(
+            err.to_string(),
+            format!("invalid number of params for {}. {}", name, message)
+        );
+    }
+}
+
+#[test]
+fn variants() {
+    assert!(matches!(run_err("nope(1)"), Error::UnknownFunction(name) if name == "nope"));
+    assert!(matches!(
+        run_err("\"use strict\"; 1 + \"a\""),
+        Error::InvalidOperands { op, operands } if op == "+" && operands == ["1", "\"a\""]
+    ));
+    assert!(matches!(
+        run_err("(1).nope()"),
+        Error::UnknownMethod { typ, name, available }
+            if typ == "Int" && name == "nope" && available == ["toFixed", "toString"]
+    ));
+    assert!(matches!(run_err("x"), Error::Reference(_)));
+    assert!(matches!(run_err("1n / 0n"), Error::Range(_)));
+    match run_err("load_json(\"resources/missing.json\")") {
+        Error::Io { path, source } => {
+            assert_eq!(path, "resources/missing.json");
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        }
+        err => panic!("{:?}", err),
+    }
+    match run_err("load_json(\"resources/ex1.json5\")") {
+        Error::Json { path, source } => {
+            assert_eq!(path, "resources/ex1.json5");
+            assert!(matches!(
+                source,
+                parser::Error::Syntax {
+                    line: 1,
+                    col: 1,
+                    ..
+                }
+            ));
+        }
+        err => panic!("{:?}", err),
+    }
+}
+
+#[test]
+fn syntax_errors() {
+    match parser::parse_prg("var a = 1;\nvar b = ;") {
+        Err(parser::Error::Syntax {
+            line,
+            col,
+            span,
+            message,
+        }) => {
+            assert_eq!((line, col, span.start), (2, 9, 19));
+            assert_eq!(message, "expected an expression, found `;`");
+        }
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+    // both kinds of errors convert to the crate's error
+    let err: js_light::Error = parser::parse_prg("1 +").unwrap_err().into();
+    assert!(err.to_string().starts_with("parse error: line 1, column 4"));
+    let err: js_light::Error = run_err("nope()").into();
+    assert_eq!(err.to_string(), "runtime error: Unknown function: nope");
+}
@@ -0,0 +1,197 @@
+// formatting keeps the program and its comments, and formatting formatted code changes nothing
+use js_light::formatter::{self, Options};
+use js_light::parser::{self, lexer::Lexer, lexer::TokenKind};
+
+mod common;
+use common::{mutate, Gen, Rng, PROGRAMS};
+
+fn without_spans(tree: String) -> String {
+    let mut res = String::with_capacity(tree.len());
+    let mut rest = tree.as_str();
+    while let Some(idx) = rest.find("span: Span { ") {
+        res.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        rest = &rest[rest.find(" }").unwrap() + 2..];
+    }
+    res.push_str(rest);
+    res
+}
+
+fn comments(src: &str) -> Vec<String> {
+    let mut comments: Vec<String> = Lexer::new(src)
+        .filter(|token| token.kind == TokenKind::Comment)
+        .map(|token| token.text(src).trim_end().to_string())
+        .collect();
+    comments.sort();
+    comments
+}
+
+fn check_format(src: &str, options: &Options) {
+    let prg = match parser::parse_prg(src) {
+        Ok(prg) => prg,
+        Err(_) => return assert!(formatter::format(src, options).is_err()),
+    };
+    let formatted = formatter::format(src, options).unwrap();
+    let reparsed = parser::parse_prg(&formatted)
+        .unwrap_or_else(|err| panic!("{:?} gives {:?}: {}", src, formatted, err));
+    assert_eq!(
+        without_spans(format!("{:?}", reparsed)),
+        without_spans(format!("{:?}", prg)),
+        "{:?} gives {:?}",
+        src,
+        formatted
+    );
+    assert_eq!(
+        comments(&formatted),
+        comments(src),
+        "{:?} gives {:?}",
+        src,
+        formatted
+    );
+    let again = formatter::format(&formatted, options).unwrap();
+    assert_eq!(again, formatted, "formatting {:?} twice", src);
+}
+
+fn all_options() -> [Options; 3] {
+    [
+        Options::default(),
+        Options {
+            line_width: 20,
+            indent: 4,
+        },
+        Options {
+            line_width: 0,
+            indent: 1,
+        },
+    ]
+}
+
+#[test]
+fn idempotent() {
+    for options in all_options() {
+        for src in PROGRAMS {
+            check_format(src, &options);
+        }
+        check_format(include_str!("../resources/ex1.js_new"), &options);
+        for line in include_str!("../README.md").lines() {
+            if let Some(code) = line.strip_prefix("- ") {
+                check_format(code, &options);
+            }
+        }
+    }
+}
+
+#[test]
+fn idempotent_on_random_programs() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for seed in 1..=300u64 {
+        let mut gen = Gen {
+            rng: Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15)),
+            out: String::new(),
+        };
+        gen.stmts(0);
+        for options in all_options() {
+            check_format(&gen.out, &options);
+            check_format(&mutate(&mut rng, &gen.out), &options);
+        }
+    }
+}
+
+#[test]
+fn layout() {
+    let cases = [
+        (
+            "var x=1;var y = [1,2 ,3];",
+            "var x = 1;\nvar y = [1, 2, 3];\n",
+        ),
+        ("if(a)b;else{c;}", "if (a) b;\nelse {\n  c;\n}\n"),
+        (
+            "function  f( a,b=1 ){return a+b;}",
+            "function f(a, b = 1) {\n  return a + b;\n}\n",
+        ),
+        ("var o = {a: 1, b: 2,};", "var o = { a: 1, b: 2 };\n"),
+        ("var o = {\na: 1};", "var o = { a: 1 };\n"),
+        ("var o = {a: 1,\n\n\n b: {}};", "var o = { a: 1, b: {} };\n"),
+        ("x;\n\n\n\ny;\nz;", "x;\n\ny;\nz;\n"),
+        (
+            "f(function() { g(); }, 10);",
+            "f(function() {\n  g();\n}, 10);\n",
+        ),
+        (
+            "1 .toFixed(2); -1..x = 2; - -1;",
+            "1 .toFixed(2);\n-1..x = 2;\n- -1;\n",
+        ),
+        (
+            "async  function f() {} function *g() {}",
+            "async function f() {}\nfunction* g() {}\n",
+        ),
+        (
+            "var o = { async f() {}, *g() {}, get x() { return 1; } };",
+            "var o = {\n  async f() {},\n  *g() {},\n  get x() {\n    return 1;\n  }\n};\n",
+        ),
+        // comments
+        (
+            "// a\nvar x = 1; // b\n/* c */ var y;",
+            "// a\nvar x = 1; // b\n/* c */ var y;\n",
+        ),
+        ("f(a, // first\nb);", "f(\n  a, // first\n  b\n);\n"),
+        ("var x = // why\n 1;", "var x = // why\n1;\n"),
+        (
+            "var x = /* one */ 1 /* end */;",
+            "var x = /* one */ 1 /* end */;\n",
+        ),
+        ("{ // empty\n}", "{ // empty\n}\n"),
+        (
+            "if (a) // c1\n  b(); // c2\nelse // c3\n  c();",
+            "if (a) // c1\n  b(); // c2\nelse // c3\n  c();\n",
+        ),
+        ("while (x) // w\nf();", "while (x) // w\n  f();\n"),
+        (
+            "if (a) { b(); }\n// own\nelse c();",
+            "if (a) {\n  b();\n}\n// own\nelse c();\n",
+        ),
+        ("[\n// nothing\n]", "[\n  // nothing\n];\n"),
+        ("", ""),
+        ("// only\n\n", "// only\n"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(
+            formatter::format(src, &Options::default()).unwrap(),
+            expected,
+            "formatting {:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn wraps_at_the_line_width() {
+    let src = "var point = { x: 10, y: 20, label: \"origin\" }; f(point, [1, 2, 3]);";
+    let narrow = Options {
+        line_width: 30,
+        indent: 4,
+    };
+    let expected =
+        "var point = {\n    x: 10,\n    y: 20,\n    label: \"origin\"\n};\nf(point, [1, 2, 3]);\n";
+    assert_eq!(formatter::format(src, &narrow).unwrap(), expected);
+    let src = "var r = load_json(path) |> merge(defaults, %) |> normalize(%);";
+    let expected = "var r = load_json(path)\n    |> merge(defaults, %)\n    |> normalize(%);\n";
+    assert_eq!(formatter::format(src, &narrow).unwrap(), expected);
+}
+
+#[test]
+fn syntax_errors_are_not_formatted() {
+    let err = formatter::format("var x = ;", &Options::default()).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            parser::Error::Syntax {
+                line: 1,
+                col: 9,
+                ..
+            }
+        ),
+        "{:?}",
+        err
+    );
+}
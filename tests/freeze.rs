@@ -0,0 +1,53 @@
+// frozen objects can't change: writes are errors in strict mode, and ignored otherwise
+mod common;
+use common::{assert_cases, eval, eval_err};
+
+#[test]
+fn freeze() {
+    assert_cases(&[
+        (
+            "var o = Object.freeze({ a: 1 }); o.a = 2; o.b = 3; [o, Object.isFrozen(o), Object.isFrozen({})]",
+            r#"[{"a": 1}, true, false]"#,
+        ),
+        // the same object, frozen in place
+        ("var o = { a: 1 }; var p = Object.freeze(o); o.a = 2; [o === p, o.a]", "[true, 1]"),
+        // setters still run, but can't change the object either
+        (
+            "var o = Object.freeze({ n: 1, set v(x) { this.n = x; } }); o.v = 5; o.n",
+            "1",
+        ),
+        // primitives are frozen. arrays aren't, even once frozen: unlike javascript, that isn't tracked
+        (
+            "[Object.isFrozen(1), Object.isFrozen(\"a\"), Object.isFrozen([1]), Object.isFrozen(Object.freeze([1]))]",
+            "[true, true, false, false]",
+        ),
+    ]);
+}
+
+#[test]
+fn deep_freeze() {
+    let src = "var d = deepFreeze({ inner: { x: 1 }, list: [{ y: 1 }] });
+        d.inner.x = 2;
+        let frozen = [Object.isFrozen(d), Object.isFrozen(d.inner)];
+        for (const item of d.list) { item.y = 2; frozen = frozen.concat(Object.isFrozen(item)); }
+        [d, frozen]";
+    assert_eq!(
+        eval(src),
+        r#"[{"inner": {"x": 1}, "list": [{"y": 1}]}, [true, true, true]]"#
+    );
+    // a shared object is frozen for every holder
+    let src = "var defaults = { port: 80 }; var cfg = { defaults }; deepFreeze(cfg); defaults.port = 1; defaults.port";
+    assert_eq!(eval(src), "80");
+}
+
+#[test]
+fn strict_writes() {
+    assert_eq!(
+        eval_err("\"use strict\"; var o = Object.freeze({ a: 1 }); o.a = 2;"),
+        "Cannot assign to read only property a of a frozen object"
+    );
+    assert_eq!(
+        eval_err("\"use strict\"; var o = Object.freeze({ a: 1 }); o.b = 2;"),
+        "Cannot add property b, object is not extensible"
+    );
+}
@@ -0,0 +1,73 @@
+// generators stop at each yield, and pick up where they left off on the next call to next()
+mod common;
+use common::{eval, eval_err};
+
+#[test]
+fn next_and_return() {
+    let src = "function* g() { yield 1; yield 2; return 3; }
+        var it = g();
+        [it.next(), it.next(), it.next(), it.next()]";
+    let expected = concat!(
+        r#"[{"value": 1, "done": false}, {"value": 2, "done": false}, "#,
+        r#"{"value": 3, "done": true}, {"done": true}]"#
+    );
+    assert_eq!(eval(src), expected);
+    // nothing runs before the first next()
+    let src = "var log = \"\"; function* g() { log = log + \"a\"; yield 1; } var it = g(); var before = log;
+        it.next(); [before, log]";
+    assert_eq!(eval(src), r#"["", "a"]"#);
+    // a generator is shared: every copy advances the same one
+    let src =
+        "function* g() { yield 1; yield 2; } var a = g(); var b = a; a.next(); b.next().value";
+    assert_eq!(eval(src), "2");
+}
+
+#[test]
+fn resume_value() {
+    // what next(val) sends is the value of the paused yield
+    let src = "function* g() { let a = yield 1; let b = (yield a + 1) * 10; return a + b; }
+        var it = g();
+        [it.next(\"ignored\").value, it.next(5).value, it.next(2).value]";
+    assert_eq!(eval(src), "[1, 6, 25]");
+    let src = "function* g() { var s = \"x\" + (yield 1) + (yield 2); return s; }
+        var it = g(); it.next(); it.next(\"a\"); it.next(\"b\").value";
+    assert_eq!(eval(src), "\"xab\"");
+    let src = "function* g() { var x = 0; x = yield x; yield x; } var it = g(); it.next(); it.next(7).value";
+    assert_eq!(eval(src), "7");
+}
+
+#[test]
+fn yield_star() {
+    let src = "function* inner() { yield 1; yield 2; return \"r\"; }
+        function* outer() { yield 0; yield* inner(); yield* [3, 4]; yield* \"ab\"; }
+        [...outer()]";
+    assert_eq!(eval(src), r#"[0, 1, 2, 3, 4, "a", "b"]"#);
+}
+
+#[test]
+fn control_flow() {
+    let src = "function* range(n) { let i = 0; while (i < n) { yield i; i = i + 1; } }
+        let s = 0; for (const x of range(5)) { s = s + x; } s";
+    assert_eq!(eval(src), "10");
+    let src = "function* g(items) { for (const x of items) { if (x > 1) { yield x * 10; } else yield x; } }
+        [...g([1, 2, 3])]";
+    assert_eq!(eval(src), "[1, 20, 30]");
+    // a generator expression and a generator method
+    let src =
+        "var o = { *keys() { yield \"a\"; } }; var f = function* () { yield* o.keys(); }; [...f()]";
+    assert_eq!(eval(src), r#"["a"]"#);
+}
+
+#[test]
+fn generator_errors() {
+    assert_eq!(
+        eval_err("yield 1;"),
+        "yield is only valid in generator functions"
+    );
+    assert_eq!(
+        eval_err("function* g() { yield x; } g().next()"),
+        "x is not defined"
+    );
+    let src = "function* g() { yield it.next(); } var it = g(); it.next()";
+    assert_eq!(eval_err(src), "Generator is already running");
+}
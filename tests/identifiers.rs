@@ -0,0 +1,67 @@
+// identifiers follow ecmascript: unicode letters, `$`, `_` and `\u` escapes, but no reserved words
+mod common;
+use common::assert_cases;
+use js_light::parser;
+
+fn parse_err(src: &str) -> String {
+    match parser::parse_prg(src) {
+        Ok(_) => panic!("{:?} parses", src),
+        Err(err) => err.to_string(),
+    }
+}
+
+#[test]
+fn names() {
+    assert_cases(&[
+        ("var _private = 1; var $el = 2; [_private, $el]", "[1, 2]"),
+        ("var café = 3; var Ωmega_2 = 4; café + Ωmega_2", "7"),
+        // an escape is the char it stands for
+        ("var ñ = 1; \\u00f1 + \\u{F1}", "2"),
+        ("var a\\u0062 = 5; ab", "5"),
+        ("function $(x) { return x * 2; } $(21)", "42"),
+        // reserved words are fine as property names
+        ("var o = { default: 1, if: 2 }; o.default + o.if", "3"),
+    ]);
+}
+
+#[test]
+fn invalid_names() {
+    let cases = [
+        (
+            "let class = 1;",
+            "line 1, column 5: `class` is a reserved word, it can't be used as a variable or function name",
+        ),
+        (
+            "function let() {}",
+            "line 1, column 10: `let` is a reserved word, it can't be used as a variable or function name",
+        ),
+        (
+            "var undefined = 2;",
+            "line 1, column 5: `undefined` is read-only, it can't be used as a variable or function name",
+        ),
+        (
+            "var a\\u0020b = 1;",
+            "line 1, column 5: Invalid escape \\u0020 in identifier a\\u0020b",
+        ),
+        // a declaration keyword must be followed by a name
+        ("let = 4;", "line 1, column 5: expected a variable name, found `=`"),
+        ("var 1a = 2;", "line 1, column 5: expected a variable name, found `1`"),
+        ("const {a} = o;", "line 1, column 7: expected a variable name, found `{`"),
+        (
+            "let a\\u0 = 1;",
+            "line 1, column 5: invalid escape sequence in identifier: `a\\u0`",
+        ),
+        // unsupported operators aren't taken for names
+        (
+            "typeof 1;",
+            "line 1, column 1: The `typeof` operator is not supported",
+        ),
+        (
+            "var x = void 0;",
+            "line 1, column 9: The `void` operator is not supported",
+        ),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(parse_err(src), expected, "parsing {:?}", src);
+    }
+}
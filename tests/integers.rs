@@ -0,0 +1,94 @@
+// integers are exact: Ints up to i64, then BigInts, from `123n` literals and large json integers
+mod common;
+use common::{assert_cases, eval, eval_err, Rng};
+
+#[test]
+fn bigint_arithmetic() {
+    assert_cases(&[
+        ("18446744073709551616n", "18446744073709551616n"),
+        ("7n / 2n", "3n"),
+        ("-7n / 2n", "-3n"),
+        ("-7n % 2n", "-1n"),
+        ("2n ^ 64n", "18446744073709551616n"),
+        ("-(2n ^ 64n) + 1n", "-18446744073709551615n"),
+        ("(-1n) ^ 1000000000000000000001n", "-1n"),
+        (
+            "99999999999999999999n * 99999999999999999999n",
+            "9999999999999999999800000000000000000001n",
+        ),
+        ("9223372036854775807n + 1n", "9223372036854775808n"),
+        (
+            "[1n == 1, 1n === 1, 1n === 1n, 2n == 2.0, 10n == \"10\", 2n > 1.5, 3n < 2n, 2n ^ 64n > 9223372036854775807]",
+            "[true, false, true, true, true, true, false, true]",
+        ),
+        (
+            "var a = 1; if (0n) { a = 2; } if (3n) { a = a + 10; } a",
+            "11",
+        ),
+        ("\"id: \" + 12n", "\"id: 12\""),
+        ("(5n).toString()", "\"5\""),
+        ("to_json([1n, 2n ^ 70n])", "\"[1,1180591620717411303424]\""),
+        // a number literal without `n` is a float past i64, as in javascript
+        ("18446744073709551616", "18446744073709552000"),
+    ]);
+}
+
+#[test]
+fn bigint_errors() {
+    assert_eq!(eval_err("1n / 0n"), "Division by zero");
+    assert_eq!(eval_err("1n % 0n"), "Division by zero");
+    assert_eq!(
+        eval_err("1n + 1.5"),
+        "Cannot mix BigInt and other types, use explicit conversions"
+    );
+    // not even with an integer, as in javascript
+    for src in ["1n + 1", "2 * 3n", "1n - true", "4n / \"2\"", "2n ^ 2"] {
+        assert_eq!(
+            eval_err(src),
+            "Cannot mix BigInt and other types, use explicit conversions",
+            "running {:?}",
+            src
+        );
+    }
+    assert_eq!(eval_err("+1n"), "Cannot convert a BigInt value to a number");
+    assert_eq!(
+        eval_err("isNaN(1n)"),
+        "Cannot convert a BigInt value to a number"
+    );
+    assert_eq!(eval_err("2n ^ -1n"), "Exponent must be non-negative");
+    assert_eq!(eval_err("2n ^ 2000000n"), "Maximum BigInt size exceeded");
+}
+
+// against i128, which holds the products of two i64
+#[test]
+fn random_arithmetic() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..300 {
+        let [a, b, c] = [(); 3].map(|_| rng.next() as i64 as i128);
+        let d = match rng.next() as i64 as i128 >> rng.below(64) {
+            0 => 1,
+            d => d,
+        };
+        let src = format!(
+            "[({a}n * {b}n + {c}n) / {d}n, {a}n * {b}n % {d}n, {a}n - {b}n, {a}n < {b}n, {a}n * {b}n == {c}n]"
+        );
+        let expected = format!(
+            "[{}n, {}n, {}n, {}, {}]",
+            (a * b + c) / d,
+            a * b % d,
+            a - b,
+            a < b,
+            a * b == c
+        );
+        assert_eq!(eval(&src), expected, "running {:?}", src);
+    }
+}
+
+#[test]
+fn json_ids() {
+    let src = "var o = load_json(\"resources/ids.json\"); [o.id, o.id + 1n, o.small, o.big]";
+    assert_eq!(
+        eval(src),
+        "[18446744073709551615n, 18446744073709551616n, 42, -123456789012345678901234567890n]"
+    );
+}
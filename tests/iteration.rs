@@ -0,0 +1,64 @@
+// for...of walks through the values of an iterable, for...in through the keys of an object
+mod common;
+use common::{assert_cases, eval_err};
+
+#[test]
+fn for_of() {
+    assert_cases(&[
+        ("let s = 0; for (const x of [1, 2, 3]) { s = s + x; } s", "6"),
+        ("let s = \"\"; for (const c of \"héj\") { s = c + s; } s", "\"jéh\""),
+        ("let n = 0; for (let x of []) { n = n + 1; } n", "0"),
+        // each iteration gets its own binding
+        ("var x = 0; for (const x of [1, 2]) {} x", "0"),
+        (
+            "let s = 0; for (const a of [1, 2]) for (const b of [10, 20]) s = s + a * b; s",
+            "90",
+        ),
+        (
+            "var it = { i: 0, next() { this.i = this.i + 1; return { value: this.i, done: this.i > 3 }; } };
+             let s = \"\"; for (const x of it) { s = s + x; } s",
+            "\"123\"",
+        ),
+        ("[...\"ab\", ...[1, 2]]", "[\"a\", \"b\", 1, 2]"),
+    ]);
+}
+
+#[test]
+fn for_in() {
+    assert_cases(&[
+        (
+            "let s = \"\"; for (const k in { b: 1, a: 2 }) { s = s + k; } s",
+            "\"ba\"",
+        ),
+        (
+            "let s = \"\"; for (const i in [7, 8]) { s = s + i; } s",
+            "\"01\"",
+        ),
+        (
+            "let s = \"\"; for (const i in \"xyz\") { s = s + i; } s",
+            "\"012\"",
+        ),
+        ("let n = 0; for (const k in null) { n = n + 1; } n", "0"),
+        (
+            "var o = { a: 1, b: 2 }; let s = 0; for (var k in o) { s = s + o.a; } k",
+            "\"b\"",
+        ),
+    ]);
+}
+
+#[test]
+fn not_iterable() {
+    assert_eq!(eval_err("for (const x of 1) {}"), "Int is not iterable");
+    assert_eq!(
+        eval_err("for (const x of { a: 1 }) {}"),
+        "Object is not iterable, it has no next() method"
+    );
+    assert_eq!(
+        eval_err("for (const x of { next() { return 1; } }) {}"),
+        "Iterator result 1 is not an object"
+    );
+    assert_eq!(
+        eval_err("for (const k in 1) {}"),
+        "Int has no enumerable keys"
+    );
+}
@@ -0,0 +1,554 @@
+// load_json reads strict json: anything else is an error, at its line and column.
+// load_json5 reads json5, which gives the same values for the same json
+use js_light::parser::{self, ast::quote_str};
+use js_light::runner::{parse_json, parse_json5, DuplicateKeys, Runner, Value};
+
+mod common;
+use common::Rng;
+
+fn to_json(val: &Value) -> String {
+    Runner::new()
+        .stringify_json(val, &mut vec![])
+        .unwrap()
+        .unwrap()
+}
+
+fn json(src: &str) -> String {
+    to_json(
+        &parse_json(src, DuplicateKeys::Error).unwrap_or_else(|err| panic!("{:?}: {}", src, err)),
+    )
+}
+
+fn error(src: &str) -> (usize, usize, String) {
+    syntax_error(src, parse_json(src, DuplicateKeys::Error))
+}
+
+fn error5(src: &str) -> (usize, usize, String) {
+    syntax_error(src, parse_json5(src, DuplicateKeys::Error))
+}
+
+fn syntax_error(src: &str, res: parser::Result<Value>) -> (usize, usize, String) {
+    match res {
+        Err(parser::Error::Syntax {
+            line, col, message, ..
+        }) => (line, col, message),
+        res => panic!("{:?} gives {:?}", src, res.map(|val| to_json(&val))),
+    }
+}
+
+// the value with its types, which to_json doesn't tell apart: `1` is an Int, `1.0` a Num
+fn tree(val: &Value) -> String {
+    match val {
+        Value::Int(n) => format!("{}i", n),
+        Value::Num(n) => format!("{:?}", n),
+        Value::Array(elts) => format!("[{}]", elts.iter().map(tree).collect::<Vec<_>>().join(",")),
+        Value::Object(obj) => {
+            let names: Vec<String> = obj
+                .0
+                .borrow()
+                .props
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect();
+            let props: Vec<String> = names
+                .iter()
+                .map(|name| {
+                    format!(
+                        "{}:{}",
+                        quote_str(name),
+                        tree(&Runner::new().get_prop(val, name).unwrap())
+                    )
+                })
+                .collect();
+            format!("{{{}}}", props.join(","))
+        }
+        _ => val.to_string(),
+    }
+}
+
+fn json5(src: &str) -> String {
+    tree(&parse_json5(src, DuplicateKeys::Error).unwrap_or_else(|err| panic!("{:?}: {}", src, err)))
+}
+
+#[test]
+fn values() {
+    let cases = [
+        ("null", "null"),
+        (" true ", "true"),
+        ("\n\tfalse\r\n", "false"),
+        ("0", "0"),
+        ("-0", "0"),
+        ("-12", "-12"),
+        ("1.5", "1.5"),
+        ("1e-6", "0.000001"),
+        ("1E+2", "100"),
+        ("1e400", "null"),
+        (
+            r#""a \"b\" \\ \/ \b\f\n\r\t é 😀""#,
+            r#""a \"b\" \\ / \b\f\n\r\t é 😀""#,
+        ),
+        ("[]", "[]"),
+        ("[1, [2, []], {}]", "[1,[2,[]],{}]"),
+        (
+            r#"{"b": 1, "a": {"c": [null]}}"#,
+            r#"{"b":1,"a":{"c":[null]}}"#,
+        ),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(json(src), expected, "parsing {:?}", src);
+    }
+    let expected = concat!(
+        r#"{"nesting":{"inner object":{}},"an array":[1.5,true,null,0.000001],"#,
+        r#""string with escaped double quotes":"\"quick brown foxes\""}"#
+    );
+    assert_eq!(json(include_str!("../resources/ex1.json")), expected);
+}
+
+#[test]
+fn integers_stay_exact() {
+    let src =
+        "[9007199254740993, -9223372036854775808, 1.0, 9223372036854775808, 18446744073709551615]";
+    let val = parse_json(src, DuplicateKeys::Error);
+    let Ok(Value::Array(elts)) = val else {
+        panic!("{:?}", val.err())
+    };
+    assert!(matches!(elts[0], Value::Int(9007199254740993)));
+    assert!(matches!(elts[1], Value::Int(i64::MIN)));
+    assert!(matches!(elts[2], Value::Num(n) if n == 1.0));
+    // past i64, integers are BigInts
+    assert_eq!(elts[3].to_string(), "9223372036854775808n");
+    assert_eq!(elts[4].to_string(), "18446744073709551615n");
+    assert_eq!(to_json(&elts[4]), "18446744073709551615");
+}
+
+#[test]
+fn not_json() {
+    let cases = [
+        ("{a: 1 + x}", "expected a string key, found `a`"),
+        (
+            r#"{"a": 1 + x}"#,
+            "expected `,` or `}` after the property, found `+`",
+        ),
+        (r#"{"a": x}"#, "expected a json value, found `x`"),
+        (r#"{"a": 1,}"#, "expected a string key, found `}`"),
+        ("[1, 2,]", "expected a json value, found `]`"),
+        (r#"{"a" 1}"#, "expected `:` after the key, found `1`"),
+        ("'a'", "expected a json value, found `'`"),
+        ("undefined", "expected a json value, found `undefined`"),
+        ("NaN", "expected a json value, found `NaN`"),
+        ("-Infinity", "expected a digit, found `Infinity`"),
+        ("+1", "expected a json value, found `+`"),
+        (".5", "expected a json value, found `.`"),
+        (
+            "1.",
+            "expected a digit after `.`, found the end of the input",
+        ),
+        (
+            "1e",
+            "expected a digit in the exponent, found the end of the input",
+        ),
+        ("012", "Leading zeros are not allowed in json numbers"),
+        ("0x1F", "expected the end of the json, found `x1F`"),
+        ("// c\n1", "expected a json value, found `/`"),
+        ("1 2", "expected the end of the json, found `2`"),
+        ("", "expected a json value, found the end of the input"),
+        ("tru", "expected a json value, found `tru`"),
+        (r#""a\x""#, "Invalid escape in a json string: `\\x`"),
+        (r#""\u12""#, "Invalid unicode escape: `\\u12`"),
+        (
+            "\"a\nb\"",
+            "Control characters must be escaped in json strings",
+        ),
+        ("\"abc", "Unterminated string"),
+        ("[\u{feff}1]", "expected a json value, found `\u{feff}`"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(error(src).2, expected, "parsing {:?}", src);
+    }
+    let deep = "[".repeat(300) + &"]".repeat(300);
+    assert!(error(&deep).2.starts_with("Nested too deeply"));
+}
+
+#[test]
+fn errors_have_a_line_and_column() {
+    assert_eq!(
+        error("{\n  \"a\": 1,\n  b: 2\n}"),
+        (3, 3, String::from("expected a string key, found `b`"))
+    );
+    assert_eq!(
+        error("[\n  \"é\", é\n]"),
+        (2, 8, String::from("expected a json value, found `é`"))
+    );
+    // the byte order mark isn't a column
+    assert_eq!(
+        error("\u{feff}{\"a\" 1}"),
+        (1, 6, String::from("expected `:` after the key, found `1`"))
+    );
+}
+
+#[test]
+fn byte_order_mark() {
+    assert_eq!(json("\u{feff}{\"a\": 1}"), r#"{"a":1}"#);
+}
+
+#[test]
+fn duplicate_keys() {
+    let src = "{\"a\": 1, \"b\": 2,\n \"a\": 3}";
+    assert_eq!(error(src), (2, 2, String::from("Duplicate key \"a\"")));
+    let val = parse_json(src, DuplicateKeys::LastWins).unwrap();
+    assert_eq!(to_json(&val), r#"{"a":3,"b":2}"#);
+    // the same name, escaped differently
+    assert_eq!(error(r#"{"é": 1, "é": 2}"#).2, "Duplicate key \"é\"");
+}
+
+// random json, written with random spacing and escapes, along with what JSON.stringify gives for it
+struct JsonGen {
+    rng: Rng,
+    out: String,
+    expected: String,
+    // with comments, single quotes, unquoted keys, trailing commas and json5 numbers
+    json5: bool,
+}
+
+const NUMBERS: &[(&str, &str)] = &[
+    ("0", "0"),
+    ("-0", "0"),
+    ("42", "42"),
+    ("-7", "-7"),
+    ("9007199254740993", "9007199254740993"),
+    ("0.5", "0.5"),
+    ("-1.25e3", "-1250"),
+    ("1E-7", "1e-7"),
+    ("2e21", "2e+21"),
+    ("12345678901234567890", "12345678901234567890"),
+    ("-98765432109876543210123", "-98765432109876543210123"),
+];
+const NUMBERS5: &[(&str, &str)] = &[
+    ("0x2a", "42"),
+    ("-0X10", "-16"),
+    ("+7", "7"),
+    (".5", "0.5"),
+    ("-5.", "-5"),
+    ("Infinity", "null"),
+    ("NaN", "null"),
+];
+const CHARS: &[(&str, &str)] = &[
+    ("a", "a"),
+    (" ", " "),
+    ("\\u0041", "A"),
+    ("\\\"", "\""),
+    ("\\\\", "\\"),
+    ("\\/", "/"),
+    ("/", "/"),
+    ("\\n", "\n"),
+    ("\\t", "\t"),
+    ("\\u001f", "\u{1f}"),
+    ("é", "é"),
+    ("\\u00E9", "é"),
+    ("😀", "😀"),
+    ("\\uD83D\\uDE00", "😀"),
+    ("'", "'"),
+];
+
+impl JsonGen {
+    fn space(&mut self) {
+        let space = self.rng.pick(&["", "", " ", "\n", "\t", "\r\n  "]);
+        self.out.push_str(space);
+        if self.json5 && self.rng.below(4) == 0 {
+            let comment = self.rng.pick(&["/* c */", "// c\n", "/**/"]);
+            self.out.push_str(comment);
+        }
+    }
+
+    fn string(&mut self, suffix: &str) -> String {
+        let mut decoded = String::new();
+        let quote = if self.json5 && self.rng.below(2) == 0 {
+            "'"
+        } else {
+            "\""
+        };
+        self.out.push_str(quote);
+        for _ in 0..self.rng.below(5) {
+            let (raw, c) = CHARS[self.rng.below(CHARS.len())];
+            self.out.push_str(if raw == quote { "\\'" } else { raw });
+            decoded.push_str(c);
+        }
+        self.out.push_str(suffix);
+        self.out.push_str(quote);
+        decoded + suffix
+    }
+
+    fn value(&mut self, depth: usize) {
+        self.space();
+        let choices = if depth > 4 { 6 } else { 8 };
+        match self.rng.below(choices) {
+            0 => self.push("null", "null"),
+            1 => self.push("true", "true"),
+            2 => self.push("false", "false"),
+            3 | 4 => {
+                let numbers = if self.json5 && self.rng.below(2) == 0 {
+                    NUMBERS5
+                } else {
+                    NUMBERS
+                };
+                let (raw, expected) = numbers[self.rng.below(numbers.len())];
+                self.push(raw, expected);
+            }
+            5 => {
+                let s = self.string("");
+                self.expected.push_str(&quote_str(&s));
+            }
+            6 => {
+                self.push("[", "[");
+                let len = self.rng.below(4);
+                for idx in 0..len {
+                    if idx > 0 {
+                        self.push(",", ",");
+                    }
+                    self.value(depth + 1);
+                }
+                self.trailing_comma(len);
+                self.space();
+                self.push("]", "]");
+            }
+            _ => {
+                self.push("{", "{");
+                let len = self.rng.below(4);
+                for idx in 0..len {
+                    if idx > 0 {
+                        self.push(",", ",");
+                    }
+                    self.space();
+                    if self.json5 && self.rng.below(2) == 0 {
+                        let key = format!("k{}", idx);
+                        self.push(&key, &quote_str(&key));
+                    } else {
+                        // keys are unique, whatever their escapes
+                        let key = self.string(&idx.to_string());
+                        self.expected.push_str(&quote_str(&key));
+                    }
+                    self.space();
+                    self.push(":", ":");
+                    self.value(depth + 1);
+                }
+                self.trailing_comma(len);
+                self.space();
+                self.push("}", "}");
+            }
+        }
+        self.space();
+    }
+
+    fn trailing_comma(&mut self, len: usize) {
+        if self.json5 && len > 0 && self.rng.below(3) == 0 {
+            self.space();
+            self.out.push(',');
+        }
+    }
+
+    fn push(&mut self, raw: &str, expected: &str) {
+        self.out.push_str(raw);
+        self.expected.push_str(expected);
+    }
+}
+
+#[test]
+fn random_json() {
+    for seed in 1..=2000u64 {
+        let mut gen = JsonGen {
+            rng: Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15)),
+            out: String::new(),
+            expected: String::new(),
+            json5: false,
+        };
+        gen.value(0);
+        assert_eq!(json(&gen.out), gen.expected, "parsing {:?}", gen.out);
+        // json is json5 too
+        let val = parse_json(&gen.out, DuplicateKeys::Error).unwrap();
+        assert_eq!(
+            json5(&gen.out),
+            tree(&val),
+            "parsing {:?} as json5",
+            gen.out
+        );
+        // a json value is a javascript value too, and the same one, except for integers past
+        // i64: javascript rounds them to floats
+        let big = NUMBERS
+            .iter()
+            .any(|(raw, expected)| raw == expected && raw.len() > 19 && gen.out.contains(raw));
+        if big {
+            continue;
+        }
+        let prg = parser::parse_prg(&format!("to_json({});", gen.out)).unwrap();
+        match Runner::new().run_prg(&prg) {
+            Ok(Value::Str(res)) => assert_eq!(res, gen.expected, "running {:?}", gen.out),
+            res => panic!(
+                "running {:?} gives {:?}",
+                gen.out,
+                res.map(|val| val.to_string())
+            ),
+        }
+    }
+}
+
+#[test]
+fn random_json5() {
+    for seed in 1..=2000u64 {
+        let mut gen = JsonGen {
+            rng: Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15)),
+            out: String::new(),
+            expected: String::new(),
+            json5: true,
+        };
+        gen.value(0);
+        let val = parse_json5(&gen.out, DuplicateKeys::Error)
+            .unwrap_or_else(|err| panic!("{:?}: {}", gen.out, err));
+        assert_eq!(to_json(&val), gen.expected, "parsing {:?}", gen.out);
+    }
+}
+
+#[test]
+fn load_json_options() {
+    let run = |src: &str| {
+        Runner::new()
+            .run_prg(&parser::parse_prg(src).unwrap())
+            .map(|val| val.to_string())
+    };
+    let res = run("load_json(\"resources/ex1.json\", { duplicateKeys: \"lastWins\" }).nesting;");
+    assert_eq!(res.unwrap(), r#"{"inner object": {}}"#);
+    let err = run("load_json(\"resources/ex1.json\", { duplicateKeys: \"first\" });").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"duplicateKeys must be "error" or "lastWins", not "first""#
+    );
+}
+
+#[test]
+fn json5_values() {
+    let cases = [
+        (
+            "// a comment\n{a: 1, /* b */ 'b': 'x',}",
+            r#"{"a":1i,"b":"x"}"#,
+        ),
+        ("[1, 2, ]", "[1i,2i]"),
+        (
+            "{$_é1: null, \\u0061b: true, if: 0}",
+            r#"{"$_é1":null,"ab":true,"if":0i}"#,
+        ),
+        ("{Infinity: NaN}", r#"{"Infinity":NaN}"#),
+        (
+            "'a \"b\" \\' \\v\\0 \\x41 \\q'",
+            r#""a \"b\" ' \u000b\u0000 A q""#,
+        ),
+        ("\"line \\\n continued\\\r\n\"", r#""line  continued""#),
+        ("'\u{2028}\t'", "\"\u{2028}\\t\""),
+        ("0x1F", "31i"),
+        ("-0XFF", "-255i"),
+        ("0xFFFFFFFFFFFFFFFF", "18446744073709551615n"),
+        ("-0x10000000000000000", "-18446744073709551616n"),
+        ("+1", "1i"),
+        ("+1.5", "1.5"),
+        (".5", "0.5"),
+        ("-.5", "-0.5"),
+        ("5.", "5.0"),
+        ("5.e1", "50.0"),
+        ("Infinity", "inf"),
+        ("-Infinity", "-inf"),
+        ("+NaN", "NaN"),
+        ("\u{feff}\u{a0}\u{b}\u{c}1\u{2029}", "1i"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(json5(src), expected, "parsing {:?}", src);
+    }
+}
+
+#[test]
+fn json5_same_values() {
+    // the same json gives the same tree, whatever the syntax
+    let strict = r#"{"a": [1, 1.0, -0, 1e2, "x"], "b c": {"d": null}}"#;
+    let relaxed = "{a: [0x1, 1., -0, +1e2, 'x',], 'b c': {d: null /* nothing */}} // end";
+    let val = parse_json(strict, DuplicateKeys::Error).unwrap();
+    assert_eq!(json5(relaxed), tree(&val));
+    assert_eq!(json5(strict), tree(&val));
+}
+
+#[test]
+fn not_json5() {
+    let cases = [
+        (
+            "{a: 1 + x}",
+            "expected `,` or `}` after the property, found `+`",
+        ),
+        ("{a b: 1}", "expected `:` after the key, found `b`"),
+        ("{1: 1}", "expected a key, found `1`"),
+        ("{a: x}", "expected a json5 value, found `x`"),
+        ("[1,,]", "expected a json5 value, found `,`"),
+        ("{,}", "expected a key, found `,`"),
+        ("undefined", "expected a json5 value, found `undefined`"),
+        ("012", "Leading zeros are not allowed in json5 numbers"),
+        (
+            ".",
+            "expected a digit after `.`, found the end of the input",
+        ),
+        ("+-1", "expected a digit, found `-`"),
+        ("0x", "expected a hex digit, found the end of the input"),
+        ("1 /* never closed", "Unterminated comment"),
+        ("'a\nb'", "Line breaks must be escaped in json5 strings"),
+        ("'abc", "Unterminated string"),
+        ("'\\1'", "Invalid escape in a json5 string: `\\1`"),
+        ("'\\x4'", "Invalid hex escape: `\\x4`"),
+        ("1 2", "expected the end of the json5, found `2`"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(error5(src).2, expected, "parsing {:?}", src);
+    }
+}
+
+#[test]
+fn json5_errors_have_a_line_and_column() {
+    let src = "{\n  // the port\n  port: 8080,\n  /* the host */ host: localhost,\n}";
+    assert_eq!(
+        error5(src),
+        (
+            4,
+            24,
+            String::from("expected a json5 value, found `localhost`")
+        )
+    );
+    assert_eq!(
+        error5("{a: 1,\n a: 2}"),
+        (2, 2, String::from("Duplicate key \"a\""))
+    );
+    assert_eq!(
+        error5("[\n  1, // é\n"),
+        (
+            3,
+            1,
+            String::from("expected a json5 value, found the end of the input")
+        )
+    );
+    assert_eq!(
+        error5("[1, /* é"),
+        (1, 5, String::from("Unterminated comment"))
+    );
+}
+
+#[test]
+fn load_json5() {
+    let run = |src: &str| {
+        Runner::new()
+            .run_prg(&parser::parse_prg(src).unwrap())
+            .map(|val| val.to_string())
+    };
+    let res = run("load_json5(\"resources/ex1.json5\", { duplicateKeys: \"lastWins\" }).servers;");
+    let expected =
+        r#"[{"host": "a.example.com", "port": 8080}, {"host": "b.example.com", "port": 8443}]"#;
+    assert_eq!(res.unwrap(), expected);
+    let err = run("load_json(\"resources/ex1.json5\");").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("1, column 1: expected a json value, found `/`"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,120 @@
+// malformed input is an error, never a panic: nesting, recursion and run time are limited
+use std::thread;
+
+mod common;
+use common::{eval_err, mutate, Gen, Rng, PROGRAMS};
+use js_light::parser;
+use js_light::runner::{parse_json, parse_json5, DuplicateKeys, Error, Runner};
+
+// deep recursions need more than the stack of a test thread in a debug build, like the CLI
+fn on_big_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    thread::Builder::new()
+        .stack_size(1 << 30)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+// timers far in the future once overflowed the virtual clock. they are fuzzing seeds too
+const HUGE_DELAYS: &[&str] = &[
+    "await sleep(5); setTimeout(function() {}, 1e300);",
+    "await sleep(1e300); await sleep(1e300);",
+    "setTimeout(function() {}, Infinity); setTimeout(function() {}, -1e300); await sleep(2147483648);",
+];
+
+fn run_limited(src: &str) -> Option<Error> {
+    let prg = parser::parse_prg(src).ok()?;
+    let mut runner = Runner::new();
+    runner.step_limit = Some(10_000);
+    runner.run_prg(&prg).err()
+}
+
+#[test]
+fn nesting() {
+    on_big_stack(|| {
+        let src = format!("{}{}", "[".repeat(300), "]".repeat(300));
+        assert_eq!(
+            parser::parse_prg(&src).unwrap_err().to_string(),
+            "line 1, column 256: Nested too deeply: more than 256 levels"
+        );
+        assert!(parse_json(&src, DuplicateKeys::Error).is_err());
+        assert!(parse_json5(&"{a:".repeat(300), DuplicateKeys::Error).is_err());
+        // below the limit, it runs
+        let src = format!("{}1{}", "(".repeat(200), ")".repeat(200));
+        assert!(run_limited(&src).is_none());
+    });
+}
+
+#[test]
+fn recursion_and_steps() {
+    let err = on_big_stack(|| {
+        run_limited("function f(n) { return f(n + 1); } f(0)").map(|err| err.inner().to_string())
+    });
+    assert_eq!(err.as_deref(), Some("Maximum call stack size exceeded"));
+    assert!(matches!(
+        run_limited("while (true) {}").as_ref().map(Error::inner),
+        Some(Error::StepLimit(10_000))
+    ));
+    assert!(matches!(
+        run_limited("function* g() { while (true) { yield 1; } } for (const x of g()) {}")
+            .as_ref()
+            .map(Error::inner),
+        Some(Error::StepLimit(10_000))
+    ));
+}
+
+#[test]
+fn bad_values() {
+    let cases = [
+        ("nope", "nope is not defined"),
+        (
+            "load_json(\"resources/ex1.js_new\")",
+            "Invalid json in resources/ex1.js_new: line 1, column 1: expected a json value, found `var`",
+        ),
+        ("\"a\".repeat(1e10)", "Invalid string length"),
+        ("null.x", "Unknown method: x for Null, which has no methods"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(eval_err(src), expected, "running {:?}", src);
+    }
+}
+
+#[test]
+fn huge_delays() {
+    for src in HUGE_DELAYS {
+        assert!(run_limited(src).is_none(), "running {:?}", src);
+    }
+    // the same programs as the fuzzing seeds
+    for src in HUGE_DELAYS {
+        let found = std::fs::read_dir("fuzz/seeds/run_prg")
+            .unwrap()
+            .any(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap() == *src);
+        assert!(found, "{:?} isn't in fuzz/seeds/run_prg", src);
+    }
+}
+
+// random and broken programs: only the outcome matters, not what it is
+#[test]
+fn no_panics() {
+    on_big_stack(|| {
+        let mut rng = Rng(0x51_7cc1_b727_220a);
+        for src in PROGRAMS.iter().chain(HUGE_DELAYS) {
+            run_limited(src);
+            for _ in 0..20 {
+                let mutated = mutate(&mut rng, src);
+                run_limited(&mutated);
+                let _ = parse_json(&mutated, DuplicateKeys::Error);
+                let _ = parse_json5(&mutated, DuplicateKeys::LastWins);
+            }
+        }
+        for seed in 1..=200u64 {
+            let mut gen = Gen {
+                rng: Rng(seed.wrapping_mul(0x2545_f491_4f6c_dd1d)),
+                out: String::new(),
+            };
+            gen.stmts(0);
+            run_limited(&gen.out);
+        }
+    });
+}
@@ -0,0 +1,89 @@
+// methods are looked up by the type of the value they are called on
+mod common;
+use common::{assert_cases, eval_err};
+
+#[test]
+fn string_methods() {
+    assert_cases(&[
+        ("\"héllo\".length", "5"),
+        ("\"abc\".toUpperCase()", "\"ABC\""),
+        ("\"ABC\".toLowerCase()", "\"abc\""),
+        ("\"  a b  \".trim()", "\"a b\""),
+        ("\"a,b,,c\".split(\",\")", "[\"a\", \"b\", \"\", \"c\"]"),
+        ("\"abc\".split(\"\")", "[\"a\", \"b\", \"c\"]"),
+        ("\"abc\".split()", "[\"abc\"]"),
+        ("\"abc\".includes(\"bc\")", "true"),
+        ("\"abc\".startsWith(\"ab\")", "true"),
+        ("\"abc\".endsWith(\"ab\")", "false"),
+        ("\"é-a\".indexOf(\"a\")", "2"),
+        ("\"abc\".indexOf(\"x\")", "-1"),
+        ("\"ab\".repeat(3)", "\"ababab\""),
+        ("\"a\".toString()", "\"a\""),
+        ("var s = \"x\"; s.toUpperCase() + s", "\"Xx\""),
+    ]);
+}
+
+#[test]
+fn number_and_array_methods() {
+    assert_cases(&[
+        ("1.005.toFixed(2)", "\"1.00\""),
+        ("(2).toFixed()", "\"2\""),
+        // ties are rounded away from zero, on the exact value of the number
+        ("(2.5).toFixed(0)", "\"3\""),
+        ("(0.5).toFixed(0)", "\"1\""),
+        ("(-2.5).toFixed(0)", "\"-3\""),
+        ("(1.125).toFixed(2)", "\"1.13\""),
+        ("(9.995).toFixed(2)", "\"9.99\""),
+        ("(99.5).toFixed()", "\"100\""),
+        ("(-0.0001).toFixed(2)", "\"-0.00\""),
+        ("(-0).toFixed(1)", "\"0.0\""),
+        ("(1e-7).toFixed(10)", "\"0.0000001000\""),
+        // numbers without a fixed notation are printed as they are
+        ("NaN.toFixed(2)", "\"NaN\""),
+        ("(-Infinity).toFixed(2)", "\"-Infinity\""),
+        ("(1e21).toFixed(2)", "\"1e+21\""),
+        ("(123.456e20).toFixed(1)", "\"1.23456e+22\""),
+        ("(1e20).toFixed(2)", "\"100000000000000000000.00\""),
+        ("(1.5).toString()", "\"1.5\""),
+        ("[1, 2, 3].length", "3"),
+        ("[1, null, \"a\"].join(\"-\")", "\"1--a\""),
+        ("[1, 2].join()", "\"1,2\""),
+        ("[1, 2].includes(2)", "true"),
+        ("[1, 2].indexOf(3)", "-1"),
+        ("[1].concat([2, 3]).concat(4)", "[1, 2, 3, 4]"),
+        (
+            "var a = [1, 2]; var b = a.reverse(); [a, b]",
+            "[[1, 2], [2, 1]]",
+        ),
+        ("true.toString()", "\"true\""),
+    ]);
+}
+
+#[test]
+fn method_errors() {
+    assert_eq!(
+        eval_err("(1).nope()"),
+        "Unknown method: nope for Int. Available methods: toFixed, toString"
+    );
+    assert_eq!(
+        eval_err("null.x"),
+        "Unknown method: x for Null, which has no methods"
+    );
+    assert_eq!(
+        eval_err("\"a\".length()"),
+        "length is a property of Str, not a method"
+    );
+    assert_eq!(
+        eval_err("\"a\".trim"),
+        "trim is a method of Str, not a property"
+    );
+    assert_eq!(
+        eval_err("\"a\".repeat(1, 2)"),
+        "invalid number of params for Str.repeat. Expected 1, got 2"
+    );
+    assert_eq!(eval_err("\"a\".repeat(-1)"), "invalid repeat count -1");
+    assert_eq!(
+        eval_err("(1).toFixed(101)"),
+        "toFixed() digits argument must be between 0 and 100, got 101"
+    );
+}
@@ -0,0 +1,70 @@
+// numbers print like javascript's Number::toString, and NaN and Infinity are literals
+mod common;
+use common::assert_cases;
+
+#[test]
+fn formatting() {
+    assert_cases(&[
+        ("1e21", "1e+21"),
+        ("1e20", "100000000000000000000"),
+        ("0.1 + 0.2", "0.30000000000000004"),
+        ("1e-7", "1e-7"),
+        ("0.000001", "0.000001"),
+        ("1 / 3", "0.3333333333333333"),
+        ("-1.5e-10", "-1.5e-10"),
+        ("1.5e300", "1.5e+300"),
+        ("123e-20", "1.23e-18"),
+        ("0 * -1.5", "0"),
+        ("5 / 2", "2.5"),
+        ("2 ^ 0.5", "1.4142135623730951"),
+        ("[-7 % 3, 5.5 % 2]", "[-1, 1.5]"),
+        ("\"\" + 1e21", "\"1e+21\""),
+        ("(0.5).toString()", "\"0.5\""),
+    ]);
+}
+
+#[test]
+fn integers() {
+    assert_cases(&[
+        ("9007199254740993 + 1", "9007199254740994"),
+        ("2 ^ 10", "1024"),
+        // overflows to a float
+        ("9223372036854775807 + 1", "9223372036854776000"),
+        ("6 / 3", "2"),
+    ]);
+}
+
+#[test]
+fn non_finite() {
+    assert_cases(&[
+        ("[NaN, Infinity, -Infinity]", "[NaN, Infinity, -Infinity]"),
+        (
+            "[100 / 0, -1 / 0, 0 / 0, 1e300 * 1e10]",
+            "[Infinity, -Infinity, NaN, Infinity]",
+        ),
+        (
+            "[isNaN(\"x\"), isNaN(\"1\"), isNaN(NaN), isFinite(1 / 0), isFinite(\"5\")]",
+            "[true, false, true, false, true]",
+        ),
+        // json has no NaN or Infinity
+        ("to_json([NaN, Infinity, 1e21])", r#""[null,null,1e+21]""#),
+    ]);
+}
+
+#[test]
+fn negative_zero() {
+    assert_cases(&[
+        ("1 / -0", "-Infinity"),
+        ("1 / (0 * -1)", "-Infinity"),
+        ("1 / (-3 * 0)", "-Infinity"),
+        ("1 / (0 / -5)", "-Infinity"),
+        ("1 / (-4 % 2)", "-Infinity"),
+        ("1 / -(0)", "-Infinity"),
+        // -0 prints as 0, and is equal to 0
+        (
+            "[-0, 0 * -1, -0 === 0, 1 / (0 * 1), 1 / (0 - 0)]",
+            "[0, 0, true, Infinity, Infinity]",
+        ),
+        ("to_json(-0)", "\"0\""),
+    ]);
+}
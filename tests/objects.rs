@@ -0,0 +1,56 @@
+// object literals: shorthand and computed keys, methods, getters and setters
+mod common;
+use common::{assert_cases, eval_err};
+
+#[test]
+fn properties() {
+    assert_cases(&[
+        (
+            "var name = \"a\"; var greetings = 1; ({ name, greetings })",
+            r#"{"name": "a", "greetings": 1}"#,
+        ),
+        (
+            "var prefix = \"user\"; ({ [prefix + \"_id\"]: 42, \"b c\": 2 })",
+            r#"{"user_id": 42, "b c": 2}"#,
+        ),
+        // the last one wins, at the place of the first
+        (
+            "var k = \"a\"; ({ a: 0, b: 1, [k]: 2 })",
+            r#"{"a": 2, "b": 1}"#,
+        ),
+        ("var o = { a: 1 }; o.b = 2; o", r#"{"a": 1, "b": 2}"#),
+        // objects are shared
+        ("var o = { a: 1 }; var p = o; p.a = 2; o.a", "2"),
+    ]);
+}
+
+#[test]
+fn methods_and_accessors() {
+    assert_cases(&[
+        ("var o = { f(x) { return x + 1; } }; o.f(1)", "2"),
+        (
+            "var o = { x: 1, getX() { return this.x; } }; o.x = 5; o.getX()",
+            "5",
+        ),
+        (
+            "var c = { n: 1, get double() { return this.n * 2; }, set double(v) { this.n = v / 2; } };
+             c.double = 10; [c.n, c.double]",
+            "[5, 10]",
+        ),
+        (
+            "({ f() {}, get g() { return 1; } })",
+            r#"{"f": [Function: f], "g": [Getter]}"#,
+        ),
+        // getters are called, methods left out
+        (
+            "to_json({ a: 1, get g() { return 2; }, f() {} })",
+            r#""{\"a\":1,\"g\":2}""#,
+        ),
+        // without a setter, an assignment is ignored
+        ("var o = { get x() { return 1; } }; o.x = 2; o.x", "1"),
+    ]);
+    assert_eq!(
+        eval_err("\"use strict\"; var o = { get x() { return 1; } }; o.x = 2;"),
+        "Cannot set property x, which only has a getter"
+    );
+}
@@ -0,0 +1,75 @@
+// default params are evaluated at call time, and `arguments` makes a function variadic
+mod common;
+use common::{assert_cases, eval, eval_err};
+
+#[test]
+fn defaults() {
+    assert_cases(&[
+        (
+            "function f(a, b = a * 2) { return [a, b]; } [f(1), f(1, 5)]",
+            "[[1, 2], [1, 5]]",
+        ),
+        // evaluated at each call which needs them
+        (
+            "var n = 0; function g() { n = n + 1; return n; } function f(a = g()) { return a; }
+             [f(), f(), f(7), n]",
+            "[1, 2, 7, 2]",
+        ),
+        // undefined takes the default
+        (
+            "function f(a = 1, b) { return [a, b]; } f(undefined, 2)",
+            "[1, 2]",
+        ),
+    ]);
+}
+
+#[test]
+fn arguments() {
+    assert_cases(&[
+        (
+            "function sum() { var s = 0; for (const x of arguments) { s = s + x; } return s; }
+             [sum(), sum(1, 2, 3)]",
+            "[0, 6]",
+        ),
+        ("function f(a) { return arguments; } f(1, 2)", "[1, 2]"),
+        // its own for each function
+        (
+            "function outer(a, b) { function inner() { return arguments; } return inner(3); } outer(1, 2)",
+            "[3]",
+        ),
+    ]);
+}
+
+#[test]
+fn arity() {
+    let cases = [
+        (
+            "function f(a, b = 1) {} f()",
+            "invalid number of params for f. Expected 1 to 2, got 0",
+        ),
+        (
+            "function f(a, b = 1) {} f(1, 2, 3)",
+            "invalid number of params for f. Expected 1 to 2, got 3",
+        ),
+        (
+            "function f(a, b) { return arguments.length; } f(1)",
+            "invalid number of params for f. Expected at least 2, got 1",
+        ),
+        (
+            "min(1)",
+            "invalid number of params for min. Expected 2, got 1",
+        ),
+        (
+            "[1].join(\",\", 2)",
+            "invalid number of params for Array.join. Expected 0 to 1, got 2",
+        ),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(eval_err(src), expected, "running {:?}", src);
+    }
+    // builtins with optional params
+    assert_eq!(
+        eval("[[1, 2].join(), [1, 2].join(\"-\")]"),
+        r#"["1,2", "1-2"]"#
+    );
+}
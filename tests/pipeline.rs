@@ -0,0 +1,38 @@
+// `|>` is a hack-style pipeline: each step is an expression where `%` is the previous value
+mod common;
+use common::{assert_cases, eval_err};
+use js_light::parser;
+
+#[test]
+fn pipelines() {
+    assert_cases(&[
+        (
+            "function double(x) { return x * 2; } 3 |> double(%) |> % + 1",
+            "7",
+        ),
+        ("\" a \" |> %.trim() |> %.toUpperCase()", "\"A\""),
+        ("[1, 2] |> %.concat(3) |> min(%.length, 10)", "3"),
+        // the lowest precedence
+        ("1 + 2 |> % * 10", "30"),
+        // `%` is still the modulo operator between two values
+        ("var x = 9; x |> % % 7", "2"),
+        // the inner pipeline has its own topic
+        ("1 |> (2 |> % + %) + %", "5"),
+        (
+            "async function f(x) { return x + 1; } await (1 |> await f(%))",
+            "2",
+        ),
+    ]);
+}
+
+#[test]
+fn topic_errors() {
+    assert_eq!(
+        eval_err("%"),
+        "The topic reference % is only valid in the body of a pipeline"
+    );
+    assert_eq!(
+        parser::parse_prg("1 |> 2").unwrap_err().to_string(),
+        "line 1, column 6: Pipeline body 2 doesn't use the topic reference %"
+    );
+}
@@ -0,0 +1,557 @@
+// printing a tree and parsing it back gives the same tree: the printer puts parentheses where the
+// precedence needs them, even when the tree was built in code, and escapes strings and keys
+use std::rc::Rc;
+
+use js_light::parser;
+use js_light::parser::ast::{
+    self, DeclKind, Expr, ExprKind, FunctionDef, InfixOp, Literal, Param, PrefixOp, Prg, Prop,
+    PropKey, Span, Stmt, StmtKind,
+};
+
+mod common;
+use common::{mutate, Gen, Rng, PROGRAMS};
+
+fn without_spans(tree: String) -> String {
+    let mut res = String::with_capacity(tree.len());
+    let mut rest = tree.as_str();
+    while let Some(idx) = rest.find("span: Span { ") {
+        res.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        rest = &rest[rest.find(" }").unwrap() + 2..];
+    }
+    res.push_str(rest);
+    res
+}
+
+// the parentheses the printer adds are in the parsed tree, and only them
+fn without_parens(stmts: &mut [Stmt]) {
+    for stmt in stmts {
+        match &mut stmt.kind {
+            StmtKind::Assign(_, _, val) | StmtKind::Return(val) => {
+                val.iter_mut().for_each(|val| strip(val))
+            }
+            StmtKind::Reassign(_, val) | StmtKind::Expr(val) => strip(val),
+            StmtKind::SetMember(obj, _, val) => {
+                strip(obj);
+                strip(val);
+            }
+            StmtKind::Block(stmts) => without_parens(stmts),
+            StmtKind::ForOf(_, _, val, body)
+            | StmtKind::ForIn(_, _, val, body)
+            | StmtKind::While(val, body) => {
+                strip(val);
+                without_parens(std::slice::from_mut(body));
+            }
+            StmtKind::If(cond, then, otherwise) => {
+                strip(cond);
+                without_parens(std::slice::from_mut(then));
+                otherwise
+                    .iter_mut()
+                    .for_each(|otherwise| without_parens(std::slice::from_mut(otherwise)));
+            }
+            StmtKind::Function(def) => strip_function(def),
+        }
+    }
+}
+
+fn strip(expr: &mut Expr) {
+    if let ExprKind::Parens(inner) = &mut expr.kind {
+        *expr = std::mem::replace(inner, ExprKind::This.at(Span::default()));
+        return strip(expr);
+    }
+    match &mut expr.kind {
+        ExprKind::Infix(_, lhs, rhs) | ExprKind::Pipe(lhs, rhs) => {
+            strip(lhs);
+            strip(rhs);
+        }
+        ExprKind::Prefix(_, val)
+        | ExprKind::Member(val, _)
+        | ExprKind::YieldStar(val)
+        | ExprKind::Await(val)
+        | ExprKind::Spread(val)
+        | ExprKind::Yield(Some(val)) => strip(val),
+        ExprKind::FctCall(_, args) | ExprKind::Literal(Literal::Array(args)) => {
+            args.iter_mut().for_each(strip)
+        }
+        ExprKind::MethodCall(obj, _, args) => {
+            strip(obj);
+            args.iter_mut().for_each(strip);
+        }
+        ExprKind::Function(def) => strip_function(def),
+        ExprKind::Literal(Literal::Object(props)) => {
+            for prop in props {
+                match prop {
+                    Prop::Value(key, val) => {
+                        strip_key(key);
+                        strip(val);
+                    }
+                    Prop::Shorthand(_) => {}
+                    Prop::Method(key, def) | Prop::Getter(key, def) | Prop::Setter(key, def) => {
+                        strip_key(key);
+                        strip_function(def);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn strip_key(key: &mut PropKey) {
+    if let PropKey::Computed(expr) = key {
+        strip(expr);
+    }
+}
+
+fn strip_function(def: &mut Rc<FunctionDef>) {
+    let def = Rc::make_mut(def);
+    def.params
+        .iter_mut()
+        .for_each(|param| param.default.iter_mut().for_each(strip));
+    without_parens(&mut def.body);
+}
+
+fn same_tree(prg: &Prg) {
+    let printed = prg.to_string();
+    let reparsed = parser::parse_prg(&printed)
+        .unwrap_or_else(|err| panic!("{:?} doesn't parse: {}", printed, err));
+    let (mut prg, mut reparsed) = (prg.clone(), reparsed);
+    without_parens(&mut prg.stmts);
+    without_parens(&mut reparsed.stmts);
+    assert_eq!(
+        without_spans(format!("{:?}", reparsed)),
+        without_spans(format!("{:?}", prg)),
+        "printed as {:?}",
+        printed
+    );
+}
+
+// a parsed program keeps its parentheses, and gets no new ones
+fn same_parsed_tree(src: &str) {
+    if let Ok(prg) = parser::parse_prg(src) {
+        let printed = prg.to_string();
+        let reparsed = parser::parse_prg(&printed)
+            .unwrap_or_else(|err| panic!("{:?} gives {:?}: {}", src, printed, err));
+        assert_eq!(
+            without_spans(format!("{:?}", reparsed)),
+            without_spans(format!("{:?}", prg)),
+            "{:?} printed as {:?}",
+            src,
+            printed
+        );
+    }
+}
+
+// random trees, like the ones the parser builds, but without the parentheses they need
+struct AstGen {
+    rng: Rng,
+}
+
+const NAMES: &[&str] = &[
+    "a", "b", "x", "foo", "café", "$el", "_", "get", "of", "async",
+];
+const MEMBERS: &[&str] = &["x", "length", "default", "if", "get", "then", "café"];
+const CHARS: &[&str] = &[
+    "a", " ", "\"", "'", "\\", "\n", "\r", "\t", "\u{0}", "\u{1f}", "\u{8}", "\u{c}", "\u{7f}",
+    "é", "😀", "\u{2028}", "//", "/*", "\\u0041",
+];
+const INTS: &[i64] = &[0, 1, 42, 9007199254740993, i64::MAX];
+const NUMS: &[f64] = &[
+    0.0,
+    0.5,
+    1.0,
+    123.456,
+    1.5e-7,
+    2e20,
+    1e21,
+    5e-324,
+    f64::MAX,
+    f64::NAN,
+    f64::INFINITY,
+];
+const NEGATIVE_INTS: &[i64] = &[-1, -42, i64::MIN];
+const NEGATIVE_NUMS: &[f64] = &[-0.0, -1.0, -1.5, -1e21, -5e-324];
+const INFIX: &[InfixOp] = &[
+    InfixOp::Add,
+    InfixOp::Sub,
+    InfixOp::Mul,
+    InfixOp::Div,
+    InfixOp::Pow,
+    InfixOp::Modulo,
+    InfixOp::Lt,
+    InfixOp::Le,
+    InfixOp::Gt,
+    InfixOp::Ge,
+    InfixOp::Eq,
+    InfixOp::Ne,
+    InfixOp::StrictEq,
+    InfixOp::StrictNe,
+];
+
+fn expr(kind: ExprKind) -> Expr {
+    kind.at(Span::default())
+}
+
+fn stmt(kind: StmtKind) -> Stmt {
+    kind.at(Span::default())
+}
+
+impl AstGen {
+    fn name(&mut self) -> String {
+        self.rng.pick(NAMES).to_string()
+    }
+
+    fn decl_kind(&mut self) -> DeclKind {
+        [DeclKind::Var, DeclKind::Let, DeclKind::Const][self.rng.below(3)]
+    }
+
+    fn stmts(&mut self, depth: usize) -> Vec<Stmt> {
+        (0..self.rng.below(4)).map(|_| self.stmt(depth)).collect()
+    }
+
+    fn stmt(&mut self, depth: usize) -> Stmt {
+        if depth > 3 {
+            return stmt(StmtKind::Expr(Box::new(self.expr(depth, false))));
+        }
+        let kind = match self.rng.below(12) {
+            0 => {
+                let kind = self.decl_kind();
+                let val = match kind == DeclKind::Const || self.rng.below(3) > 0 {
+                    true => Some(Box::new(self.expr(depth, false))),
+                    false => None,
+                };
+                StmtKind::Assign(kind, self.name(), val)
+            }
+            1 => StmtKind::Reassign(self.name(), Box::new(self.expr(depth, false))),
+            2 => StmtKind::SetMember(
+                Box::new(self.signed(depth)),
+                self.rng.pick(MEMBERS).to_string(),
+                Box::new(self.expr(depth, false)),
+            ),
+            3 => StmtKind::Block(self.stmts(depth + 1)),
+            4 => {
+                let (kind, name, val) = (
+                    self.decl_kind(),
+                    self.name(),
+                    Box::new(self.expr(depth, false)),
+                );
+                let body = Box::new(self.stmt(depth + 1));
+                match self.rng.below(2) {
+                    0 => StmtKind::ForOf(kind, name, val, body),
+                    _ => StmtKind::ForIn(kind, name, val, body),
+                }
+            }
+            5 => StmtKind::While(
+                Box::new(self.expr(depth, false)),
+                Box::new(self.stmt(depth + 1)),
+            ),
+            6 => {
+                let cond = Box::new(self.expr(depth, false));
+                let then = Box::new(self.stmt(depth + 1));
+                // `if (a) if (b) c; else d;`: the `else` can only be the one of the inner `if`
+                let otherwise = match self.rng.below(2) == 0 && !takes_else(&then) {
+                    true => Some(Box::new(self.stmt(depth + 1))),
+                    false => None,
+                };
+                StmtKind::If(cond, then, otherwise)
+            }
+            7 => StmtKind::Return(match self.rng.below(3) {
+                0 => None,
+                _ => Some(Box::new(self.expr(depth, false))),
+            }),
+            8 => {
+                let name = self.name();
+                StmtKind::Function(Rc::new(self.function(depth, Some(name))))
+            }
+            _ => StmtKind::Expr(Box::new(self.expr(depth, false))),
+        };
+        stmt(kind)
+    }
+
+    fn function(&mut self, depth: usize, name: Option<String>) -> FunctionDef {
+        let mut def = FunctionDef {
+            name,
+            params: vec![],
+            body: self.stmts(depth + 1),
+            is_generator: false,
+            is_async: false,
+            uses_arguments: false,
+        };
+        match self.rng.below(4) {
+            0 => def.is_async = true,
+            1 => def.is_generator = true,
+            _ => {}
+        }
+        for _ in 0..self.rng.below(3) {
+            let default = match self.rng.below(2) {
+                0 => Some(self.expr(depth + 1, false)),
+                _ => None,
+            };
+            def.params.push(Param {
+                name: self.name(),
+                default,
+            });
+        }
+        def.uses_arguments = ast::reads_arguments(&def.body, &def.params);
+        def
+    }
+
+    fn literal(&mut self, depth: usize, topic: bool) -> Literal {
+        match self.rng.below(9) {
+            0 => Literal::Int(INTS[self.rng.below(INTS.len())]),
+            1 => Literal::Num(NUMS[self.rng.below(NUMS.len())]),
+            2 => Literal::Str(
+                (0..self.rng.below(5))
+                    .map(|_| self.rng.pick(CHARS))
+                    .collect(),
+            ),
+            3 => Literal::Bool(self.rng.below(2) == 0),
+            4 => Literal::Null,
+            5 => Literal::Undefined,
+            6 => Literal::Array(self.args(depth + 1, topic)),
+            _ => Literal::Object(
+                (0..self.rng.below(4))
+                    .map(|_| self.prop(depth + 1, topic))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn prop(&mut self, depth: usize, topic: bool) -> Prop {
+        let key = match self.rng.below(4) {
+            0 => PropKey::Computed(Box::new(self.expr(depth, topic))),
+            _ => PropKey::Name(
+                (0..self.rng.below(4))
+                    .map(|_| self.rng.pick(CHARS))
+                    .collect(),
+            ),
+        };
+        let name = match &key {
+            PropKey::Name(name) => Some(name.clone()),
+            PropKey::Computed(_) => None,
+        };
+        match self.rng.below(6) {
+            0 => Prop::Shorthand(self.name()),
+            1 => Prop::Method(key, Rc::new(self.function(depth, name))),
+            2 | 3 => {
+                let mut def = self.function(depth, name);
+                def.is_async = false;
+                def.is_generator = false;
+                def.params.truncate(1);
+                if def.params.is_empty() {
+                    return Prop::Getter(key, Rc::new(def));
+                }
+                def.params[0].default = None;
+                def.uses_arguments = ast::reads_arguments(&def.body, &def.params);
+                Prop::Setter(key, Rc::new(def))
+            }
+            _ => Prop::Value(key, self.expr(depth, topic)),
+        }
+    }
+
+    fn args(&mut self, depth: usize, topic: bool) -> Vec<Expr> {
+        (0..self.rng.below(4))
+            .map(|_| match self.rng.below(5) {
+                0 => expr(ExprKind::Spread(Box::new(self.expr(depth, topic)))),
+                _ => self.expr(depth, topic),
+            })
+            .collect()
+    }
+
+    // an operand of `-` or `+`, or an assignment target: it can start with a negative number
+    fn signed(&mut self, depth: usize) -> Expr {
+        if self.rng.below(3) > 0 {
+            return self.expr(depth, false);
+        }
+        let literal = match self.rng.below(2) {
+            0 => Literal::Int(NEGATIVE_INTS[self.rng.below(NEGATIVE_INTS.len())]),
+            _ => Literal::Num(NEGATIVE_NUMS[self.rng.below(NEGATIVE_NUMS.len())]),
+        };
+        let mut res = expr(ExprKind::Literal(literal));
+        for _ in 0..self.rng.below(3) {
+            let name = self.rng.pick(MEMBERS).to_string();
+            res = match self.rng.below(2) {
+                0 => expr(ExprKind::Member(Box::new(res), name)),
+                _ => expr(ExprKind::MethodCall(
+                    Box::new(res),
+                    name,
+                    self.args(depth + 1, false),
+                )),
+            };
+        }
+        res
+    }
+
+    // `topic` is set in the body of a pipeline, where `%` is the previous step
+    fn expr(&mut self, depth: usize, topic: bool) -> Expr {
+        let depth = depth + 1;
+        let choices = if depth > 6 { 4 } else { 18 };
+        let kind = match self.rng.below(choices) {
+            0 => ExprKind::Ident(self.name()),
+            1 if topic => ExprKind::Topic,
+            1 => ExprKind::This,
+            2 | 3 => ExprKind::Literal(self.literal(depth, topic)),
+            4..=6 => {
+                let op = INFIX[self.rng.below(INFIX.len())].clone();
+                ExprKind::Infix(
+                    op,
+                    Box::new(self.expr(depth, topic)),
+                    Box::new(self.expr(depth, topic)),
+                )
+            }
+            7 => {
+                let op = [PrefixOp::Minus, PrefixOp::Plus][self.rng.below(2)].clone();
+                ExprKind::Prefix(op, Box::new(self.signed(depth)))
+            }
+            8 => ExprKind::Parens(Box::new(self.expr(depth, topic))),
+            9 => ExprKind::FctCall(self.name(), self.args(depth, topic)),
+            10 => ExprKind::Member(
+                Box::new(self.expr(depth, topic)),
+                self.rng.pick(MEMBERS).to_string(),
+            ),
+            11 => {
+                let name = self.rng.pick(MEMBERS).to_string();
+                ExprKind::MethodCall(
+                    Box::new(self.expr(depth, topic)),
+                    name,
+                    self.args(depth, topic),
+                )
+            }
+            12 => {
+                let name = match self.rng.below(2) {
+                    0 => Some(self.name()),
+                    _ => None,
+                };
+                ExprKind::Function(Rc::new(self.function(depth, name)))
+            }
+            13 => ExprKind::Yield(match self.rng.below(2) {
+                0 => None,
+                _ => Some(Box::new(self.expr(depth, topic))),
+            }),
+            14 => ExprKind::YieldStar(Box::new(self.expr(depth, topic))),
+            15 => ExprKind::Await(Box::new(self.expr(depth, topic))),
+            _ => {
+                let lhs = self.expr(depth, topic);
+                let mut body = self.expr(depth, true);
+                // a body has to use the previous step
+                if !ast::uses_topic(&body) {
+                    body = expr(ExprKind::Infix(
+                        InfixOp::Add,
+                        Box::new(body),
+                        Box::new(expr(ExprKind::Topic)),
+                    ));
+                }
+                ExprKind::Pipe(Box::new(lhs), Box::new(body))
+            }
+        };
+        expr(kind)
+    }
+}
+
+fn takes_else(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::If(_, _, None) => true,
+        StmtKind::If(_, _, Some(body))
+        | StmtKind::While(_, body)
+        | StmtKind::ForOf(_, _, _, body)
+        | StmtKind::ForIn(_, _, _, body) => takes_else(body),
+        _ => false,
+    }
+}
+
+#[test]
+fn parsed_programs_print_back() {
+    for src in PROGRAMS {
+        same_parsed_tree(src);
+    }
+    same_parsed_tree(include_str!("../resources/ex1.js_new"));
+    for line in include_str!("../README.md").lines() {
+        if let Some(code) = line.strip_prefix("- ") {
+            same_parsed_tree(code);
+        }
+    }
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for seed in 1..=300u64 {
+        let mut gen = Gen {
+            rng: Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15)),
+            out: String::new(),
+        };
+        gen.stmts(0);
+        same_parsed_tree(&gen.out);
+        same_parsed_tree(&mutate(&mut rng, &gen.out));
+    }
+}
+
+#[test]
+fn random_trees_print_back() {
+    for seed in 1..=2000u64 {
+        let mut gen = AstGen {
+            rng: Rng(seed.wrapping_mul(0x2545_f491_4f6c_dd1d)),
+        };
+        let prg = Prg {
+            stmts: gen.stmts(0),
+        };
+        same_tree(&prg);
+    }
+}
+
+#[test]
+fn trees_built_in_code() {
+    let num = |n| expr(ExprKind::Literal(Literal::Int(n)));
+    let ident = |name: &str| expr(ExprKind::Ident(name.to_string()));
+    let infix = |op, lhs, rhs| expr(ExprKind::Infix(op, Box::new(lhs), Box::new(rhs)));
+    let cases = [
+        (
+            infix(
+                InfixOp::Mul,
+                infix(InfixOp::Add, ident("a"), num(1)),
+                ident("b"),
+            ),
+            "(a + 1) * b;",
+        ),
+        (
+            infix(
+                InfixOp::Sub,
+                ident("a"),
+                infix(InfixOp::Sub, ident("b"), ident("c")),
+            ),
+            "a - (b - c);",
+        ),
+        (
+            infix(
+                InfixOp::Lt,
+                infix(InfixOp::Lt, ident("a"), ident("b")),
+                ident("c"),
+            ),
+            "(a < b) < c;",
+        ),
+        (
+            expr(ExprKind::Prefix(PrefixOp::Minus, Box::new(ident("x")))),
+            "-x;",
+        ),
+        (
+            expr(ExprKind::Prefix(PrefixOp::Minus, Box::new(num(-1)))),
+            "- -1;",
+        ),
+        (
+            expr(ExprKind::Member(Box::new(num(1)), "x".to_string())),
+            "1 .x;",
+        ),
+        (expr(ExprKind::Literal(Literal::Num(2.0))), "2.0;"),
+        (expr(ExprKind::Literal(Literal::Object(vec![]))), "({});"),
+        (
+            expr(ExprKind::Literal(Literal::Str("say \"hi\"\n".to_string()))),
+            "\"say \\\"hi\\\"\\n\";",
+        ),
+    ];
+    for (expr, expected) in cases {
+        let prg = Prg {
+            stmts: vec![stmt(StmtKind::Expr(Box::new(expr)))],
+        };
+        assert_eq!(prg.to_string(), format!("{}\n", expected));
+        same_tree(&prg);
+    }
+    // the literal -1 can't be written there: `-1 .x` is `-(1 .x)`
+    let member = expr(ExprKind::Member(Box::new(num(-1)), "x".to_string()));
+    assert_eq!(member.to_string(), "(-1).x");
+}
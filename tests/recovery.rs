@@ -0,0 +1,76 @@
+// a syntax error doesn't stop the parser: it starts again at the next statement boundary, so
+// every error is reported, along with the statements it could parse
+use std::process::Command;
+
+use js_light::parser;
+
+const SRC: &str = "var a = ;
+var b = 2;
+if (b { c(); }
+function f() { return 1 +; }
+var d = 4;
+";
+
+#[test]
+fn every_error() {
+    let (prg, errors) = parser::parse_prg_partial(SRC);
+    let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+        errors,
+        [
+            "line 1, column 9: expected an expression, found `;`",
+            "line 3, column 7: expected `)` after the condition, found `{`",
+            "line 4, column 26: expected an expression, found `;`",
+        ]
+    );
+    // the statements without errors are there
+    let parsed: Vec<&str> = prg
+        .stmts
+        .iter()
+        .map(|stmt| &SRC[stmt.span.start..stmt.span.end])
+        .collect();
+    assert_eq!(parsed, ["var b = 2;", "var d = 4;"]);
+    // parse_prg gives the first one
+    assert_eq!(parser::parse_prg(SRC).unwrap_err().to_string(), errors[0]);
+}
+
+#[test]
+fn resync_points() {
+    let cases = [
+        // at a `;`, a `}` or a keyword starting a statement
+        ("1 + ; 2;", 1),
+        ("{ 1 + } var x = 1;", 1),
+        ("var = 1 let y = 2;", 1),
+        ("1 +\nvar x = ;\nx + ;", 3),
+        // an unterminated block is one error
+        ("{ var x = 1;", 1),
+    ];
+    for (src, nb_errors) in cases {
+        let (_, errors) = parser::parse_prg_partial(src);
+        assert_eq!(errors.len(), nb_errors, "parsing {:?}: {:?}", src, errors);
+    }
+}
+
+#[test]
+fn cli_reports_all() {
+    let path = std::env::temp_dir().join("js_light_recovery.js");
+    std::fs::write(&path, SRC).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_js-light"))
+        .arg("--file")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(
+        stderr.matches("Error parsing prg: ").count(),
+        3,
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(":4:26: expected an expression, found `;`"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,95 @@
+// every node knows where it is in the source, and runtime errors point at the node which failed
+use std::process::Command;
+
+mod common;
+use common::run;
+use js_light::parser::{self, ast::StmtKind};
+use js_light::runner::Error;
+
+// the source of the innermost node which failed
+fn failing_code(src: &str) -> &str {
+    let err = run(src).unwrap_err();
+    let span = err
+        .span()
+        .unwrap_or_else(|| panic!("{:?}: no span for {}", src, err));
+    &src[span.start..span.end]
+}
+
+#[test]
+fn node_spans() {
+    let src = "var a = 1;\nif (a) { b(a + 2); }";
+    let prg = parser::parse_prg(src).unwrap();
+    let spans: Vec<&str> = prg
+        .stmts
+        .iter()
+        .map(|stmt| &src[stmt.span.start..stmt.span.end])
+        .collect();
+    assert_eq!(spans, ["var a = 1;", "if (a) { b(a + 2); }"]);
+    let StmtKind::If(cond, _, None) = &prg.stmts[1].kind else {
+        panic!("{:?}", prg.stmts[1])
+    };
+    assert_eq!(&src[cond.span.start..cond.span.end], "a");
+    // spans are compared too: the same code elsewhere is another node
+    assert_ne!(
+        parser::parse_prg("1;").unwrap(),
+        parser::parse_prg(" 1;").unwrap()
+    );
+    assert_eq!(
+        parser::parse_prg("1;").unwrap(),
+        parser::parse_prg("1;").unwrap()
+    );
+}
+
+#[test]
+fn runtime_error_spans() {
+    let cases = [
+        ("var a = 1;\nvar b = a + nope(2);", "nope(2)"),
+        ("1 +\n\"x\".foo()", "\"x\".foo()"),
+        ("function f() { return y; }\nf()", "y"),
+        ("\"use strict\"; var s = 1 - \"a\";", "1 - \"a\""),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(failing_code(src), expected, "running {:?}", src);
+    }
+}
+
+#[test]
+fn rejection_spans() {
+    // a rejection keeps the error which caused it, with its type and position
+    let cases = [
+        ("async function f() { return x; }\nawait f()", "x"),
+        ("async function f() { await 1; nope(); }\nf(); 1", "nope()"),
+        ("sleep(1).then(function () { return 1 + y; }); 2", "y"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(failing_code(src), expected, "running {:?}", src);
+        match run(src).unwrap_err() {
+            Error::Uncaught {
+                error: Some(err), ..
+            } => assert!(
+                matches!(err.inner(), Error::Reference(_) | Error::UnknownFunction(_)),
+                "{:?}",
+                err
+            ),
+            err => panic!("{:?}: {:?}", src, err),
+        }
+    }
+}
+
+#[test]
+fn cli_excerpt() {
+    let out = Command::new(env!("CARGO_BIN_EXE_js-light"))
+        .arg("--expr=var a = 1;\nvar b = a + nope(2);")
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&out.stderr),
+        concat!(
+            "Error running expr: <expr>:2:13: Unknown function: nope\n",
+            "  |\n",
+            "2 | var b = a + nope(2);\n",
+            "  |             ^^^^^^^\n"
+        )
+    );
+}
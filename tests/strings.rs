@@ -0,0 +1,43 @@
+// escapes are decoded when parsing, and strings are printed back with json escapes
+mod common;
+use common::{assert_cases, eval};
+use js_light::parser;
+
+#[test]
+fn escapes() {
+    assert_cases(&[
+        (r#""a\nb".length"#, "3"),
+        (r#""\"q\" \\ \/""#, r#""\"q\" \\ /""#),
+        (r#""\b\f\r\t""#, r#""\b\f\r\t""#),
+        (r#""\u00e9" === "é""#, "true"),
+        (r#""\uD83D\uDE00" === "😀""#, "true"),
+        // a lone surrogate can't be in a rust string
+        (r#""\uD83D""#, "\"\u{fffd}\""),
+        // in chars, not bytes
+        (r#""😀é".length"#, "2"),
+        (r#""\u0001""#, r#""\u0001""#),
+        (r#"to_json("a\"b\u0001")"#, r#""\"a\\\"b\\u0001\"""#),
+    ]);
+}
+
+#[test]
+fn invalid_escapes() {
+    for src in [r#""\q""#, r#""\u12""#, r#""\x41""#, "\"a\\\nb\""] {
+        let err = parser::parse_prg(src).unwrap_err().to_string();
+        assert!(
+            err.contains("invalid escape sequence in string"),
+            "parsing {:?} gives {}",
+            src,
+            err
+        );
+    }
+}
+
+#[test]
+fn printed_back() {
+    // a string printed is a literal giving the same string
+    let decl = r#"var s = "tab\t \"quoted\" back\\slash é 😀 \u001f";"#;
+    let printed = eval(&format!("{} s", decl));
+    assert_eq!(printed, r#""tab\t \"quoted\" back\\slash é 😀 \u001f""#);
+    assert_eq!(eval(&format!("{} {} === s", decl, printed)), "true");
+}
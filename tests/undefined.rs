@@ -0,0 +1,43 @@
+// undefined is what is missing: a property, a value, a return. null is only ever written
+mod common;
+use common::{assert_cases, eval, eval_err};
+
+#[test]
+fn missing_values() {
+    assert_cases(&[
+        ("var o = { a: 1 }; o.b", "undefined"),
+        ("var x; x", "undefined"),
+        ("let x; x === undefined", "true"),
+        ("function f() {} f()", "undefined"),
+        ("function f() { return; } f()", "undefined"),
+        (
+            "function f(a, b = undefined) { return b; } f(1)",
+            "undefined",
+        ),
+        (
+            "var o = { a: null }; [o.a === undefined, o.b === undefined, o.b == null]",
+            "[false, true, true]",
+        ),
+        ("[undefined == null, undefined === null]", "[true, false]"),
+        ("\"a\" + undefined", "\"aundefined\""),
+        ("undefined + 1", "NaN"),
+    ]);
+}
+
+#[test]
+fn undefined_in_json() {
+    // left out of objects, null in arrays, like JSON.stringify
+    assert_eq!(
+        eval("to_json({ a: undefined, b: null, c: [undefined, 1] })"),
+        r#""{\"b\":null,\"c\":[null,1]}""#
+    );
+    assert_eq!(eval("to_json(undefined)"), "undefined");
+}
+
+#[test]
+fn undefined_errors() {
+    assert_eq!(
+        eval_err("var o = { a: 1 }; o.b.c"),
+        "Unknown method: c for Undefined, which has no methods"
+    );
+}